@@ -0,0 +1,552 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, utils::Duration};
+use bevy_kira_audio::AudioSource;
+use bevy_prototype_lyon::prelude::*;
+use bevy_tweening::*;
+use heron::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{bullet, game, player};
+
+pub const SHIELD_DUR: f32 = 2.;
+pub const SHIELD_RADIUS: f32 = 32.;
+pub const BLINK_DIST: f32 = 180.;
+pub const CHARGE_DUR: f32 = 1.;
+pub const PULSE_DUR: f32 = 0.6;
+pub const PULSE_RADIUS: f32 = 250.;
+pub const JAM_DUR: f32 = 2.;
+pub const DECOY_DUR: f32 = 3.;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Ability {
+    Dash,
+    Shield,
+    Blink,
+    Decoy,
+    SlowField,
+    Barrier,
+    Shot,
+    ChargedShot,
+    Mine,
+    RadarPulse,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Role {
+    Runner,
+    Tagger,
+}
+
+// The registry every loadout slot is filled from
+pub const RUNNER_ABILITIES: [Ability; 6] = [Ability::Dash, Ability::Shield, Ability::Blink, Ability::Decoy, Ability::SlowField, Ability::Barrier];
+pub const TAGGER_ABILITIES: [Ability; 4] = [Ability::Shot, Ability::ChargedShot, Ability::Mine, Ability::RadarPulse];
+
+impl Ability {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ability::Dash => "Dash",
+            Ability::Shield => "Shield",
+            Ability::Blink => "Blink",
+            Ability::Decoy => "Decoy",
+            Ability::SlowField => "Slow field",
+            Ability::Barrier => "Barrier",
+            Ability::Shot => "Shot",
+            Ability::ChargedShot => "Charged shot",
+            Ability::Mine => "Mine",
+            Ability::RadarPulse => "Radar pulse",
+        }
+    }
+
+    pub fn cooldown(&self) -> f32 {
+        match self {
+            Ability::Dash => player::DASH_DUR + player::COOLDOWN_DUR,
+            Ability::Shield => 4.,
+            Ability::Blink => 2.5,
+            Ability::Decoy => 5.,
+            Ability::SlowField => 2.,
+            Ability::Barrier => 3.,
+            Ability::Shot => 0.2,
+            Ability::ChargedShot => 0.6,
+            Ability::Mine => 1.5,
+            Ability::RadarPulse => 6.,
+        }
+    }
+
+    pub fn sound_file(&self) -> &'static str {
+        match self {
+            Ability::Dash | Ability::Blink | Ability::Decoy => "dash.wav",
+            Ability::Shield | Ability::SlowField | Ability::Barrier => "orb.wav",
+            Ability::Shot | Ability::ChargedShot => "shoot.wav",
+            Ability::Mine | Ability::RadarPulse => "timer.wav",
+        }
+    }
+
+    pub fn all() -> impl Iterator<Item = Ability> {
+        RUNNER_ABILITIES.into_iter().chain(TAGGER_ABILITIES)
+    }
+}
+
+impl Role {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Runner => "Runner",
+            Role::Tagger => "Tagger",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Role::Runner => game::RUNNER_COL,
+            Role::Tagger => game::TAGGER_COL,
+        }
+    }
+
+    pub fn abilities(&self) -> &'static [Ability] {
+        match self {
+            Role::Runner => &RUNNER_ABILITIES,
+            Role::Tagger => &TAGGER_ABILITIES,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, Deserialize)]
+pub struct Loadout {
+    pub runner: Ability,
+    pub tagger: Ability,
+}
+
+impl Default for Loadout {
+    fn default() -> Self {
+        Self {
+            runner: Ability::Dash,
+            tagger: Ability::Shot,
+        }
+    }
+}
+
+impl Loadout {
+    pub fn get(&self, role: Role) -> Ability {
+        match role {
+            Role::Runner => self.runner,
+            Role::Tagger => self.tagger,
+        }
+    }
+
+    // Selects the next ability of the registry for the given role
+    pub fn cycle(&mut self, role: Role) {
+        let abilities = role.abilities();
+        let current = abilities.iter().position(|ability| *ability == self.get(role)).unwrap_or(0);
+        let next = abilities[(current + 1) % abilities.len()];
+
+        match role {
+            Role::Runner => self.runner = next,
+            Role::Tagger => self.tagger = next,
+        }
+    }
+}
+
+// The loadout of each player, indexed by the player's number
+pub struct Loadouts(pub Vec<Loadout>);
+
+impl Default for Loadouts {
+    fn default() -> Self {
+        Self(vec![Loadout::default(); 2])
+    }
+}
+
+pub struct AbilitySounds(pub HashMap<Ability, Handle<AudioSource>>);
+
+impl AbilitySounds {
+    pub fn get(&self, ability: Ability) -> Handle<AudioSource> {
+        self.0[&ability].clone()
+    }
+}
+
+#[derive(Component)]
+pub struct Shield {
+    timer: Timer,
+    bubble: Entity,
+}
+
+#[derive(Component)]
+pub struct Charging(pub Timer);
+
+#[derive(Component)]
+pub struct Pulse {
+    timer: Timer,
+    jammed: Vec<Entity>,
+}
+
+// A fake runner that keeps going where the runner was heading and pops on the first hit
+#[derive(Component)]
+pub struct Decoy {
+    timer: Timer,
+    heading: Vec3,
+    pub velocity: f32,
+}
+
+#[derive(Component)]
+pub struct Effect;
+
+pub struct DrawColorLens {
+    pub start: Vec3, // Vec3 because it can be subtracted and mulitplied
+    pub end: Vec3, // Vec3 because it can be subtracted and mulitplied
+}
+
+impl Lens<DrawMode> for DrawColorLens {
+    fn lerp(&mut self, target: &mut DrawMode, ratio: f32) {
+        if let DrawMode::Fill(ref mut fill_mode) | DrawMode::Outlined { ref mut fill_mode, .. } = *target {
+            let diff = (self.end - self.start) * ratio;
+            let curr = self.start + diff;
+
+            fill_mode.color = Color::rgb(curr.x, curr.y, curr.z);
+        }
+    }
+}
+
+fn color_vec(color: Color) -> Vec3 {
+    Vec3::new(color.r(), color.g(), color.b())
+}
+
+// Puts the ability of the player on cooldown and fades it back to the color of its role
+pub fn start_cooldown(
+    commands: &mut Commands,
+    entity: Entity,
+    seconds: f32,
+    role: Role,
+) {
+    let tween = Tween::new(
+        EaseFunction::CircularIn,
+        TweeningType::Once,
+        Duration::from_secs_f32(seconds),
+        DrawColorLens {
+            start: Vec3::new(1., 1., 1.),
+            end: color_vec(role.color()),
+        },
+    );
+
+    commands.entity(entity)
+        .insert(player::Cooldown(Timer::from_seconds(seconds, false)))
+        .insert(Animator::new(tween));
+}
+
+// RUNNER ABILITIES
+pub fn dash(
+    commands: &mut Commands,
+    entity: Entity,
+    player: &mut player::Player,
+) {
+    commands.entity(entity).insert(player::Dash(Timer::from_seconds(player::DASH_DUR, false)));
+    player.velocity = player::DASH_SPEED;
+
+    let tween = Tween::new(
+        EaseFunction::QuadraticInOut,
+        TweeningType::PingPong,
+        Duration::from_millis((player::DASH_DUR*500.) as u64),
+        lens::TransformScaleLens {
+            start: Vec3::new(1., 1., 1.),
+            end: Vec3::new(0.2, 1., 1.),
+        },
+    );
+    commands.entity(entity).insert(Animator::new(tween));
+}
+
+pub fn shield(
+    commands: &mut Commands,
+    entity: Entity,
+) {
+    let bubble = commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::Circle {
+            radius: SHIELD_RADIUS,
+            center: Vec2::new(0., 0.),
+        },
+        DrawMode::Stroke(StrokeMode::new(game::RUNNER_COL, 3.)),
+        Transform::from_xyz(0., 0., 1.),
+    ))
+    .insert(game::GameEntity)
+    .id();
+
+    commands.entity(entity)
+        .push_children(&[bubble])
+        .insert(Shield {
+            timer: Timer::from_seconds(SHIELD_DUR, false),
+            bubble,
+        });
+}
+
+pub fn break_shield(
+    commands: &mut Commands,
+    entity: Entity,
+    shield: &Shield,
+) {
+    commands.entity(shield.bubble).despawn_recursive();
+    commands.entity(entity).remove::<Shield>();
+}
+
+pub fn blink(
+    commands: &mut Commands,
+    transform: &mut Transform,
+    player: &player::Player,
+) {
+    spawn_burst(commands, transform.translation, game::RUNNER_COL);
+
+    // Clamping to the arena is left to player::move_players
+    transform.translation += player.heading() * BLINK_DIST;
+}
+
+// The decoy looks like its runner does right now, team outline included
+pub fn decoy(
+    commands: &mut Commands,
+    player: &player::Player,
+    transform: &Transform,
+    draw_mode: DrawMode,
+) {
+    // A standing decoy would be too easy to spot
+    let velocity = if player.velocity.abs() < player::MAX_SPEED / 2. {
+        player::MAX_SPEED * 0.75
+    } else {
+        player.velocity
+    };
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &player::player_shape(),
+        draw_mode,
+        Transform {
+            translation: transform.translation,
+            rotation: transform.rotation,
+            ..Default::default()
+        },
+    ))
+    .insert(RigidBody::KinematicPositionBased)
+    .insert(CollisionShape::Capsule{radius: 10., half_segment: 10.})
+    // Decoys are in every team, so they fool the taggers of any team
+    .insert(CollisionLayers::new(game::Layer::Runner, game::Layer::Runner).with_group(game::Layer::Team1).with_group(game::Layer::Team2))
+    .insert(Decoy {
+        timer: Timer::from_seconds(DECOY_DUR, false),
+        heading: player.heading(),
+        velocity,
+    })
+    .insert(game::GameEntity);
+}
+
+pub fn pop_decoy(
+    commands: &mut Commands,
+    entity: Entity,
+    transform: &Transform,
+) {
+    spawn_burst(commands, transform.translation, game::RUNNER_COL);
+    commands.entity(entity).despawn();
+}
+
+// TAGGER ABILITIES
+pub fn shoot(
+    commands: &mut Commands,
+    entity: Entity,
+    player: &player::Player,
+    transform: &Transform,
+) {
+    bullet::spawn_bullet(commands, transform.translation, player.heading(), entity);
+}
+
+pub fn start_charge(
+    commands: &mut Commands,
+    entity: Entity,
+) {
+    let tween = Tween::new(
+        EaseMethod::Linear,
+        TweeningType::Once,
+        Duration::from_secs_f32(CHARGE_DUR),
+        DrawColorLens {
+            start: color_vec(game::TAGGER_COL),
+            end: Vec3::new(1., 1., 1.),
+        },
+    );
+
+    commands.entity(entity)
+        .insert(Charging(Timer::from_seconds(CHARGE_DUR, false)))
+        .insert(Animator::new(tween));
+}
+
+pub fn release_charge(
+    commands: &mut Commands,
+    entity: Entity,
+    player: &player::Player,
+    transform: &Transform,
+    charging: &Charging,
+) {
+    let charge = charging.0.percent();
+
+    bullet::spawn_sized_bullet(
+        commands,
+        transform.translation,
+        player.heading(),
+        entity,
+        bullet::BULLET_RADIUS * (1. + charge),
+        bullet::BULLET_SPEED * (1. + charge),
+    );
+
+    commands.entity(entity).remove::<Charging>();
+}
+
+pub fn radar_pulse(
+    commands: &mut Commands,
+    transform: &Transform,
+) {
+    let scale_tween = Tween::new(
+        EaseFunction::QuadraticOut,
+        TweeningType::Once,
+        Duration::from_secs_f32(PULSE_DUR),
+        lens::TransformScaleLens {
+            start: Vec3::new(0., 0., 1.),
+            end: Vec3::new(1., 1., 1.),
+        },
+    );
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::Circle {
+            radius: PULSE_RADIUS,
+            center: Vec2::new(0., 0.),
+        },
+        DrawMode::Stroke(StrokeMode::new(game::TAGGER_COL, 4.)),
+        Transform {
+            translation: Vec3::new(transform.translation.x, transform.translation.y, 0.),
+            scale: Vec3::new(0., 0., 1.),
+            ..Default::default()
+        },
+    ))
+    .insert(Animator::new(scale_tween))
+    .insert(Pulse {
+        timer: Timer::from_seconds(PULSE_DUR, false),
+        jammed: Vec::new(),
+    })
+    .insert(game::GameEntity);
+}
+
+// A short-lived ring marking where something happened
+pub fn spawn_burst(
+    commands: &mut Commands,
+    pos: Vec3,
+    color: Color,
+) {
+    let tween = Tween::new(
+        EaseFunction::QuadraticOut,
+        TweeningType::Once,
+        Duration::from_millis(300),
+        lens::TransformScaleLens {
+            start: Vec3::new(1., 1., 1.),
+            end: Vec3::new(0., 0., 1.),
+        },
+    )
+    .with_completed_event(true, 0);
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::Circle {
+            radius: 20.,
+            center: Vec2::new(0., 0.),
+        },
+        DrawMode::Stroke(StrokeMode::new(color, 3.)),
+        Transform::from_xyz(pos.x, pos.y, 0.),
+    ))
+    .insert(Animator::new(tween))
+    .insert(Effect)
+    .insert(game::GameEntity);
+}
+
+// SYSTEMS
+pub fn handle_shield_timer(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Shield)>,
+) {
+    for (entity, mut shield) in query.iter_mut() {
+        shield.timer.tick(time.delta());
+
+        if shield.timer.just_finished() {
+            break_shield(&mut commands, entity, &shield);
+        }
+    }
+}
+
+pub fn handle_charge_timer(
+    time: Res<Time>,
+    mut query: Query<&mut Charging>,
+) {
+    for mut charging in query.iter_mut() {
+        charging.0.tick(time.delta());
+    }
+}
+
+pub fn move_decoys(
+    mut commands: Commands,
+    time: Res<Time>,
+    arena: Res<game::Arena>,
+    mut query: Query<(Entity, &mut Transform, &mut Decoy)>,
+) {
+    for (entity, mut transform, mut decoy) in query.iter_mut() {
+        decoy.timer.tick(time.delta());
+
+        if decoy.timer.finished() {
+            pop_decoy(&mut commands, entity, &transform);
+            continue;
+        }
+
+        transform.translation += decoy.heading * decoy.velocity * time.delta_seconds();
+        transform.translation = arena.clamp(transform.translation);
+    }
+}
+
+pub fn expand_pulses(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pulse_query: Query<(Entity, &Transform, &mut Pulse)>,
+    mut runner_query: Query<(Entity, &mut Transform), (With<player::Runner>, Without<Pulse>)>,
+    decoy_query: Query<(Entity, &Transform), (With<Decoy>, Without<Pulse>, Without<player::Runner>)>,
+) {
+    for (pulse_entity, pulse_transform, mut pulse) in pulse_query.iter_mut() {
+        pulse.timer.tick(time.delta());
+
+        let radius = PULSE_RADIUS * pulse.timer.percent();
+
+        for (runner_entity, mut runner_transform) in runner_query.iter_mut() {
+            let dist = (runner_transform.translation - pulse_transform.translation).truncate().length();
+
+            if dist <= radius && !pulse.jammed.contains(&runner_entity) {
+                // Jamming interrupts the dash and puts the ability of the runner on cooldown
+                commands.entity(runner_entity)
+                    .remove::<player::Dash>()
+                    .remove::<Animator<Transform>>();
+                runner_transform.scale = Vec3::new(1., 1., 1.);
+
+                start_cooldown(&mut commands, runner_entity, JAM_DUR, Role::Runner);
+                pulse.jammed.push(runner_entity);
+            }
+        }
+
+        // The pulse also gives away the decoys it reaches
+        for (decoy_entity, decoy_transform) in decoy_query.iter() {
+            let dist = (decoy_transform.translation - pulse_transform.translation).truncate().length();
+
+            if dist <= radius && !pulse.jammed.contains(&decoy_entity) {
+                pop_decoy(&mut commands, decoy_entity, decoy_transform);
+                pulse.jammed.push(decoy_entity);
+            }
+        }
+
+        if pulse.timer.finished() {
+            commands.entity(pulse_entity).despawn();
+        }
+    }
+}
+
+pub fn despawn_finished_effects(
+    mut commands: Commands,
+    mut events: EventReader<TweenCompleted>,
+    query: Query<(), With<Effect>>,
+) {
+    for event in events.iter() {
+        if query.get(event.entity).is_ok() {
+            commands.entity(event.entity).despawn();
+        }
+    }
+}
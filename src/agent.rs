@@ -0,0 +1,537 @@
+use std::{io::{self, Read, Write}, net::{Ipv4Addr, TcpListener, TcpStream}, sync::Mutex, time::Duration};
+
+use bevy::{prelude::*, core::CoreSystem, ecs::{schedule::ShouldRun, system::SystemParam}};
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, game, mode, player, bullet, orb};
+use crate::ability::Role;
+
+pub const DEFAULT_AGENT_PORT: u16 = 7780;
+pub const LOCKSTEP_STEP: f32 = 1. / 60.;
+pub const LOCKSTEP_TIMEOUT: f32 = 30.;
+pub const MAX_LINE: usize = 64 * 1024;
+// An agent that lets this much pile up isn't reading anymore
+pub const MAX_OUTGOING: usize = 1024 * 1024;
+
+// What an agent sends, one JSON object per line
+#[derive(Deserialize)]
+pub enum AgentCommand {
+    Join { id: usize },
+    Intent(player::Intent),
+    Leave,
+}
+
+// What an agent gets back, one JSON object per line
+#[derive(Serialize)]
+pub enum AgentMessage {
+    Joined { id: usize },
+    Observation(Observation),
+    End { winner: String },
+    Error(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Observation {
+    pub tick: u64,
+    pub match_time: f32,
+    // Only in modes with a time limit
+    pub time_left: Option<f32>,
+    pub players: Vec<PlayerObservation>,
+    pub bullets: Vec<BulletObservation>,
+    pub orbs: Vec<[f32; 2]>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerObservation {
+    pub id: usize,
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub velocity: f32,
+    pub role: Role,
+    pub cooldown: f32,
+    pub frozen: bool,
+    pub stunned: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulletObservation {
+    pub x: f32,
+    pub y: f32,
+    // The player that shot it, bots have no id an agent knows of
+    pub shooter: Option<usize>,
+}
+
+// Read from `--agent-port <port>` and `--lockstep` on the command line
+pub struct AgentConfig {
+    pub port: u16,
+    pub lockstep: bool,
+}
+
+impl AgentConfig {
+    pub fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let lockstep = args.iter().any(|arg| arg == "--lockstep");
+        let port = args.iter()
+            .position(|arg| arg == "--agent-port")
+            .map(|index| args.get(index + 1).and_then(|port| port.parse().ok()).unwrap_or(DEFAULT_AGENT_PORT));
+
+        port.map(|port| Self { port, lockstep })
+    }
+}
+
+pub struct Agent {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    // Written as the socket takes it
+    outgoing: Vec<u8>,
+    pub id: Option<usize>,
+    pub intent: player::Intent,
+    // In lock-step the game waits until the agent answered the last observation
+    pub answered: bool,
+}
+
+impl Agent {
+    fn send(&mut self, message: &AgentMessage) -> io::Result<()> {
+        serde_json::to_writer(&mut self.outgoing, message)?;
+        self.outgoing.push(b'\n');
+
+        self.flush()
+    }
+
+    // Writes what the socket takes without waiting, the rest stays for the next frame
+    fn flush(&mut self) -> io::Result<()> {
+        let mut written = 0;
+
+        while written < self.outgoing.len() {
+            match self.stream.write(&self.outgoing[written..]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => written += len,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+                Err(err) => return Err(err),
+            }
+        }
+
+        self.outgoing.drain(..written);
+
+        if self.outgoing.len() > MAX_OUTGOING {
+            return Err(io::Error::other("The agent stopped reading"));
+        }
+
+        Ok(())
+    }
+
+    fn read_once(&mut self) -> io::Result<usize> {
+        let mut chunk = [0; 4096];
+        let len = self.stream.read(&mut chunk)?;
+        self.buffer.extend_from_slice(&chunk[..len]);
+
+        Ok(len)
+    }
+
+    // Reads what already arrived, returns false once the agent is gone
+    fn read(&mut self) -> bool {
+        loop {
+            match self.read_once() {
+                Ok(0) => return false,
+                Ok(_) => {},
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(_) => return false,
+            }
+
+            if self.buffer.len() > MAX_LINE && !self.buffer.contains(&b'\n') {
+                return false;
+            }
+        }
+    }
+
+    fn take_lines(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            lines.push(String::from_utf8_lossy(&line).trim().to_string());
+        }
+
+        lines.retain(|line| !line.is_empty());
+        lines
+    }
+}
+
+pub struct AgentServer {
+    listener: TcpListener,
+    pub lockstep: bool,
+    pub agents: Vec<Agent>,
+    tick: u64,
+}
+
+impl AgentServer {
+    // Only processes on this machine can connect
+    pub fn listen(config: &AgentConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, config.port))?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            listener,
+            lockstep: config.lockstep,
+            agents: Vec::new(),
+            tick: 0,
+        })
+    }
+
+    pub fn controls(&self, id: usize) -> bool {
+        self.agents.iter().any(|agent| agent.id == Some(id))
+    }
+}
+
+// The time of every lock-stepped frame, however long the agents took to answer.
+// Time only measures real time, so a single step is measured once and then reused
+pub struct LockstepTime(pub Time);
+
+impl LockstepTime {
+    // Every app in the process gets the same measurement, so their matches play out the same
+    pub fn measure(step: Duration) -> Self {
+        static MEASURED: Mutex<Vec<(Duration, Time)>> = Mutex::new(Vec::new());
+
+        let mut measured = MEASURED.lock().unwrap();
+
+        if let Some((_, time)) = measured.iter().find(|(measured_step, _)| *measured_step == step) {
+            return Self(time.clone());
+        }
+
+        let mut time = Time::default();
+        time.update();
+        std::thread::sleep(step);
+        time.update();
+
+        measured.push((step, time.clone()));
+        Self(time)
+    }
+}
+
+// A player whose intents come from an agent instead of the keyboard
+#[derive(Component)]
+pub struct AgentPlayer;
+
+pub struct AgentPlugin;
+
+impl Plugin for AgentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(start_agent_server)
+            .add_system(accept_agents.label("agents"))
+            .add_system(receive_agent_commands.label("agent_commands").after("agents"))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(in_agent_match)
+                    .with_system(take_over_players.before("intent"))
+                    .with_system(drive_agent_players.label("intent").after("agent_commands"))
+                    .with_system(send_observations.after("collisions").after("countdown")),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::Game)
+                    .with_system(end_agent_match),
+            )
+            .add_system_to_stage(CoreStage::First, fix_time_step.after(CoreSystem::Time))
+            .add_system_to_stage(CoreStage::Last, wait_for_agents);
+    }
+}
+
+// Agents are only listened for if the app was given an AgentConfig
+pub fn start_agent_server(mut commands: Commands, config: Option<Res<AgentConfig>>) {
+    let config = match config {
+        Some(config) => config,
+        None => return,
+    };
+
+    match AgentServer::listen(&config) {
+        Ok(server) => {
+            info!("Listening for agents on port {}", config.port);
+            commands.insert_resource(server);
+
+            if config.lockstep {
+                commands.insert_resource(LockstepTime::measure(Duration::from_secs_f32(LOCKSTEP_STEP)));
+            }
+        },
+        Err(err) => error!("Failed to listen for agents on port {}: {}", config.port, err),
+    }
+}
+
+pub fn in_agent_match(state: Res<State<GameState>>, server: Option<Res<AgentServer>>) -> ShouldRun {
+    if *state.current() == GameState::Game && server.is_some() {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+pub fn accept_agents(server: Option<ResMut<AgentServer>>) {
+    let mut server = match server {
+        Some(server) => server,
+        None => return,
+    };
+
+    while let Ok((stream, addr)) = server.listener.accept() {
+        if stream.set_nonblocking(true).is_err() {
+            continue;
+        }
+
+        let _ = stream.set_nodelay(true);
+        info!("Agent connected from {}", addr);
+
+        server.agents.push(Agent {
+            stream,
+            buffer: Vec::new(),
+            outgoing: Vec::new(),
+            id: None,
+            intent: player::Intent::default(),
+            answered: true,
+        });
+    }
+}
+
+pub fn receive_agent_commands(server: Option<ResMut<AgentServer>>) {
+    let mut server = match server {
+        Some(server) => server,
+        None => return,
+    };
+
+    let mut index = 0;
+
+    while index < server.agents.len() {
+        let connected = server.agents[index].read() && server.agents[index].flush().is_ok();
+        let lines = server.agents[index].take_lines();
+
+        let mut keep = connected;
+
+        for line in lines {
+            let reply = match serde_json::from_str::<AgentCommand>(&line) {
+                Ok(AgentCommand::Join { id }) => {
+                    if id >= player::MAX_PLAYERS {
+                        Some(AgentMessage::Error(format!("There is no player {}", id)))
+                    } else if server.agents.iter().enumerate().any(|(other, agent)| other != index && agent.id == Some(id)) {
+                        Some(AgentMessage::Error(format!("Player {} is already taken", id)))
+                    } else {
+                        server.agents[index].id = Some(id);
+                        Some(AgentMessage::Joined { id })
+                    }
+                },
+                Ok(AgentCommand::Intent(intent)) => {
+                    let agent = &mut server.agents[index];
+                    agent.intent = intent;
+                    agent.answered = true;
+                    None
+                },
+                Ok(AgentCommand::Leave) => {
+                    keep = false;
+                    None
+                },
+                Err(err) => Some(AgentMessage::Error(err.to_string())),
+            };
+
+            if let Some(reply) = reply {
+                keep &= server.agents[index].send(&reply).is_ok();
+            }
+        }
+
+        if keep {
+            index += 1;
+        } else {
+            info!("Agent disconnected");
+            server.agents.remove(index);
+        }
+    }
+}
+
+// Players taken by an agent stop listening to the keyboard, and get it back once the agent leaves
+pub fn take_over_players(
+    mut commands: Commands,
+    server: Res<AgentServer>,
+    keys_query: Query<(Entity, &player::Player), (With<player::Keys>, Without<AgentPlayer>)>,
+    agent_query: Query<(Entity, &player::Player), With<AgentPlayer>>,
+) {
+    for (entity, player) in keys_query.iter() {
+        if server.controls(player.id) {
+            commands.entity(entity)
+                .remove::<player::Keys>()
+                .insert(AgentPlayer);
+        }
+    }
+
+    for (entity, player) in agent_query.iter() {
+        if !server.controls(player.id) {
+            commands.entity(entity)
+                .remove::<AgentPlayer>()
+                .insert(player::PLAYER_KEYS[player.id]);
+        }
+    }
+}
+
+pub fn drive_agent_players(
+    server: Res<AgentServer>,
+    mut query: Query<(&player::Player, &mut player::Intent), With<AgentPlayer>>,
+) {
+    for (player, mut intent) in query.iter_mut() {
+        if let Some(agent) = server.agents.iter().find(|agent| agent.id == Some(player.id)) {
+            intent.update(agent.intent);
+        }
+    }
+}
+
+// Everything an agent sees of the match
+#[derive(SystemParam)]
+pub struct MatchObserver<'w, 's> {
+    settings: Res<'w, mode::MatchSettings>,
+    timer: Res<'w, game::CountDownTimer>,
+    match_time: Res<'w, game::MatchTime>,
+    player_query: Query<'w, 's, (&'static player::Player, &'static Transform, Option<&'static player::Tagger>, Option<&'static player::Cooldown>, Option<&'static player::Frozen>, Option<&'static player::Stunned>)>,
+    bullet_query: Query<'w, 's, (&'static bullet::Bullet, &'static Transform)>,
+    orb_query: Query<'w, 's, &'static Transform, With<orb::Orb>>,
+}
+
+impl<'w, 's> MatchObserver<'w, 's> {
+    pub fn observe(&self, tick: u64) -> Observation {
+        let players = self.player_query.iter()
+            .map(|(player, transform, tagger, cooldown, frozen, stunned)| PlayerObservation {
+                id: player.id,
+                x: transform.translation.x,
+                y: transform.translation.y,
+                rotation: player.rotation(),
+                velocity: player.velocity,
+                role: if tagger.is_some() {Role::Tagger} else {Role::Runner},
+                cooldown: cooldown.map_or(0., |cooldown| cooldown.0.duration().as_secs_f32() - cooldown.0.elapsed_secs()),
+                frozen: frozen.is_some(),
+                stunned: stunned.is_some(),
+            })
+            .collect();
+
+        let bullets = self.bullet_query.iter()
+            .map(|(bullet, transform)| BulletObservation {
+                x: transform.translation.x,
+                y: transform.translation.y,
+                shooter: self.player_query.get(bullet.shooter).ok()
+                    .map(|(player, ..)| player.id)
+                    .filter(|id| *id < player::MAX_PLAYERS),
+            })
+            .collect();
+
+        Observation {
+            tick,
+            match_time: self.match_time.0,
+            time_left: self.settings.mode.has_time_limit().then(|| self.timer.0.duration().as_secs_f32() - self.timer.0.elapsed_secs()),
+            players,
+            bullets,
+            orbs: self.orb_query.iter().map(|transform| [transform.translation.x, transform.translation.y]).collect(),
+        }
+    }
+}
+
+pub fn send_observations(
+    mut server: ResMut<AgentServer>,
+    observer: MatchObserver,
+) {
+    if server.agents.iter().all(|agent| agent.id.is_none()) {
+        return;
+    }
+
+    server.tick += 1;
+
+    let observation = AgentMessage::Observation(observer.observe(server.tick));
+    let lockstep = server.lockstep;
+
+    server.agents.retain_mut(|agent| {
+        if agent.id.is_none() {
+            return true;
+        }
+
+        agent.answered = !lockstep;
+        agent.send(&observation).is_ok()
+    });
+}
+
+pub fn fix_time_step(
+    mut time: ResMut<Time>,
+    state: Res<State<GameState>>,
+    lockstep_time: Option<Res<LockstepTime>>,
+) {
+    if let (GameState::Game, Some(lockstep_time)) = (state.current(), lockstep_time) {
+        *time = lockstep_time.0.clone();
+    }
+}
+
+// The match stands still until every agent answered
+pub fn wait_for_agents(
+    state: Res<State<GameState>>,
+    server: Option<ResMut<AgentServer>>,
+) {
+    let mut server = match server {
+        Some(server) if server.lockstep && *state.current() == GameState::Game => server,
+        _ => return,
+    };
+
+    if server.agents.iter().all(|agent| agent.answered) {
+        return;
+    }
+
+    let timeout = Duration::from_secs_f32(LOCKSTEP_TIMEOUT);
+
+    server.agents.retain_mut(|agent| {
+        if agent.stream.set_nonblocking(false).is_err()
+            || agent.stream.set_read_timeout(Some(timeout)).is_err()
+            || agent.stream.set_write_timeout(Some(timeout)).is_err()
+        {
+            return false;
+        }
+
+        // The observation to answer may still be waiting, blocking it all goes out
+        if agent.flush().is_err() {
+            return false;
+        }
+
+        while !agent.answered {
+            match agent.read_once() {
+                Ok(0) => return false,
+                Ok(_) => {},
+                Err(err) => {
+                    if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) {
+                        warn!("An agent didn't answer within {} seconds", LOCKSTEP_TIMEOUT);
+                    }
+
+                    return false;
+                },
+            }
+
+            // Only the answer matters here, anything else is dropped
+            for line in agent.take_lines() {
+                match serde_json::from_str::<AgentCommand>(&line) {
+                    Ok(AgentCommand::Intent(intent)) => {
+                        agent.intent = intent;
+                        agent.answered = true;
+                    },
+                    Ok(AgentCommand::Leave) => return false,
+                    _ => {},
+                }
+            }
+        }
+
+        agent.stream.set_nonblocking(true).is_ok()
+    });
+}
+
+// Agents stay connected between matches, they only let go of their player
+pub fn end_agent_match(
+    server: Option<ResMut<AgentServer>>,
+    winner: Option<Res<crate::Winner>>,
+) {
+    let mut server = match server {
+        Some(server) => server,
+        None => return,
+    };
+
+    let winner = winner.map_or_else(String::new, |winner| winner.0.clone());
+
+    server.agents.retain_mut(|agent| {
+        agent.answered = true;
+        agent.id.is_none() || agent.send(&AgentMessage::End { winner: winner.clone() }).is_ok()
+    });
+}
@@ -0,0 +1,186 @@
+use std::{fs, time::Duration};
+
+use bevy::{prelude::*, app::{AppExit, ScheduleRunnerSettings}};
+use serde::Deserialize;
+use serde_json::json;
+
+use bevy_jam::*;
+use bevy_jam::mode::MatchSettings;
+
+pub const DEFAULT_RULES_FILE: &str = "assets/server_rules.json";
+pub const TICK_RATE: f64 = 60.;
+
+// The matches the server runs one after the other, starting over at the end if it repeats
+#[derive(Deserialize)]
+pub struct ServerRules {
+    #[serde(flatten)]
+    pub presets: tournament::RulesPresets,
+    #[serde(default)]
+    pub repeat: bool,
+}
+
+impl ServerRules {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let rules: Self = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+
+        if rules.presets.matches.is_empty() {
+            return Err("there are no matches in it".to_string());
+        }
+
+        rules.presets.check()?;
+
+        Ok(rules)
+    }
+}
+
+pub struct ServerConfig {
+    pub port: u16,
+    pub next_match: usize,
+    pub played: usize,
+}
+
+// Read from `--port <port>` and `--rules <file>` on the command line
+fn parse_args() -> (u16, String) {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |name: &str| args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .cloned();
+
+    let port = value("--port").and_then(|port| port.parse().ok()).unwrap_or(net::DEFAULT_PORT);
+    let rules = value("--rules").unwrap_or_else(|| DEFAULT_RULES_FILE.to_string());
+
+    (port, rules)
+}
+
+fn main() {
+    let (port, rules_file) = parse_args();
+    let rules = match ServerRules::load(&rules_file) {
+        Ok(rules) => rules,
+        Err(err) => {
+            eprintln!("Failed to load the rules from {}: {}", rules_file, err);
+            std::process::exit(1);
+        },
+    };
+
+    let mut app = App::new();
+    insert_arg_resources(&mut app);
+
+    app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(1. / TICK_RATE)))
+    .insert_resource(rules)
+    .insert_resource(ServerConfig { port, next_match: 0, played: 0 })
+    .insert_resource(net::HostName("Dedicated server".to_string()))
+    .add_plugins(MinimalPlugins)
+    .add_plugin(headless::HeadlessPlugin)
+    .add_plugins(MatchPlugins)
+    .add_state(GameState::Lobby)
+    .add_startup_system(setup_server)
+    .add_system_set(
+        SystemSet::on_update(GameState::Lobby)
+            .with_system(start_when_ready),
+    )
+    .add_system_set(
+        SystemSet::on_update(GameState::MainMenu)
+            .with_system(report_match),
+    )
+    .run();
+}
+
+fn setup_server(
+    mut commands: Commands,
+    config: Res<ServerConfig>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if !host(&mut commands, config.port) {
+        exit.send(AppExit);
+    }
+}
+
+fn host(commands: &mut Commands, port: u16) -> bool {
+    match net::NetSession::host_dedicated(port) {
+        Ok(session) => {
+            commands.insert_resource(session);
+            println!("{}", json!({ "event": "listening", "port": port }));
+            true
+        },
+        Err(err) => {
+            eprintln!("Failed to listen on port {}: {}", port, err);
+            false
+        },
+    }
+}
+
+pub fn start_when_ready(
+    rules: Res<ServerRules>,
+    config: Res<ServerConfig>,
+    session: Option<ResMut<net::NetSession>>,
+    mut settings: ResMut<MatchSettings>,
+    mut random: ResMut<Random>,
+    mut app_state: ResMut<State<GameState>>,
+) {
+    let mut session = match session {
+        Some(session) => session,
+        None => return,
+    };
+
+    // Set before the players are in, so the announcements show the coming mode
+    let rules = &rules.presets.matches[config.next_match];
+    settings.mode = rules.mode;
+    settings.overtime = rules.overtime;
+
+    if session.players < rules.players {
+        return;
+    }
+
+    net::start_match(&mut session, &mut settings, &mut random, &mut app_state);
+
+    println!("{}", json!({
+        "event": "match_started",
+        "match": config.played + 1,
+        "mode": settings.mode.name(),
+        "players": settings.players,
+    }));
+}
+
+// A finished match lands in the main menu, the server reports it and waits for the next one
+pub fn report_match(
+    mut commands: Commands,
+    rules: Res<ServerRules>,
+    mut config: ResMut<ServerConfig>,
+    settings: Res<MatchSettings>,
+    winner: Option<Res<Winner>>,
+    match_time: Option<Res<game::MatchTime>>,
+    mut app_state: ResMut<State<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    config.played += 1;
+
+    println!("{}", json!({
+        "event": "match_ended",
+        "match": config.played,
+        "mode": settings.mode.name(),
+        "players": settings.players,
+        "winner": winner.map(|winner| winner.0.clone()),
+        "duration": match_time.map_or(0., |match_time| match_time.0),
+    }));
+
+    commands.remove_resource::<Winner>();
+    config.next_match += 1;
+
+    if config.next_match >= rules.presets.matches.len() {
+        if !rules.repeat {
+            exit.send(AppExit);
+            return;
+        }
+
+        config.next_match = 0;
+    }
+
+    // Replacing the whole stack passes back through the finished match, so the stack doesn't grow with every match
+    if host(&mut commands, config.port) {
+        app_state.replace(GameState::Lobby).unwrap();
+    } else {
+        exit.send(AppExit);
+    }
+}
@@ -0,0 +1,91 @@
+use std::{fs, thread, time::Instant};
+
+use bevy_jam::tournament::{self, BotPolicy, RulesPresets, TournamentConfig};
+
+pub enum Format {
+    Csv,
+    Json,
+}
+
+// Read from `--policies <a,b,..>`, `--seeds <count>`, `--rules <file>`, `--threads <count>`,
+// `--format <csv|json>` and `--out <file>` on the command line
+fn parse_args() -> Result<(TournamentConfig, Format, Option<String>), String> {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |name: &str| args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .cloned();
+
+    let policies = match value("--policies") {
+        Some(names) => names.split(',')
+            .map(|name| BotPolicy::from_name(name.trim()).ok_or_else(|| format!("There is no policy {}", name)))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => BotPolicy::ALL.to_vec(),
+    };
+
+    if policies.len() < 2 {
+        return Err("A tournament needs at least two policies".to_string());
+    }
+
+    let rules = value("--rules").unwrap_or_else(|| tournament::DEFAULT_RULES_FILE.to_string());
+    let presets = RulesPresets::load(&rules)
+        .map_err(|err| format!("Failed to load the rules file {}: {}", rules, err))?
+        .matches;
+
+    if presets.is_empty() {
+        return Err(format!("The rules file {} has no matches", rules));
+    }
+
+    let seeds = value("--seeds").and_then(|seeds| seeds.parse().ok()).unwrap_or(10);
+    let threads = value("--threads")
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()));
+
+    let format = match value("--format").as_deref() {
+        None | Some("csv") => Format::Csv,
+        Some("json") => Format::Json,
+        Some(other) => return Err(format!("Unknown format {}, use csv or json", other)),
+    };
+
+    Ok((TournamentConfig { policies, presets, seeds, threads }, format, value("--out")))
+}
+
+fn main() {
+    let (config, format, out) = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        },
+    };
+
+    let matches = tournament::jobs(&config).len();
+    eprintln!("Playing {} matches on {} threads", matches, config.threads);
+
+    let started = Instant::now();
+    let results = match tournament::run(&config) {
+        Ok(results) => results,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        },
+    };
+    let stats = tournament::matchup_stats(&config, &results);
+
+    eprintln!("Played {} matches in {:.1}s", results.len(), started.elapsed().as_secs_f32());
+
+    let output = match format {
+        Format::Csv => tournament::to_csv(&stats),
+        Format::Json => serde_json::to_string_pretty(&stats).unwrap(),
+    };
+
+    match out {
+        Some(path) => {
+            if let Err(err) = fs::write(&path, output) {
+                eprintln!("Failed to write {}: {}", path, err);
+                std::process::exit(1);
+            }
+        },
+        None => println!("{}", output),
+    }
+}
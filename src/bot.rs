@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use crate::{player, ability};
+use crate::ability::{Ability, Role};
+
+pub const TURN_TOLERANCE: f32 = 0.05;
+pub const FACING_ANGLE: f32 = 0.8;
+pub const AIM_ANGLE: f32 = 0.15;
+pub const SHOT_RANGE: f32 = 400.;
+pub const MINE_RANGE: f32 = 80.;
+
+// A tagger steered by the computer instead of a keyboard
+#[derive(Component)]
+pub struct Bot;
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(crate::GameState::Game)
+                .with_system(drive_bots.label("intent")),
+        );
+    }
+}
+
+// Bots get the ids after the ones of the human players, so they never show up in the top bar
+pub fn spawn_bot(
+    commands: &mut Commands,
+    id: usize,
+    pos: Vec3,
+    ability: Ability,
+    speed: f32,
+) -> Entity {
+    let loadout = ability::Loadout { runner: Ability::Dash, tagger: ability };
+    let entity = player::spawn_player(commands, player::MAX_PLAYERS + id, Vec3::new(pos.x, pos.y, 1.), Role::Tagger, loadout);
+
+    commands.entity(entity)
+        .insert(player::SpeedFactor(speed))
+        .insert(Bot);
+
+    entity
+}
+
+fn ability_range(ability: Ability) -> f32 {
+    match ability {
+        Ability::Mine => MINE_RANGE,
+        Ability::RadarPulse => ability::PULSE_RADIUS,
+        _ => SHOT_RANGE,
+    }
+}
+
+// Turns towards the target and only speeds up once roughly facing it
+pub fn steering_intent(player: &player::Player, pos: Vec2, target: Vec2) -> player::Intent {
+    steer(player.heading().truncate(), pos, target)
+}
+
+pub fn steer(heading: Vec2, pos: Vec2, target: Vec2) -> player::Intent {
+    let angle = heading.angle_between(target - pos);
+
+    let mut intent = player::Intent::default();
+    intent.up = angle.abs() < FACING_ANGLE;
+    intent.left = angle > TURN_TOLERANCE;
+    intent.right = angle < -TURN_TOLERANCE;
+
+    intent
+}
+
+// Chases the closest runner or decoy, the bots can't tell them apart either
+pub fn drive_bots(
+    mut bot_query: Query<(&player::Player, &Transform, &ability::Loadout, Option<&ability::Charging>, &mut player::Intent), With<Bot>>,
+    target_query: Query<&Transform, (Or<(With<player::Runner>, With<ability::Decoy>)>, Without<player::Frozen>, Without<Bot>)>,
+) {
+    for (bot, transform, loadout, charging, mut intent) in bot_query.iter_mut() {
+        let pos = transform.translation.truncate();
+
+        let target = target_query.iter()
+            .map(|target| target.translation.truncate())
+            .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos)));
+
+        let target = match target {
+            Some(target) => target,
+            None => {
+                intent.update(player::Intent::default());
+                continue;
+            },
+        };
+
+        let angle = bot.heading().truncate().angle_between(target - pos);
+        let aimed = angle.abs() < AIM_ANGLE;
+        let in_range = target.distance(pos) < ability_range(loadout.tagger);
+
+        // The button has to be let go in between, otherwise only the first press counts
+        let action = match (loadout.tagger, charging) {
+            (Ability::ChargedShot, Some(charging)) => !charging.0.finished() || !aimed,
+            (Ability::Mine, _) | (Ability::RadarPulse, _) => in_range && !intent.action,
+            _ => aimed && in_range && !intent.action,
+        };
+
+        let mut next = steering_intent(bot, pos, target);
+        next.action = action;
+
+        intent.update(next);
+    }
+}
@@ -1,11 +1,16 @@
-use bevy::{prelude::*};
+use bevy::{prelude::*, utils::Duration};
 use bevy_prototype_lyon::prelude::*;
+use bevy_tweening::*;
 use heron::prelude::*;
 use crate::game;
 
-const BULLET_RADIUS: f32 = 5.;
+pub const BULLET_RADIUS: f32 = 5.;
+pub const BULLET_SPEED: f32 = 800.;
+pub const MINE_RADIUS: f32 = 12.;
+pub const MINE_DUR: f32 = 8.;
+pub const MAX_MINES: usize = 3;
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Bullet {
     pub shooter: Entity,
     dir_vec: Vec3,
@@ -14,28 +19,47 @@ pub struct Bullet {
 }
 
 impl Bullet {
-    pub fn new(dir_vec: Vec3, shooter: Entity) -> Self {
+    pub fn new(dir_vec: Vec3, shooter: Entity, radius: f32, speed: f32) -> Self {
         Self {
             dir_vec,
-            radius: BULLET_RADIUS,
-            speed: 800.,
+            radius,
+            speed,
             shooter,
         }
     }
+
+    pub fn advance(&self, translation: Vec3, delta: f32) -> Vec3 {
+        translation + self.dir_vec * self.speed * delta
+    }
+
+    pub fn dir_vec(&self) -> Vec3 {
+        self.dir_vec
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
 }
 
+// Mines are bullets standing still, so they tag runners the same way
+#[derive(Component)]
+pub struct Mine(pub Timer);
+
 pub fn move_bullets(
     mut commands: Commands,
     mut query: Query<(Entity, &mut Transform, &Bullet)>,
-    windows: Res<Windows>,
+    arena_size: Res<game::ArenaSize>,
     time: Res<Time>,
 ) {
-    let primary_window = windows.get_primary().unwrap();
-    let x_dist = primary_window.width() / 2.;
-    let y_dist = primary_window.height() / 2.;
+    let x_dist = arena_size.0.x / 2.;
+    let y_dist = arena_size.0.y / 2.;
 
     for (entity, mut transform, bullet) in query.iter_mut() {
-        transform.translation += bullet.dir_vec * bullet.speed * time.delta_seconds();
+        transform.translation = bullet.advance(transform.translation, time.delta_seconds());
 
         if transform.translation.x.abs() >= x_dist + bullet.radius {
             commands.entity(entity).despawn();
@@ -46,6 +70,20 @@ pub fn move_bullets(
     }
 }
 
+pub fn handle_mine_timer(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Mine)>,
+) {
+    for (entity, mut mine) in query.iter_mut() {
+        mine.0.tick(time.delta());
+
+        if mine.0.just_finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
 // HELPER FUNCTIONS
 pub fn spawn_bullet(
     commands: &mut Commands,
@@ -53,9 +91,20 @@ pub fn spawn_bullet(
     dir_vec: Vec3,
     shooter: Entity
 ) {
+    spawn_sized_bullet(commands, pos, dir_vec, shooter, BULLET_RADIUS, BULLET_SPEED);
+}
+
+pub fn spawn_sized_bullet(
+    commands: &mut Commands,
+    pos: Vec3,
+    dir_vec: Vec3,
+    shooter: Entity,
+    radius: f32,
+    speed: f32,
+) -> Entity {
     commands.spawn_bundle(GeometryBuilder::build_as(
         &shapes::Circle{
-            radius: BULLET_RADIUS,
+            radius,
             center: Vec2::new(0., 0.),
         },
         DrawMode::Fill(FillMode::color(game::TAGGER_COL)),
@@ -64,9 +113,66 @@ pub fn spawn_bullet(
             ..Default::default()
         },
     ))
-    .insert(Bullet::new(dir_vec, shooter))
+    .insert(Bullet::new(dir_vec, shooter, radius, speed))
     .insert(RigidBody::KinematicPositionBased)
-    .insert(CollisionShape::Sphere{radius: BULLET_RADIUS})
-    .insert(CollisionLayers::new(game::Layer::Runner, game::Layer::Runner))
-    .insert(game::GameEntity);
-}
\ No newline at end of file
+    .insert(CollisionShape::Sphere{radius})
+    .insert(CollisionLayers::new(game::Layer::Runner, game::Layer::Runner).with_mask(game::Layer::Trap))
+    .insert(game::GameEntity)
+    .id()
+}
+
+pub fn spawn_mine(
+    commands: &mut Commands,
+    pos: Vec3,
+    shooter: Entity
+) -> Entity {
+    let tween = Tween::new(
+        EaseFunction::QuadraticInOut,
+        TweeningType::PingPong,
+        Duration::from_millis(600),
+        lens::TransformScaleLens {
+            start: Vec3::new(1., 1., 1.),
+            end: Vec3::new(0.7, 0.7, 1.),
+        },
+    );
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::RegularPolygon {
+            sides: 6,
+            center: Vec2::new(0., 0.),
+            feature: shapes::RegularPolygonFeature::Radius(MINE_RADIUS),
+        },
+        DrawMode::Outlined {
+            outline_mode: StrokeMode::new(game::TAGGER_COL, 3.),
+            fill_mode: FillMode::color(Color::rgb(23./255., 23./255., 23./255.)),
+        },
+        Transform {
+            translation: Vec3::new(pos.x, pos.y, 0.),
+            ..Default::default()
+        },
+    ))
+    .insert(Animator::new(tween))
+    .insert(Bullet::new(Vec3::ZERO, shooter, MINE_RADIUS, 0.))
+    .insert(Mine(Timer::from_seconds(MINE_DUR, false)))
+    .insert(RigidBody::KinematicPositionBased)
+    .insert(CollisionShape::Sphere{radius: MINE_RADIUS})
+    .insert(CollisionLayers::new(game::Layer::Runner, game::Layer::Runner).with_mask(game::Layer::Trap))
+    .insert(game::GameEntity)
+    .id()
+}
+// Puts a bullet back the way it was in an earlier frame
+pub fn respawn_bullet(
+    commands: &mut Commands,
+    bullet: &Bullet,
+    pos: Vec3,
+    mine: Option<Timer>,
+) -> Entity {
+    match mine {
+        Some(timer) => {
+            let entity = spawn_mine(commands, pos, bullet.shooter);
+            commands.entity(entity).insert(Mine(timer));
+            entity
+        },
+        None => spawn_sized_bullet(commands, pos, bullet.dir_vec, bullet.shooter, bullet.radius, bullet.speed),
+    }
+}
@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+use bevy_kira_audio::Audio;
+use bevy_prototype_lyon::prelude::*;
+use heron::prelude::*;
+
+use crate::{GameState, game, player, orb};
+use crate::mode::{self, GameMode, TagEvent, TeamScores};
+
+pub const SCORE_LIMIT: u32 = 3;
+pub const BASE_RADIUS: f32 = 60.;
+pub const BASE_OFFSET: f32 = 90.;
+pub const FLAG_RETURN_DUR: f32 = 8.;
+pub const FLAG_PICKUP_DELAY: f32 = 1.;
+pub const FLAG_COL: Color = Color::rgb(1., 1., 1.);
+
+// The neutral orb the teams are fighting for, it returns home if left on the ground for too long
+#[derive(Component)]
+pub struct Flag {
+    home: Vec3,
+    dropped: Option<Timer>,
+}
+
+// Put on the flag while a player is carrying it
+#[derive(Component)]
+pub struct Carried(pub Entity);
+
+#[derive(Component)]
+pub struct Base(pub player::Team);
+
+pub struct CaptureTheOrbPlugin;
+
+impl Plugin for CaptureTheOrbPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::CaptureTheOrb))
+                .with_system(setup_capture_the_orb)
+                .with_system(return_dropped_flag)
+                .with_system(follow_carrier.after("move")),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::CaptureTheOrb))
+                .with_system(drop_flag_on_tag.label("resolve").after("collisions"))
+                .with_system(handle_flag_collisions.after("resolve"))
+                .with_system(end_capture_the_orb_match.after("countdown")),
+        );
+    }
+}
+
+// Places the bases and the flag once the players of the match are spawned
+pub fn setup_capture_the_orb(
+    mut commands: Commands,
+    arena: Res<game::Arena>,
+    query: Query<(), Added<player::Player>>,
+) {
+    if query.is_empty() {
+        return;
+    }
+
+    let center = arena.center();
+    let x_dist = arena.size().x / 2. - BASE_OFFSET;
+
+    for (team, x) in [(0, center.x - x_dist), (1, center.x + x_dist)] {
+        let mut fill_color = game::TEAM_COLS[team];
+        fill_color.set_a(0.15);
+
+        commands.spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: BASE_RADIUS,
+                center: Vec2::new(0., 0.),
+            },
+            DrawMode::Outlined {
+                outline_mode: StrokeMode::new(game::TEAM_COLS[team], 3.),
+                fill_mode: FillMode::color(fill_color),
+            },
+            Transform::from_xyz(x, center.y, 0.),
+        ))
+        .insert(RigidBody::Sensor)
+        .insert(CollisionShape::Sphere{radius: BASE_RADIUS})
+        .insert(CollisionLayers::new(game::Layer::Zone, game::Layer::Runner))
+        .insert(Base(player::Team(team)))
+        .insert(game::GameEntity);
+    }
+
+    let home = center.extend(2.);
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &orb::orb_shape(),
+        DrawMode::Outlined {
+            outline_mode: StrokeMode::new(game::ORB_OUTLINE_COLOR, 5.),
+            fill_mode: FillMode::color(FLAG_COL),
+        },
+        Transform::from_translation(home),
+    ))
+    .insert_bundle(flag_collider())
+    .insert(Flag { home, dropped: None })
+    .insert(game::GameEntity);
+}
+
+fn flag_collider() -> (RigidBody, CollisionShape, CollisionLayers) {
+    (
+        RigidBody::Sensor,
+        CollisionShape::Sphere{radius: orb::ORB_RADIUS},
+        CollisionLayers::new(game::Layer::Zone, game::Layer::Tagger),
+    )
+}
+
+fn pick_up_flag(commands: &mut Commands, flag_entity: Entity, flag: &mut Flag, player_entity: Entity) {
+    flag.dropped = None;
+
+    commands.entity(flag_entity)
+        .remove::<RigidBody>()
+        .remove::<CollisionShape>()
+        .remove::<CollisionLayers>()
+        .insert(Carried(player_entity));
+
+    player::become_runner(commands, player_entity);
+}
+
+fn release_flag(commands: &mut Commands, flag_entity: Entity, transform: &mut Transform, translation: Vec3) {
+    transform.translation = Vec3::new(translation.x, translation.y, 2.);
+    transform.scale = Vec3::new(1., 1., 1.);
+
+    commands.entity(flag_entity)
+        .remove::<Carried>()
+        .insert_bundle(flag_collider());
+}
+
+// Tagging the carrier of the other team makes them drop the flag where they are
+pub fn drop_flag_on_tag(
+    mut commands: Commands,
+    mut events: EventReader<TagEvent>,
+    team_query: Query<(&player::Team, &Transform), Without<Flag>>,
+    mut flag_query: Query<(Entity, &mut Flag, &Carried, &mut Transform)>,
+) {
+    for event in events.iter() {
+        let (runner_team, runner_transform) = match team_query.get(event.runner) {
+            Ok(runner) => runner,
+            Err(_) => continue,
+        };
+
+        let same_team = team_query.get(event.tagger).is_ok_and(|(team, _)| team == runner_team);
+
+        for (flag_entity, mut flag, carried, mut transform) in flag_query.iter_mut() {
+            if carried.0 == event.runner && !same_team {
+                flag.dropped = Some(Timer::from_seconds(FLAG_RETURN_DUR, false));
+                release_flag(&mut commands, flag_entity, &mut transform, runner_transform.translation);
+                player::become_tagger(&mut commands, event.runner);
+            }
+        }
+    }
+}
+
+// Anyone can pick up a flag lying around, carrying it into the own base scores a point
+pub fn handle_flag_collisions(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    mut scores: ResMut<TeamScores>,
+    audio: Res<Audio>,
+    orb_sound: Res<crate::OrbSound>,
+    mut flag_query: Query<(Entity, &mut Flag, Option<&Carried>, &mut Transform)>,
+    base_query: Query<&Base>,
+    player_query: Query<&player::Team, With<player::Player>>,
+) {
+    for event in events.iter() {
+        if let CollisionEvent::Started(data1, data2) = event {
+            let entity1 = data1.rigid_body_entity();
+            let entity2 = data2.rigid_body_entity();
+
+            let (other, player_entity) = if player_query.get(entity2).is_ok() {
+                (entity1, entity2)
+            } else if player_query.get(entity1).is_ok() {
+                (entity2, entity1)
+            } else {
+                continue;
+            };
+            let team = player_query.get(player_entity).unwrap();
+
+            for (flag_entity, mut flag, carried, mut transform) in flag_query.iter_mut() {
+                match carried {
+                    None if flag_entity == other => {
+                        let can_pick_up = flag.dropped.as_ref()
+                            .is_none_or(|timer| timer.elapsed_secs() >= FLAG_PICKUP_DELAY);
+
+                        if can_pick_up {
+                            audio.play(orb_sound.0.clone());
+                            pick_up_flag(&mut commands, flag_entity, &mut flag, player_entity);
+                        }
+                    },
+                    Some(carried) if carried.0 == player_entity && base_query.get(other).is_ok_and(|base| base.0 == *team) => {
+                        scores.0[team.0] += 1;
+
+                        let home = flag.home;
+                        release_flag(&mut commands, flag_entity, &mut transform, home);
+                        player::become_tagger(&mut commands, player_entity);
+                    },
+                    _ => {},
+                }
+            }
+        }
+    }
+}
+
+pub fn return_dropped_flag(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Flag, &mut Transform), Without<Carried>>,
+) {
+    for (entity, mut flag, mut transform) in query.iter_mut() {
+        let returned = match flag.dropped.as_mut() {
+            Some(timer) => timer.tick(time.delta()).just_finished(),
+            None => false,
+        };
+
+        if returned {
+            flag.dropped = None;
+
+            let home = flag.home;
+            release_flag(&mut commands, entity, &mut transform, home);
+        }
+    }
+}
+
+// The carried flag sits on top of its carrier in a smaller size
+pub fn follow_carrier(
+    mut flag_query: Query<(&Carried, &mut Transform), With<Flag>>,
+    player_query: Query<&Transform, (With<player::Player>, Without<Flag>)>,
+) {
+    for (carried, mut transform) in flag_query.iter_mut() {
+        if let Ok(player_transform) = player_query.get(carried.0) {
+            transform.translation = Vec3::new(player_transform.translation.x, player_transform.translation.y, 2.);
+            transform.scale = Vec3::new(0.5, 0.5, 1.);
+        }
+    }
+}
+
+// The first team reaching the score limit wins, if the time is up the team with more points does
+pub fn end_capture_the_orb_match(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    timer: Res<game::CountDownTimer>,
+    scores: Res<TeamScores>,
+) {
+    let best_score = scores.0.iter().copied().max().unwrap_or(0);
+
+    if best_score >= SCORE_LIMIT || timer.0.just_finished() {
+        game::end_match(&mut commands, &mut app_state, mode::best_teams(&scores));
+    }
+}
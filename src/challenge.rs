@@ -0,0 +1,341 @@
+use std::{collections::HashMap, fs};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, game, player, ability, orb, bot, practice, time_trial};
+use crate::ability::{Ability, Role};
+use crate::player::{ActionEvent, ActionKind};
+use crate::mode::{self, GameMode, MatchSettings, TagEvent, OrbEvent};
+
+pub const CHALLENGES_FILE: &str = "assets/challenges.json";
+pub const STARS_FILE: &str = "challenge_stars.json";
+pub const MAX_STARS: u32 = 3;
+
+// What clears the challenge, getting tagged always fails it
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum Goal {
+    Survive,
+    Tag,
+    Orbs(u32),
+}
+
+// Every bonus reached on top of the goal is worth another star
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum Bonus {
+    Within(f32),
+    Orbs(u32),
+    NoAbility,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct StartPosition {
+    pub role: Role,
+    pub x: f32,
+    pub y: f32,
+}
+
+// Tagger bots chase the player, runner bots follow their path or stand still without one
+#[derive(Deserialize, Clone)]
+pub struct ScenarioBot {
+    pub role: Role,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default = "default_bot_speed")]
+    pub speed: f32,
+    #[serde(default)]
+    pub ability: Option<Ability>,
+    #[serde(default)]
+    pub path: Vec<Vec2>,
+}
+
+fn default_bot_speed() -> f32 {
+    1.
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub description: String,
+    pub player: StartPosition,
+    #[serde(default)]
+    pub loadout: Option<ability::Loadout>,
+    #[serde(default)]
+    pub orbs: Vec<Vec2>,
+    #[serde(default)]
+    pub bots: Vec<ScenarioBot>,
+    pub time_limit: f32,
+    pub goal: Goal,
+    #[serde(default)]
+    pub forbid: Vec<Ability>,
+    #[serde(default)]
+    pub bonus: Vec<Bonus>,
+}
+
+pub fn load_scenarios() -> Result<Vec<Scenario>, String> {
+    let content = fs::read_to_string(CHALLENGES_FILE).map_err(|err| err.to_string())?;
+    let scenarios: Vec<Scenario> = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+
+    if scenarios.is_empty() {
+        return Err("there are no challenges in it".to_string());
+    }
+
+    // A challenge without time would be over before it starts
+    if let Some(scenario) = scenarios.iter().find(|scenario| !scenario.time_limit.is_finite() || scenario.time_limit <= 0.) {
+        return Err(format!("the time limit of {} has to be a positive number of seconds", scenario.name));
+    }
+
+    Ok(scenarios)
+}
+
+// The most stars reached in every challenge, stored next to the game
+#[derive(Serialize, Deserialize, Default)]
+pub struct ChallengeStars(pub HashMap<String, u32>);
+
+impl ChallengeStars {
+    pub fn load() -> Self {
+        fs::read_to_string(STARS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let content = serde_json::to_string_pretty(self).unwrap();
+
+        if let Err(err) = fs::write(STARS_FILE, content) {
+            warn!("Failed to save the challenge stars: {}", err);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> u32 {
+        self.0.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn record(&mut self, name: &str, stars: u32) {
+        let best = self.0.entry(name.to_string()).or_insert(0);
+        *best = (*best).max(stars);
+    }
+}
+
+#[derive(Default)]
+pub struct SelectedChallenge(pub usize);
+
+pub struct Challenge {
+    scenario: Scenario,
+    player: Option<Entity>,
+    orbs: u32,
+    used_ability: bool,
+}
+
+impl Challenge {
+    fn stars(&self, time: f32) -> u32 {
+        let bonuses = self.scenario.bonus.iter()
+            .filter(|bonus| match bonus {
+                Bonus::Within(seconds) => time <= *seconds,
+                Bonus::Orbs(count) => self.orbs >= *count,
+                Bonus::NoAbility => !self.used_ability,
+            })
+            .count() as u32;
+
+        (1 + bonuses).min(MAX_STARS)
+    }
+}
+
+pub struct ChallengePlugin;
+
+impl Plugin for ChallengePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedChallenge>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game)
+                    .with_system(load_challenge.after("setup_game")),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(mode::in_mode(GameMode::Challenge))
+                    .with_system(setup_challenge)
+                    .with_system(time_trial::drive_scripted_runner.label("intent"))
+                    .with_system(update_challenge_status.after("collisions"))
+                    .with_system(practice::leave_practice),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(mode::in_mode_as_host(GameMode::Challenge))
+                    .with_system(track_challenge.after("collisions")),
+            );
+    }
+}
+
+// The time limit and the orb layout have to be in place before the first orbs are spawned.
+// The file may have changed since the menu showed it, then the match is left right away
+pub fn load_challenge(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    settings: Res<MatchSettings>,
+    selected: Res<SelectedChallenge>,
+) {
+    if settings.mode != GameMode::Challenge {
+        return;
+    }
+
+    let scenario = load_scenarios().and_then(|scenarios| {
+        scenarios.get(selected.0).cloned().ok_or_else(|| format!("there is no challenge {}", selected.0 + 1))
+    });
+
+    let scenario = match scenario {
+        Ok(scenario) => scenario,
+        Err(err) => {
+            error!("Failed to load the challenge from {}: {}", CHALLENGES_FILE, err);
+            commands.remove_resource::<crate::Winner>();
+            let _ = app_state.push(GameState::MainMenu);
+            return;
+        },
+    };
+
+    commands.insert_resource(game::CountDownTimer::new(scenario.time_limit));
+    commands.insert_resource(orb::OrbLayout(scenario.orbs.clone()));
+    commands.insert_resource(Challenge {
+        scenario,
+        player: None,
+        orbs: 0,
+        used_ability: false,
+    });
+}
+
+// Puts the player on the starting position and spawns the bots of the scenario
+pub fn setup_challenge(
+    mut commands: Commands,
+    font: Res<crate::FontHandle>,
+    challenge: Option<ResMut<Challenge>>,
+    mut query: Query<(Entity, &mut player::Player, &mut Transform), (Added<player::Player>, Without<bot::Bot>, Without<practice::Dummy>)>,
+) {
+    let mut challenge = match challenge {
+        Some(challenge) => challenge,
+        None => return,
+    };
+
+    let (entity, mut player, mut transform) = match query.iter_mut().next() {
+        Some(player) => player,
+        None => return,
+    };
+
+    let scenario = challenge.scenario.clone();
+
+    player.reset_motion();
+    transform.translation = Vec3::new(scenario.player.x, scenario.player.y, transform.translation.z);
+
+    if scenario.player.role == Role::Tagger {
+        player::become_tagger(&mut commands, entity);
+    }
+
+    if let Some(loadout) = scenario.loadout {
+        commands.entity(entity).insert(loadout);
+    }
+
+    for (id, scenario_bot) in scenario.bots.iter().enumerate() {
+        let pos = Vec2::new(scenario_bot.x, scenario_bot.y);
+
+        match scenario_bot.role {
+            Role::Tagger => {
+                let ability = scenario_bot.ability.unwrap_or(Ability::Shot);
+                bot::spawn_bot(&mut commands, id, pos.extend(0.), ability, scenario_bot.speed);
+            },
+            Role::Runner => {
+                let dummy = practice::spawn_dummy(&mut commands, pos, Role::Runner);
+                commands.entity(dummy).insert(player::SpeedFactor(scenario_bot.speed));
+
+                if scenario_bot.path.len() > 1 {
+                    commands.entity(dummy).insert(time_trial::ScriptedRunner::new(scenario_bot.path.clone()));
+                }
+            },
+        }
+    }
+
+    game::spawn_prompt(&mut commands, font.0.clone(), &scenario.description);
+    challenge.player = Some(entity);
+}
+
+pub fn track_challenge(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    mut action_events: EventReader<ActionEvent>,
+    mut orb_events: EventReader<OrbEvent>,
+    mut tag_events: EventReader<TagEvent>,
+    timer: Res<game::CountDownTimer>,
+    match_time: Res<game::MatchTime>,
+    challenge: Option<ResMut<Challenge>>,
+    query: Query<&player::Player>,
+) {
+    let abilities: Vec<(Entity, Ability)> = action_events.iter()
+        .filter_map(|event| match event.kind {
+            ActionKind::Ability(ability) => Some((event.player, ability)),
+            _ => None,
+        })
+        .collect();
+    let orbs: Vec<Entity> = orb_events.iter().map(|event| event.player).collect();
+    let tags: Vec<(Entity, Entity)> = tag_events.iter().map(|event| (event.runner, event.tagger)).collect();
+
+    let mut challenge = match challenge {
+        Some(challenge) => challenge,
+        None => return,
+    };
+
+    let player = match challenge.player {
+        Some(player) => player,
+        None => return,
+    };
+
+    let mut failed = false;
+
+    for (entity, ability) in abilities {
+        if entity == player {
+            challenge.used_ability = true;
+            failed |= challenge.scenario.forbid.contains(&ability);
+        }
+    }
+
+    challenge.orbs += orbs.iter().filter(|entity| **entity == player).count() as u32;
+    failed |= tags.iter().any(|(runner, _)| *runner == player);
+
+    let cleared = match challenge.scenario.goal {
+        Goal::Survive => timer.0.just_finished(),
+        Goal::Tag => tags.iter().any(|(_, tagger)| *tagger == player),
+        Goal::Orbs(count) => challenge.orbs >= count,
+    };
+
+    if failed || (!cleared && timer.0.just_finished()) {
+        game::end_match(&mut commands, &mut app_state, "Nobody".to_string());
+    }
+    else if cleared {
+        let stars = challenge.stars(match_time.0);
+
+        let mut best = ChallengeStars::load();
+        best.record(&challenge.scenario.name, stars);
+        best.save();
+
+        let name = query.get(player).map_or("Nobody".to_string(), |player| player.name());
+        game::end_match(&mut commands, &mut app_state, format!("{} ({}/{} stars)", name, stars, MAX_STARS));
+    }
+}
+
+pub fn update_challenge_status(
+    challenge: Option<Res<Challenge>>,
+    mut query: Query<&mut Text, With<game::ModeStatus>>,
+) {
+    let challenge = match challenge {
+        Some(challenge) => challenge,
+        None => return,
+    };
+
+    let value = match challenge.scenario.goal {
+        Goal::Orbs(count) => format!("Orbs {}/{}", challenge.orbs, count),
+        _ => challenge.scenario.name.clone(),
+    };
+
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
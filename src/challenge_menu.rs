@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use crate::{GameState, game, mode, challenge};
+
+#[derive(Component)]
+pub struct ChallengeMenu;
+
+#[derive(Component)]
+pub struct ChallengeButton(usize);
+
+#[derive(Component)]
+pub struct BackButton;
+
+pub struct ChallengeMenuPlugin;
+
+impl Plugin for ChallengeMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::ChallengeMenu)
+                .with_system(setup_challenge_menu),
+        )
+        .add_system_set(
+            SystemSet::on_resume(GameState::ChallengeMenu)
+                .with_system(setup_challenge_menu),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::ChallengeMenu)
+                .with_system(handle_challenge_menu_interactions),
+        )
+        .add_system_set(
+            SystemSet::on_pause(GameState::ChallengeMenu)
+                .with_system(teardown_challenge_menu_items),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::ChallengeMenu)
+                .with_system(teardown_challenge_menu_items),
+        );
+    }
+}
+
+pub fn setup_challenge_menu(
+  mut commands: Commands,
+  font: Res<crate::FontHandle>,
+) {
+    let scenarios = challenge::load_scenarios();
+    let stars = challenge::ChallengeStars::load();
+
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceEvenly,
+            ..Style::default()
+        },
+        visibility: Visibility {
+            is_visible: false,
+        },
+        ..NodeBundle::default()
+    })
+    .insert(ChallengeMenu)
+    .with_children(|parent| {
+        let alignment = TextAlignment {
+            vertical: VerticalAlign::Center,
+            horizontal: HorizontalAlign::Center,
+        };
+
+        let btn_text_style = TextStyle {
+            font: font.0.clone(),
+            font_size: 30.0,
+            color: Color::WHITE,
+        };
+
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section("Challenges", TextStyle {
+                font: font.0.clone(),
+                font_size: 50.0,
+                color: game::TAGGER_COL,
+            }, alignment),
+            ..TextBundle::default()
+        });
+
+        // CHALLENGE BUTTONS
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            let scenarios = match &scenarios {
+                Ok(scenarios) => scenarios,
+                Err(err) => {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(format!("Failed to load the challenges: {}", err), TextStyle {
+                            font: font.0.clone(),
+                            font_size: 20.0,
+                            color: game::RUNNER_COL,
+                        }, alignment),
+                        ..TextBundle::default()
+                    });
+                    return;
+                },
+            };
+
+            for (index, scenario) in scenarios.iter().enumerate() {
+                parent.spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Px(400.0),
+                            height: Val::Px(50.0),
+                        },
+                        margin: Rect {
+                            bottom: Val::Px(12.),
+                            ..Default::default()
+                        },
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceAround,
+                        ..Style::default()
+                    },
+                    ..ButtonBundle::default()
+                })
+                .insert(ChallengeButton(index))
+                .with_children(|parent| {
+                    let text = format!("{}  {}/{}", scenario.name, stars.get(&scenario.name), challenge::MAX_STARS);
+
+                    parent.spawn_bundle(TextBundle {
+                        style: Style::default(),
+                        text: Text::with_section(text, btn_text_style.clone(), alignment),
+                        ..TextBundle::default()
+                    });
+                });
+            }
+        });
+
+        // BACK BUTTON
+        parent.spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                },
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceAround,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(BackButton)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style::default(),
+                text: Text::with_section("Back", btn_text_style, alignment),
+                ..TextBundle::default()
+            });
+        });
+    });
+}
+
+// A challenge is a solo match of its own mode, it skips the loadout menu
+pub fn handle_challenge_menu_interactions(
+    mut app_state: ResMut<State<GameState>>,
+    mut settings: ResMut<mode::MatchSettings>,
+    mut selected: ResMut<challenge::SelectedChallenge>,
+    mut challenge_btn_query: Query<(&Interaction, &mut UiColor, &ChallengeButton), Without<BackButton>>,
+    mut back_btn_query: Query<(&Interaction, &mut UiColor), (With<BackButton>, Without<ChallengeButton>)>,
+) {
+    for (interaction, mut button, challenge_button) in challenge_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                selected.0 = challenge_button.0;
+                settings.mode = mode::GameMode::Challenge;
+                settings.players = 1;
+                app_state.push(GameState::Game).unwrap();
+                return;
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+
+    for (interaction, mut button) in back_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::MainMenu).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn teardown_challenge_menu_items(
+    mut commands: Commands,
+    query: Query<Entity, With<ChallengeMenu>>
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -56,27 +56,95 @@ pub fn setup_controlls_menu(
     })
     .insert(ControllsMenu)
     .with_children(|parent| {
-        // PLAYER_1 CONTROLLS
-        let player_1_controlls = [
-            ("Move forward", "W"),
-            ("Move backwards", "S"),
-            ("Turn right", "D"),
-            ("Turn left", "A"),
-            ("Action", "Space"),
-        ];
-
-        create_player_controlls(parent, "Player_1", player_1_controlls, font.0.clone());
-
-        // PLAYER_2 CONTROLLS
-        let player_2_controlls = [
-            ("Move forward", "Up"),
-            ("Move backwards", "Down"),
-            ("Turn right", "Right"),
-            ("Turn left", "Left"),
-            ("Action", "Enter"),
-        ];
-
-        create_player_controlls(parent, "Player_2", player_2_controlls, font.0.clone());
+        let row_style = Style {
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceEvenly,
+            ..Style::default()
+        };
+
+        parent.spawn_bundle(NodeBundle {
+            style: row_style.clone(),
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            // PLAYER_1 CONTROLLS
+            let player_1_controlls = [
+                ("Move forward", "W"),
+                ("Move backwards", "S"),
+                ("Turn right", "D"),
+                ("Turn left", "A"),
+                ("Action", "Space"),
+            ];
+
+            create_player_controlls(parent, "Player_1", player_1_controlls, font.0.clone());
+
+            // PLAYER_2 CONTROLLS
+            let player_2_controlls = [
+                ("Move forward", "Up"),
+                ("Move backwards", "Down"),
+                ("Turn right", "Right"),
+                ("Turn left", "Left"),
+                ("Action", "Enter"),
+            ];
+
+            create_player_controlls(parent, "Player_2", player_2_controlls, font.0.clone());
+
+            // PLAYER_3 CONTROLLS
+            let player_3_controlls = [
+                ("Move forward", "I"),
+                ("Move backwards", "K"),
+                ("Turn right", "L"),
+                ("Turn left", "J"),
+                ("Action", "O"),
+            ];
+
+            create_player_controlls(parent, "Player_3", player_3_controlls, font.0.clone());
+        });
+
+        parent.spawn_bundle(NodeBundle {
+            style: row_style,
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            // PLAYER_4 CONTROLLS
+            let player_4_controlls = [
+                ("Move forward", "Numpad 8"),
+                ("Move backwards", "Numpad 5"),
+                ("Turn right", "Numpad 6"),
+                ("Turn left", "Numpad 4"),
+                ("Action", "Numpad 0"),
+            ];
+
+            create_player_controlls(parent, "Player_4", player_4_controlls, font.0.clone());
+
+            // PLAYER_5 CONTROLLS
+            let player_5_controlls = [
+                ("Move forward", "T"),
+                ("Move backwards", "G"),
+                ("Turn right", "H"),
+                ("Turn left", "F"),
+                ("Action", "R"),
+            ];
+
+            create_player_controlls(parent, "Player_5", player_5_controlls, font.0.clone());
+
+            // PLAYER_6 CONTROLLS
+            let player_6_controlls = [
+                ("Move forward", "Home"),
+                ("Move backwards", "End"),
+                ("Turn right", "Page Down"),
+                ("Turn left", "Delete"),
+                ("Action", "Insert"),
+            ];
+
+            create_player_controlls(parent, "Player_6", player_6_controlls, font.0.clone());
+        });
 
         // BACK BUTTON
         parent.spawn_bundle(ButtonBundle {
@@ -121,13 +189,13 @@ fn create_player_controlls(
 ) {
     let title_text_style = TextStyle {
         font: font.clone(),
-        font_size: 40.0,
+        font_size: 35.0,
         color: Color::WHITE,
     };
 
     let title_style = Style {
         margin: Rect {
-            top: Val::Px(40.),
+            top: Val::Px(20.),
             bottom: Val::Px(10.),
             ..Default::default()
         },
@@ -136,7 +204,7 @@ fn create_player_controlls(
 
     let controll_text_style = TextStyle {
         font: font.clone(),
-        font_size: 25.0,
+        font_size: 22.0,
         color: Color::WHITE,
     };
 
@@ -150,6 +218,11 @@ fn create_player_controlls(
             flex_direction: FlexDirection::ColumnReverse,
             align_items: AlignItems::Center,
             justify_content: JustifyContent::SpaceEvenly,
+            margin: Rect {
+                left: Val::Px(20.),
+                right: Val::Px(20.),
+                ..Default::default()
+            },
             ..Style::default()
         },
         visibility: Visibility {
@@ -168,7 +241,7 @@ fn create_player_controlls(
             parent.spawn_bundle(NodeBundle {
                 style: Style {
                     size: Size {
-                        width: Val::Px(400.0),
+                        width: Val::Px(320.0),
                         ..Default::default()
                     },
                     flex_direction: FlexDirection::Row,
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy_kira_audio::Audio;
+use bevy_prototype_lyon::prelude::*;
+use bevy_tweening::*;
+use heron::prelude::*;
+
+use crate::{GameState, game, player, ability};
+use crate::mode::{self, GameMode, TagEvent};
+
+pub const FROZEN_COL: Color = Color::rgb(190./255., 230./255., 245./255.);
+
+pub struct FreezeTagPlugin;
+
+impl Plugin for FreezeTagPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::FreezeTag))
+                .with_system(freeze_runners.label("resolve").after("collisions"))
+                .with_system(unfreeze_runners.after("collisions")),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::FreezeTag))
+                .with_system(end_freeze_tag_match.after("resolve").after("countdown")),
+        );
+    }
+}
+
+pub fn freeze_runners(
+    mut commands: Commands,
+    mut events: EventReader<TagEvent>,
+    mut query: Query<&mut player::Player, Without<player::Frozen>>,
+) {
+    for event in events.iter() {
+        if let Ok(mut player) = query.get_mut(event.runner) {
+            player.velocity = 0.;
+
+            commands.entity(event.runner)
+                .remove::<player::Dash>()
+                .remove::<player::Cooldown>()
+                .remove::<Animator<Transform>>()
+                .remove::<Animator<DrawMode>>()
+                .insert(player::Frozen)
+                .insert(DrawMode::Fill(FillMode::color(FROZEN_COL)));
+        }
+    }
+}
+
+// A runner touching a frozen teammate sets them free
+pub fn unfreeze_runners(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    frozen_query: Query<(), (With<player::Runner>, With<player::Frozen>)>,
+    free_query: Query<(), (With<player::Runner>, Without<player::Frozen>)>,
+    audio: Res<Audio>,
+    ability_sounds: Res<ability::AbilitySounds>,
+) {
+    for event in events.iter() {
+        if let CollisionEvent::Started(data1, data2) = event {
+            let entity1 = data1.rigid_body_entity();
+            let entity2 = data2.rigid_body_entity();
+
+            let frozen = if frozen_query.get(entity1).is_ok() && free_query.get(entity2).is_ok() {
+                entity1
+            }
+            else if frozen_query.get(entity2).is_ok() && free_query.get(entity1).is_ok() {
+                entity2
+            }
+            else {
+                continue;
+            };
+
+            audio.play(ability_sounds.get(ability::Ability::Shield));
+
+            commands.entity(frozen)
+                .remove::<player::Frozen>()
+                .insert(DrawMode::Fill(FillMode::color(game::RUNNER_COL)));
+        }
+    }
+}
+
+// The taggers win as soon as every runner is frozen, the runners win if the time is up before that
+pub fn end_freeze_tag_match(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    timer: Res<game::CountDownTimer>,
+    runner_query: Query<Option<&player::Frozen>, With<player::Runner>>,
+    frozen_query: Query<(), Added<player::Frozen>>,
+) {
+    // Checked only when someone got frozen, so a match ending this way is decided once
+    if !frozen_query.is_empty() && runner_query.iter().all(|frozen| frozen.is_some()) {
+        game::end_match(&mut commands, &mut app_state, "Taggers".to_string());
+    }
+    else if timer.0.just_finished() {
+        game::end_match(&mut commands, &mut app_state, "Runners".to_string());
+    }
+}
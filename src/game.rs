@@ -1,26 +1,36 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, ecs::system::SystemParam, math::const_vec2};
 use bevy_kira_audio::Audio;
 use bevy_prototype_lyon::prelude::*;
 use bevy_tweening::*;
 use heron::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
 
 use crate::GameState;
-use crate::{player, bullet, orb};
+use crate::{player, bullet, orb, ability, trap, mode, freeze_tag, overtime, survival, tutorial, challenge};
+use crate::match_log::{MatchEvent, MatchEventKind};
 
 pub const GAME_DUR: f32 = 90.;
+pub const DEFAULT_ARENA_SIZE: Vec2 = const_vec2!([800., 800.]);
 pub const TAGGER_COL: Color = Color::rgb(94./255., 165./255., 255./255.);
 pub const RUNNER_COL: Color = Color::rgb(107./255., 186./255., 93./255.);
 pub const ORB_FILL_COLOR: Color = Color::rgb(181./255., 90./255., 214./255.1);
 pub const ORB_OUTLINE_COLOR: Color = Color::rgb(138./255., 30./255., 97./255.);
+pub const TEAM_COLS: [Color; 2] = [
+    Color::rgb(235./255., 87./255., 87./255.),
+    Color::rgb(242./255., 201./255., 76./255.),
+];
 
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
+        app.init_resource::<ArenaSize>()
+            .init_resource::<Arena>()
+            .add_system_set(
             SystemSet::on_enter(GameState::Game)
-                .with_system(setup_game)
-                .with_system(player::spawn_players),
+                .with_system(log_match_start.before("setup_game"))
+                .with_system(setup_game.label("setup_game"))
+                .with_system(player::spawn_players.after("setup_game")),
         )
         .add_system_set(
             SystemSet::on_resume(GameState::Game)
@@ -28,21 +38,40 @@ impl Plugin for GamePlugin {
         )
         .add_system_set(
             SystemSet::on_update(GameState::Game)
-                .with_system(player::handle_move_inputs.label("input"))
+                .with_system(player::read_key_intents.label("intent"))
+                .with_system(player::handle_move_inputs.label("input").after("intent"))
                 .with_system(component_animator_system::<DrawMode>.label("animate").after("input"))
                 .with_system(player::move_players.label("move").after("input"))
                 .with_system(player::handle_action_button_for_runner.after("move").label("action"))
                 .with_system(player::handle_action_button_for_tagger.after("move").label("action"))
                 .with_system(player::handle_dash_timer_for_runner)
-                .with_system(player::handle_cooldown_timer_for_runner)
+                .with_system(player::handle_cooldown_timer)
+                .with_system(ability::handle_shield_timer)
+                .with_system(ability::handle_charge_timer)
+                .with_system(ability::expand_pulses.after("move"))
+                .with_system(ability::move_decoys.label("move").after("input"))
+                .with_system(ability::despawn_finished_effects)
                 .with_system(bullet::move_bullets.label("move").after("input"))
+                .with_system(bullet::handle_mine_timer)
+                .with_system(trap::handle_trap_timer)
+                .with_system(player::handle_stun_timer)
+                .with_system(trap::apply_slow_fields.before("input"))
                 .with_system(orb::spawn_orb)
-                .with_system(detect_collisions.after("action").after("animate"))
-                .with_system(tick_count_down_timer)
+                .with_system(detect_collisions.label("collisions").after("action").after("animate"))
+                .with_system(log_collisions.after("collisions"))
+                .with_system(player::log_role_swaps)
+                .with_system(update_player_labels.after("collisions"))
+                .with_system(update_team_count.after("collisions"))
+                .with_system(update_score_board)
+                .with_system(apply_team_layers.before("collisions"))
+                .with_system(apply_team_bullet_layers.before("collisions"))
+                .with_system(outline_team_players)
+                .with_system(tick_count_down_timer.label("countdown"))
         )
         .add_system_set(
             SystemSet::on_pause(GameState::Game)
-                .with_system(teardown_game),
+                .with_system(teardown_game)
+                .with_system(log_match_end),
         )
         .add_system_set(
             SystemSet::on_exit(GameState::Game)
@@ -60,13 +89,104 @@ struct Countdown;
 #[derive(Component)]
 pub struct TopBar;
 
+#[derive(Component)]
+pub struct PlayerLabel(pub usize);
+
+#[derive(Component)]
+pub struct TeamCount;
+
+#[derive(Component)]
+pub struct ScoreBoard;
+
+// Free text next to the timer, filled in by the game mode
+#[derive(Component)]
+pub struct ModeStatus;
+
+// A line of text below the top bar telling the player what to do
+#[derive(Component)]
+pub struct Prompt;
+
 #[derive(PhysicsLayer)]
 pub enum Layer {
     Tagger,
     Runner,
+    Trap,
+    Zone,
+    Boundary,
+    Team1,
+    Team2,
+}
+
+pub fn team_layer(team: player::Team) -> Layer {
+    if team.0 == 0 {Layer::Team1} else {Layer::Team2}
 }
 
-pub struct CountDownTimer(Timer);
+// The playable area, everything moving is kept inside of it
+#[derive(Clone, Copy)]
+pub struct Arena {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::from_size(DEFAULT_ARENA_SIZE)
+    }
+}
+
+impl Arena {
+    // The top bar covers the top of the window
+    pub fn from_size(size: Vec2) -> Self {
+        Self {
+            min: Vec2::new(-size.x / 2., -size.y / 2.),
+            max: Vec2::new(size.x / 2., size.y / 2. - 50.),
+        }
+    }
+
+    pub fn from_window(window: &Window) -> Self {
+        Self::from_size(Vec2::new(window.width(), window.height()))
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    // The arena with the same center, but scaled down by the given factor
+    pub fn scaled(&self, factor: f32) -> Self {
+        let half_size = self.size() * factor / 2.;
+
+        Self {
+            min: self.center() - half_size,
+            max: self.center() + half_size,
+        }
+    }
+
+    pub fn contains(&self, pos: Vec3) -> bool {
+        pos.x >= self.min.x && pos.x <= self.max.x && pos.y >= self.min.y && pos.y <= self.max.y
+    }
+
+    pub fn clamp(&self, pos: Vec3) -> Vec3 {
+        Vec3::new(pos.x.clamp(self.min.x, self.max.x), pos.y.clamp(self.min.y, self.max.y), pos.z)
+    }
+}
+
+// The size of the window the arena fills, without a window it stays at the default
+pub struct ArenaSize(pub Vec2);
+
+impl Default for ArenaSize {
+    fn default() -> Self {
+        Self(DEFAULT_ARENA_SIZE)
+    }
+}
+
+// Seconds played in the current match, the countdown stops at the time limit
+pub struct MatchTime(pub f32);
+
+pub struct CountDownTimer(pub Timer);
 impl CountDownTimer {
     pub fn new(seconds: f32) -> Self {Self(Timer::from_seconds(seconds, false))}
 }
@@ -74,10 +194,27 @@ impl CountDownTimer {
 pub fn setup_game(
   mut commands: Commands,
   font: Res<crate::FontHandle>,
+  settings: Res<mode::MatchSettings>,
+  windows: Option<Res<Windows>>,
+  mut arena_size: ResMut<ArenaSize>,
+  mut arena: ResMut<Arena>,
 ) {
     commands.insert_resource(CountDownTimer::new(GAME_DUR));
-    commands.insert_resource(player::DashTimer::new(player::DASH_DUR));
-    commands.insert_resource(player::CooldownTimer::new(player::COOLDOWN_DUR));
+    commands.insert_resource(MatchTime(0.));
+
+    if let Some(seed) = settings.mode.fixed_seed() {
+        commands.insert_resource(crate::Random(StdRng::seed_from_u64(seed)));
+    }
+
+    // The players are spawned right after, so the arena is changed in place
+    if let Some(window) = windows.as_ref().and_then(|windows| windows.get_primary()) {
+        arena_size.0 = Vec2::new(window.width(), window.height());
+    }
+    *arena = Arena::from_size(arena_size.0);
+
+    if settings.mode.shows_team_scores() {
+        commands.insert_resource(mode::TeamScores([0, 0]));
+    }
 
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
     
@@ -110,19 +247,13 @@ pub fn setup_game(
     })
     .insert(TopBar)
     .with_children(|parent| {
-        // Player1
-        let text_style = TextStyle {
-            font: font.0.clone(),
-            font_size: 30.,
-            color: TAGGER_COL,
-            ..Default::default()
-        };
-        
-        parent.spawn_bundle(TextBundle {
-            text: Text::with_section("Player_1", text_style, TextAlignment::default()),
-            ..Default::default()
-        })
-        .insert(player::Player1);
+        let name_font_size = if settings.players > 2 {20.} else {30.};
+        let tagger_count = settings.tagger_count();
+        let left_count = settings.players.div_ceil(2);
+        let teams = settings.mode.has_teams();
+
+        // Players on the left
+        spawn_player_labels(parent, 0..left_count, teams.then_some(player::Team(0)), tagger_count, name_font_size, font.0.clone());
 
         // Timer
         let text_style = TextStyle {
@@ -132,29 +263,320 @@ pub fn setup_game(
             ..Default::default()
         };
 
-        parent.spawn_bundle(TextBundle {
-            text: Text::with_section("01:00", text_style, TextAlignment::default()),
-            ..Default::default()
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
         })
-        .insert(Countdown);
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section("01:00", text_style, TextAlignment::default()),
+                ..Default::default()
+            })
+            .insert(Countdown);
+
+            // Team sizes
+            if settings.mode.shows_team_counts() {
+                let count_style = |color| TextStyle {
+                    font: font.0.clone(),
+                    font_size: 25.,
+                    color,
+                };
+
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect {
+                            left: Val::Px(15.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text {
+                        sections: vec![
+                            TextSection { value: tagger_count.to_string(), style: count_style(TAGGER_COL) },
+                            TextSection { value: " : ".to_string(), style: count_style(Color::WHITE) },
+                            TextSection { value: (settings.players - tagger_count).to_string(), style: count_style(RUNNER_COL) },
+                        ],
+                        alignment: TextAlignment::default(),
+                    },
+                    ..Default::default()
+                })
+                .insert(TeamCount);
+            }
 
+            // Team scores
+            if settings.mode.shows_team_scores() {
+                let score_style = |color| TextStyle {
+                    font: font.0.clone(),
+                    font_size: 25.,
+                    color,
+                };
+
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect {
+                            left: Val::Px(15.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text {
+                        sections: vec![
+                            TextSection { value: "0".to_string(), style: score_style(TEAM_COLS[0]) },
+                            TextSection { value: " : ".to_string(), style: score_style(Color::WHITE) },
+                            TextSection { value: "0".to_string(), style: score_style(TEAM_COLS[1]) },
+                        ],
+                        alignment: TextAlignment::default(),
+                    },
+                    ..Default::default()
+                })
+                .insert(ScoreBoard);
+            }
 
-        // Player2
-        let text_style = TextStyle {
-            font: font.0.clone(),
+            // Game mode status
+            if settings.mode.shows_status() {
+                let status_style = TextStyle {
+                    font: font.0.clone(),
+                    font_size: 25.,
+                    color: Color::WHITE,
+                };
+
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect {
+                            left: Val::Px(15.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text::with_section("", status_style, TextAlignment::default()),
+                    ..Default::default()
+                })
+                .insert(ModeStatus);
+            }
+        });
+
+        // Players on the right
+        spawn_player_labels(parent, left_count..settings.players, teams.then_some(player::Team(1)), tagger_count, name_font_size, font.0.clone());
+    });
+}
+
+pub fn spawn_prompt(commands: &mut Commands, font: Handle<Font>, value: &str) {
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size {
+                width: Val::Percent(100.0),
+                height: Val::Px(50.0),
+            },
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(60.),
+                ..Default::default()
+            },
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Style::default()
+        },
+        visibility: Visibility {
+            is_visible: false,
+        },
+        ..NodeBundle::default()
+    })
+    .insert(GameEntity)
+    .with_children(|parent| {
+        let style = TextStyle {
+            font,
             font_size: 30.,
-            color: RUNNER_COL,
-            ..Default::default()
+            color: Color::WHITE,
         };
 
         parent.spawn_bundle(TextBundle {
-            text: Text::with_section("Player_2", text_style, TextAlignment::default()),
+            text: Text::with_section(value, style, TextAlignment::default()),
             ..Default::default()
         })
-        .insert(player::Player2);
+        .insert(Prompt);
+    });
+}
+
+fn spawn_player_labels(
+    parent: &mut ChildBuilder,
+    ids: std::ops::Range<usize>,
+    team: Option<player::Team>,
+    tagger_count: usize,
+    font_size: f32,
+    font: Handle<Font>,
+) {
+    parent.spawn_bundle(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..Style::default()
+        },
+        visibility: Visibility {
+            is_visible: false,
+        },
+        ..NodeBundle::default()
+    })
+    .with_children(|parent| {
+        // In team modes the names are grouped under the name of their team
+        if let Some(team) = team {
+            let text_style = TextStyle {
+                font: font.clone(),
+                font_size,
+                color: TEAM_COLS[team.0],
+            };
+
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect {
+                        right: Val::Px(10.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text::with_section(format!("{}:", mode::team_name(team)), text_style, TextAlignment::default()),
+                ..Default::default()
+            });
+        }
+
+        for id in ids {
+            let text_style = TextStyle {
+                font: font.clone(),
+                font_size,
+                color: if id < tagger_count {TAGGER_COL} else {RUNNER_COL},
+            };
+
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect {
+                        left: Val::Px(5.),
+                        right: Val::Px(5.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text::with_section(player::player_name(id), text_style, TextAlignment::default()),
+                ..Default::default()
+            })
+            .insert(PlayerLabel(id));
+        }
     });
 }
 
+// Colors the name of every player in the top bar by their current role
+fn update_player_labels(
+    player_query: Query<(&player::Player, Option<&player::Tagger>, Option<&player::Frozen>)>,
+    mut label_query: Query<(&PlayerLabel, &mut Text)>,
+) {
+    for (player, is_tagger, is_frozen) in player_query.iter() {
+        let color = if is_tagger.is_some() {
+            TAGGER_COL
+        } else if is_frozen.is_some() {
+            freeze_tag::FROZEN_COL
+        } else {
+            RUNNER_COL
+        };
+
+        for (label, mut text) in label_query.iter_mut() {
+            if label.0 == player.id && text.sections[0].style.color != color {
+                text.sections[0].style.color = color;
+            }
+        }
+    }
+}
+
+// Shows how many players are on each team in the top bar
+fn update_team_count(
+    tagger_query: Query<(), With<player::Tagger>>,
+    runner_query: Query<(), With<player::Runner>>,
+    mut text_query: Query<&mut Text, With<TeamCount>>,
+) {
+    for mut text in text_query.iter_mut() {
+        let (taggers, runners) = (tagger_query.iter().count().to_string(), runner_query.iter().count().to_string());
+
+        if text.sections[0].value != taggers || text.sections[2].value != runners {
+            text.sections[0].value = taggers;
+            text.sections[2].value = runners;
+        }
+    }
+}
+
+pub fn update_score_board(
+    scores: Option<Res<mode::TeamScores>>,
+    mut query: Query<&mut Text, With<ScoreBoard>>,
+) {
+    let scores = match scores {
+        Some(scores) => scores,
+        None => return,
+    };
+
+    if scores.is_changed() {
+        for mut text in query.iter_mut() {
+            text.sections[0].value = scores.0[0].to_string();
+            text.sections[2].value = scores.0[1].to_string();
+        }
+    }
+}
+
+// Players are in the group of their team, whichever role they are playing
+fn apply_team_layers(
+    mut query: Query<(&player::Team, &mut CollisionLayers), Or<(Changed<CollisionLayers>, Added<player::Team>)>>,
+) {
+    for (team, mut layers) in query.iter_mut() {
+        if !layers.contains_group(team_layer(*team)) {
+            *layers = layers.with_group(team_layer(*team));
+        }
+    }
+}
+
+// Bullets of a team only hit the runners of the other team
+fn apply_team_bullet_layers(
+    mut bullet_query: Query<(&bullet::Bullet, &mut CollisionLayers), Added<bullet::Bullet>>,
+    team_query: Query<&player::Team>,
+) {
+    for (bullet, mut layers) in bullet_query.iter_mut() {
+        if let Ok(team) = team_query.get(bullet.shooter) {
+            let other_team = player::Team(1 - team.0);
+            *layers = CollisionLayers::new(Layer::Runner, team_layer(other_team)).with_mask(Layer::Trap);
+        }
+    }
+}
+
+// Players of a team wear its color as an outline, so the role keeps the fill color
+fn outline_team_players(
+    mut query: Query<(&player::Team, &mut DrawMode), Changed<DrawMode>>,
+) {
+    for (team, mut draw_mode) in query.iter_mut() {
+        if let DrawMode::Fill(fill_mode) = *draw_mode {
+            *draw_mode = DrawMode::Outlined {
+                fill_mode,
+                outline_mode: StrokeMode::new(TEAM_COLS[team.0], 3.),
+            };
+        }
+    }
+}
+
+// Only the first end condition of a frame counts, the others find the match already leaving.
+// Returns whether this call ended the match
+pub fn end_match(
+    commands: &mut Commands,
+    app_state: &mut State<GameState>,
+    winner: String,
+) -> bool {
+    if *app_state.current() != GameState::Game || app_state.push(GameState::MainMenu).is_err() {
+        return false;
+    }
+
+    commands.insert_resource(crate::Winner(winner));
+    true
+}
+
 pub fn teardown_game(
     mut commands: Commands,
     top_bar: Query<Entity, With<TopBar>>,
@@ -162,47 +584,138 @@ pub fn teardown_game(
 ) {
     let top_bar_entity = top_bar.single();
     commands.entity(top_bar_entity).despawn_recursive();
+    commands.remove_resource::<overtime::Overtime>();
+    commands.remove_resource::<survival::Survival>();
+    commands.remove_resource::<tutorial::Tutorial>();
+    commands.remove_resource::<orb::OrbLayout>();
+    commands.remove_resource::<challenge::Challenge>();
 
     for entity in game_entity_query.iter() {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
     }
 }
 
+// The collisions are logged where they happened, the runner's position for a tag
+pub fn log_collisions(
+  match_time: Res<MatchTime>,
+  mut tag_events: EventReader<mode::TagEvent>,
+  mut orb_events: EventReader<mode::OrbEvent>,
+  mut match_events: EventWriter<MatchEvent>,
+  query: Query<(&player::Player, &Transform)>,
+) {
+    for event in tag_events.iter() {
+        if let (Ok((tagger, _)), Ok((runner, transform))) = (query.get(event.tagger), query.get(event.runner)) {
+            match_events.send(MatchEvent {
+                time: match_time.0,
+                kind: MatchEventKind::Tag { tagger: tagger.id, runner: runner.id, x: transform.translation.x, y: transform.translation.y },
+            });
+        }
+    }
 
-fn detect_collisions(
+    for event in orb_events.iter() {
+        if let Ok((player, transform)) = query.get(event.player) {
+            match_events.send(MatchEvent {
+                time: match_time.0,
+                kind: MatchEventKind::OrbTouch { player: player.id, x: transform.translation.x, y: transform.translation.y },
+            });
+        }
+    }
+}
+
+// Only a match that is entered starts a log, a resumed one is just passed through
+pub fn log_match_start(
+  settings: Res<mode::MatchSettings>,
+  mut match_events: EventWriter<MatchEvent>,
+) {
+    match_events.send(MatchEvent {
+        time: 0.,
+        kind: MatchEventKind::Start { mode: settings.mode, players: settings.players, overtime: settings.overtime },
+    });
+}
+
+pub fn log_match_end(
+  match_time: Option<Res<MatchTime>>,
+  winner: Option<Res<crate::Winner>>,
+  mut match_events: EventWriter<MatchEvent>,
+) {
+    match_events.send(MatchEvent {
+        time: match_time.map_or(0., |match_time| match_time.0),
+        kind: MatchEventKind::End { winner: winner.map(|winner| winner.0.clone()) },
+    });
+}
+
+// What a player or decoy can run into
+#[derive(SystemParam)]
+pub struct CollisionTargets<'w, 's> {
+    get_runner: Query<'w, 's, (Option<&'static player::Dash>, Option<&'static ability::Shield>), (With<player::Player>, Without<player::Tagger>, Without<player::Frozen>)>,
+    get_tagger: Query<'w, 's, (), (With<player::Player>, With<player::Tagger>)>,
+    get_bullet: Query<'w, 's, &'static bullet::Bullet, With<bullet::Bullet>>,
+    get_orb: Query<'w, 's, (), (With<orb::Orb>, Without<player::Player>)>,
+}
+
+// The sounds and events a collision sets off
+#[derive(SystemParam)]
+pub struct CollisionEffects<'w, 's> {
+    audio: Res<'w, Audio>,
+    orb_sound: Res<'w, crate::OrbSound>,
+    catch_sound: Res<'w, crate::CatchSound>,
+    tag_events: EventWriter<'w, 's, mode::TagEvent>,
+    orb_events: EventWriter<'w, 's, mode::OrbEvent>,
+    action_events: EventWriter<'w, 's, player::ActionEvent>,
+}
+
+pub fn detect_collisions(
   mut commands: Commands,
   mut events: EventReader<CollisionEvent>,
-  mut dash_timer: ResMut<player::DashTimer>,
-  mut cooldown_timer: ResMut<player::CooldownTimer>,
   mut get_player: Query<&mut player::Player>,
-  mut get_runner: Query<(&mut DrawMode, Option<&player::Dash>), (With<player::Player>, Without<player::Tagger>)>,
-  mut get_tagger: Query<&mut DrawMode, (With<player::Player>, With<player::Tagger>)>,
-  mut get_player1_text: Query<&mut Text, (With<player::Player1>, Without<player::Player2>)>,
-  mut get_player2_text: Query<&mut Text, (With<player::Player2>, Without<player::Player1>)>,
-  get_bullet: Query<&bullet::Bullet, With<bullet::Bullet>>,
-  get_orb: Query<(), (With<orb::Orb>, Without<player::Player>)>,
-  audio: Res<Audio>,
-  orb_sound: Res<crate::OrbSound>,
-  catch_sound: Res<crate::CatchSound>,
+  mut get_decoy: Query<(&mut ability::Decoy, &Transform)>,
+  mut get_trap: Query<(Option<&mut trap::SlowField>, Option<&trap::Barrier>), With<trap::Trap>>,
+  targets: CollisionTargets,
+  mut effects: CollisionEffects,
 ) {
   for event in events.iter() {
       if let CollisionEvent::Started(data1, data2) = event {
           let entity1 = data1.rigid_body_entity();
           let entity2 = data2.rigid_body_entity();
 
-          if let Ok(mut player) = get_player.get_mut(entity1) {
-              handle_player_collision(&mut commands, entity1, &mut player, entity2, &mut get_runner, &mut get_tagger, &mut get_player1_text, &mut get_player2_text, &get_bullet, &get_orb, &audio, &orb_sound, &catch_sound, &mut dash_timer, &mut cooldown_timer);
+          if let Ok((slow_field, barrier)) = get_trap.get_mut(entity1) {
+              trap::handle_trap_collision(&mut commands, slow_field, barrier, entity2, true, &targets.get_bullet);
+          }
+          else if let Ok((slow_field, barrier)) = get_trap.get_mut(entity2) {
+              trap::handle_trap_collision(&mut commands, slow_field, barrier, entity1, true, &targets.get_bullet);
+          }
+          else if let Ok(mut player) = get_player.get_mut(entity1) {
+              handle_player_collision(&mut commands, entity1, &mut player, entity2, &targets, &mut effects);
           }
           else if let Ok(mut player) = get_player.get_mut(entity2) {
-              handle_player_collision(&mut commands, entity2, &mut player, entity1, &mut get_runner, &mut get_tagger, &mut get_player1_text, &mut get_player2_text, &get_bullet, &get_orb, &audio, &orb_sound, &catch_sound, &mut dash_timer, &mut cooldown_timer);
+              handle_player_collision(&mut commands, entity2, &mut player, entity1, &targets, &mut effects);
+          }
+          else if let Ok((mut decoy, transform)) = get_decoy.get_mut(entity1) {
+              handle_decoy_collision(&mut commands, entity1, &mut decoy, transform, entity2, &targets, &effects);
+          }
+          else if let Ok((mut decoy, transform)) = get_decoy.get_mut(entity2) {
+              handle_decoy_collision(&mut commands, entity2, &mut decoy, transform, entity1, &targets, &effects);
           }
-          else if let Ok(_) = get_orb.get(entity1) {
+          // Only bullets are stopped by orbs, the sensors of the modes and the overtime walls stay
+          else if targets.get_orb.get(entity1).is_ok() && targets.get_bullet.get(entity2).is_ok() {
               commands.entity(entity2).despawn()
           }
-          else if let Ok(_) = get_bullet.get(entity1) {
+          else if let Ok(_) = targets.get_bullet.get(entity1) {
               commands.entity(entity1).despawn()
           }
       }
+      else if let CollisionEvent::Stopped(data1, data2) = event {
+          let entity1 = data1.rigid_body_entity();
+          let entity2 = data2.rigid_body_entity();
+
+          // Only slow fields care about something leaving them
+          if let Ok((slow_field, barrier)) = get_trap.get_mut(entity1) {
+              trap::handle_trap_collision(&mut commands, slow_field, barrier, entity2, false, &targets.get_bullet);
+          }
+          else if let Ok((slow_field, barrier)) = get_trap.get_mut(entity2) {
+              trap::handle_trap_collision(&mut commands, slow_field, barrier, entity1, false, &targets.get_bullet);
+          }
+      }
   }
 }
 
@@ -211,25 +724,20 @@ fn handle_player_collision(
   player_entity: Entity, 
   player: &mut player::Player,
   other_entity: Entity,
-  get_runner: &mut Query<(&mut DrawMode, Option<&player::Dash>), (With<player::Player>, Without<player::Tagger>)>,
-  get_tagger: &mut Query<&mut DrawMode, (With<player::Player>, With<player::Tagger>)>,
-  get_player1_text: &mut Query<&mut Text, (With<player::Player1>, Without<player::Player2>)>,
-  get_player2_text: &mut Query<&mut Text, (With<player::Player2>, Without<player::Player1>)>,
-  get_bullet: &Query<&bullet::Bullet, With<bullet::Bullet>>,
-  get_orb: &Query<(), (With<orb::Orb>, Without<player::Player>)>,
-  audio: &Res<Audio>,
-  orb_sound: &Res<crate::OrbSound>,
-  catch_sound: &Res<crate::CatchSound>,
-  dash_timer: &mut player::DashTimer,
-  cooldown_timer: &mut player::CooldownTimer,
+  targets: &CollisionTargets,
+  effects: &mut CollisionEffects,
 ) {
-  if let Ok((mut runner_draw_mode, is_dashing)) = get_runner.get_mut(player_entity) {
+  if let Ok((is_dashing, shield)) = targets.get_runner.get(player_entity) {
       if is_dashing.is_none() {
-          handle_runner_collision(commands, player_entity, player, other_entity, &mut runner_draw_mode, get_tagger, get_player1_text, get_player2_text, get_bullet, get_orb, audio, orb_sound, catch_sound, dash_timer, cooldown_timer);
+          handle_runner_collision(commands, player_entity, player, other_entity, shield, targets, effects);
+      }
+      // Dashing runners pass through bullets unharmed
+      else if targets.get_bullet.get(other_entity).is_ok() {
+          effects.action_events.send(player::ActionEvent { player: player_entity, kind: player::ActionKind::Dodge });
       }
   }
-  else if let Ok(_) = get_tagger.get_mut(player_entity) {
-      handle_tagger_collision(commands, player, other_entity, get_orb, audio, orb_sound);
+  else if let Ok(_) = targets.get_tagger.get(player_entity) {
+      handle_tagger_collision(commands, player_entity, player, other_entity, targets, effects);
   }
 }
 
@@ -238,65 +746,32 @@ fn handle_runner_collision(
   player_entity: Entity, 
   player: &mut player::Player,
   other_entity: Entity,
-  draw_mode: &mut DrawMode,
-  get_tagger: &mut Query<&mut DrawMode, (With<player::Player>, With<player::Tagger>)>,
-  get_player1_text: &mut Query<&mut Text, (With<player::Player1>, Without<player::Player2>)>,
-  get_player2_text: &mut Query<&mut Text, (With<player::Player2>, Without<player::Player1>)>,
-  get_bullet: &Query<&bullet::Bullet, With<bullet::Bullet>>,
-  get_orb: &Query<(), (With<orb::Orb>, Without<player::Player>)>,
-  audio: &Res<Audio>,
-  orb_sound: &Res<crate::OrbSound>,
-  catch_sound: &Res<crate::CatchSound>,
-  dash_timer: &mut player::DashTimer,
-  cooldown_timer: &mut player::CooldownTimer,
+  shield: Option<&ability::Shield>,
+  targets: &CollisionTargets,
+  effects: &mut CollisionEffects,
 ) {
-    if let Ok(bullet) = get_bullet.get(other_entity) {
-        if let DrawMode::Fill(ref mut runner_fill_mode) = *draw_mode {
-            if let Ok(mut tagger_draw_mode) = get_tagger.get_mut(bullet.shooter) {
-                if let DrawMode::Fill(ref mut tagger_fill_mode) = *tagger_draw_mode {
-                    commands.entity(player_entity).remove::<player::Runner>();
-                    commands.entity(player_entity).remove::<player::Dash>();
-                    commands.entity(player_entity).remove::<player::Cooldown>();
-                    commands.entity(player_entity).remove::<Animator<Transform>>();
-                    commands.entity(player_entity).remove::<Animator<DrawMode>>();
-                    commands.entity(player_entity).remove::<CollisionLayers>();
-                    commands.entity(player_entity).insert(player::Tagger);
-                    commands.entity(player_entity).insert(CollisionLayers::new(Layer::Tagger, Layer::Tagger));
-                    
-                    commands.entity(bullet.shooter).remove::<player::Tagger>();
-                    commands.entity(bullet.shooter).remove::<CollisionLayers>();
-                    commands.entity(bullet.shooter).insert(player::Runner);
-                    commands.entity(bullet.shooter).insert(CollisionLayers::new(Layer::Runner, Layer::Runner));
-
-                    runner_fill_mode.color = TAGGER_COL;
-                    tagger_fill_mode.color = RUNNER_COL;
-                    
-                    let (mut player1_text, mut player2_text) = (get_player1_text.single_mut(), get_player2_text.single_mut());
-
-                    if player1_text.sections.first_mut().unwrap().style.color == TAGGER_COL {
-                        player1_text.sections.first_mut().unwrap().style.color = RUNNER_COL;
-                    } else {
-                        player1_text.sections.first_mut().unwrap().style.color = TAGGER_COL;
-                    }
-
-                    if player2_text.sections.first_mut().unwrap().style.color == TAGGER_COL {
-                        player2_text.sections.first_mut().unwrap().style.color = RUNNER_COL;
-                    } else {
-                        player2_text.sections.first_mut().unwrap().style.color = TAGGER_COL;
-                    }
-            
-                    audio.play(catch_sound.0.clone());
-
-                    dash_timer.0.reset();
-                    cooldown_timer.0.reset();
-            
-                    commands.entity(other_entity).despawn();
-                }
-            }
+    if let (Ok(_), Some(shield)) = (targets.get_bullet.get(other_entity), shield) {
+        effects.audio.play(effects.orb_sound.0.clone());
+
+        ability::break_shield(commands, player_entity, shield);
+        commands.entity(other_entity).despawn();
+    }
+    else if let Ok(bullet) = targets.get_bullet.get(other_entity) {
+        // Bullets of a player who is no longer tagging don't count
+        if targets.get_tagger.get(bullet.shooter).is_ok() {
+            effects.audio.play(effects.catch_sound.0.clone());
+
+            effects.tag_events.send(mode::TagEvent {
+                runner: player_entity,
+                tagger: bullet.shooter,
+            });
+
+            commands.entity(other_entity).despawn();
         }
     }
-    else if let Ok(_) = get_orb.get(other_entity) {
-        audio.play(orb_sound.0.clone());
+    else if let Ok(_) = targets.get_orb.get(other_entity) {
+        effects.audio.play(effects.orb_sound.0.clone());
+        effects.orb_events.send(mode::OrbEvent { player: player_entity });
 
         player.velocity = player.velocity.signum() * -1. * player::MAX_SPEED;
         commands.entity(other_entity).despawn();
@@ -305,58 +780,74 @@ fn handle_runner_collision(
 
 fn handle_tagger_collision(
   commands: &mut Commands,
+  player_entity: Entity,
   player: &mut player::Player,
   other_entity: Entity,
-  get_orb: &Query<(), (With<orb::Orb>, Without<player::Player>)>,
-  audio: &Res<Audio>,
-  orb_sound: &Res<crate::OrbSound>,
+  targets: &CollisionTargets,
+  effects: &mut CollisionEffects,
 ) {
-  if let Ok(_) = get_orb.get(other_entity) {
-      audio.play(orb_sound.0.clone());
+  if let Ok(_) = targets.get_orb.get(other_entity) {
+      effects.audio.play(effects.orb_sound.0.clone());
+      effects.orb_events.send(mode::OrbEvent { player: player_entity });
 
       player.velocity = player.velocity.signum() * -1. * player::MAX_SPEED;
       commands.entity(other_entity).despawn();
   }
 }
 
+// Decoys react to bullets and orbs the same way runners do, so they can't be told apart
+fn handle_decoy_collision(
+  commands: &mut Commands,
+  decoy_entity: Entity,
+  decoy: &mut ability::Decoy,
+  transform: &Transform,
+  other_entity: Entity,
+  targets: &CollisionTargets,
+  effects: &CollisionEffects,
+) {
+  if targets.get_bullet.get(other_entity).is_ok() {
+      effects.audio.play(effects.orb_sound.0.clone());
+
+      ability::pop_decoy(commands, decoy_entity, transform);
+      commands.entity(other_entity).despawn();
+  }
+  else if targets.get_orb.get(other_entity).is_ok() {
+      effects.audio.play(effects.orb_sound.0.clone());
+
+      decoy.velocity = decoy.velocity.signum() * -1. * player::MAX_SPEED;
+      commands.entity(other_entity).despawn();
+  }
+}
+
 fn tick_count_down_timer(
-    mut commands: Commands,
     time: Res<Time>,
     timer_sound: Res<crate::TimerSound>,
     audio: Res<Audio>,
-    mut app_state: ResMut<State<GameState>>,
+    settings: Res<mode::MatchSettings>,
     mut timer: ResMut<CountDownTimer>,
+    mut match_time: ResMut<MatchTime>,
     mut text_query: Query<&mut Text, With<Countdown>>,
-    player1_query: Query<(), (With<player::Player1>, With<player::Tagger>)>,
 ) { 
     timer.0.tick(time.delta());
+    match_time.0 += time.delta_seconds();
 
-    let elapsed = timer.0.elapsed_secs();
-    let remaining_time = GAME_DUR - elapsed;
-    let remaining_miutes = (remaining_time / 60.).floor();
-    let remaining_seconds = (remaining_time - remaining_miutes * 60.).floor();
+    let has_time_limit = settings.mode.has_time_limit();
+    let shown_time = if has_time_limit {timer.0.duration().as_secs_f32() - timer.0.elapsed_secs()} else {match_time.0};
+    let shown_miutes = (shown_time / 60.).floor();
+    let shown_seconds = (shown_time - shown_miutes * 60.).floor();
 
-    let mut text = text_query.single_mut();
-    let text = text.sections.first_mut().unwrap();
+    for mut text in text_query.iter_mut() {
+        let text = text.sections.first_mut().unwrap();
 
-    let prev_value = text.value.clone();
-    text.value = format!("{:0>2}:{:0>2}", remaining_miutes, remaining_seconds);
+        let prev_value = text.value.clone();
+        text.value = format!("{:0>2}:{:0>2}", shown_miutes, shown_seconds);
 
-    if remaining_time <= 16. {
-        if prev_value != text.value {
-            audio.play(timer_sound.0.clone());
-        }
-
-        text.style.color = Color::RED;
-    }
+        if has_time_limit && shown_time <= 16. {
+            if prev_value != text.value {
+                audio.play(timer_sound.0.clone());
+            }
 
-    if timer.0.just_finished() {
-        if player1_query.is_empty() {
-            commands.insert_resource(crate::Winner("Player_1".to_string()));
-        } else {
-            commands.insert_resource(crate::Winner("Player_2".to_string()));
+            text.style.color = Color::RED;
         }
-        
-        app_state.push(GameState::MainMenu).unwrap();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,296 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, core::CoreSystem};
+use heron::PhysicsSteps;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{GameState, MatchPlugins, Random, Winner, agent, headless, mode, player};
+use crate::ability::Ability;
+
+pub const TAG_REWARD: f32 = 1.;
+pub const ORB_REWARD: f32 = 0.2;
+// Per second a runner stays free
+pub const SURVIVAL_REWARD: f32 = 0.1;
+// A reset that takes longer didn't start the match
+pub const MAX_RESET_FRAMES: usize = 10;
+
+// What happened in a step besides the rewards, by player id
+#[derive(Debug, Clone, Copy)]
+pub enum StepEvent {
+    Tag { tagger: usize, runner: usize },
+    Orb { player: usize },
+    Ability { player: usize, ability: Ability },
+}
+
+// What the last frame of the match looked like, kept since a finished match is torn down in the same frame
+#[derive(Default)]
+pub struct GymStep {
+    pub tick: u64,
+    pub observation: Option<agent::Observation>,
+    // By player id
+    pub rewards: Vec<f32>,
+    pub events: Vec<StepEvent>,
+}
+
+pub struct GymPlugin;
+
+impl Plugin for GymPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GymStep>()
+            .insert_resource(agent::LockstepTime::measure(Duration::from_secs_f32(agent::LOCKSTEP_STEP)))
+            .insert_resource(PhysicsSteps::every_frame(Duration::from_secs_f32(agent::LOCKSTEP_STEP)))
+            .add_system_set(
+                SystemSet::on_update(GameState::Game)
+                    .with_system(collect_step.after("collisions").after("countdown")),
+            )
+            .add_system_to_stage(CoreStage::First, fix_gym_time.after(CoreSystem::Time));
+    }
+}
+
+// Every frame takes the same time, also the one entering the match
+pub fn fix_gym_time(mut time: ResMut<Time>, lockstep_time: Res<agent::LockstepTime>) {
+    *time = lockstep_time.0.clone();
+}
+
+pub fn collect_step(
+    mut step: ResMut<GymStep>,
+    time: Res<Time>,
+    settings: Res<mode::MatchSettings>,
+    mut tag_events: EventReader<mode::TagEvent>,
+    mut orb_events: EventReader<mode::OrbEvent>,
+    mut action_events: EventReader<player::ActionEvent>,
+    player_query: Query<(&player::Player, Option<&player::Tagger>, Option<&player::Frozen>)>,
+    observer: agent::MatchObserver,
+) {
+    step.tick += 1;
+    step.rewards.resize(settings.players, 0.);
+
+    let id = |entity: Entity| player_query.get(entity).ok()
+        .map(|(player, ..)| player.id)
+        .filter(|id| *id < settings.players);
+
+    for event in tag_events.iter() {
+        if let (Some(tagger), Some(runner)) = (id(event.tagger), id(event.runner)) {
+            step.rewards[tagger] += TAG_REWARD;
+            step.rewards[runner] -= TAG_REWARD;
+            step.events.push(StepEvent::Tag { tagger, runner });
+        }
+    }
+
+    for event in orb_events.iter() {
+        if let Some(player) = id(event.player) {
+            step.rewards[player] += ORB_REWARD;
+            step.events.push(StepEvent::Orb { player });
+        }
+    }
+
+    for event in action_events.iter() {
+        if let (Some(player), player::ActionKind::Ability(ability)) = (id(event.player), event.kind) {
+            step.events.push(StepEvent::Ability { player, ability });
+        }
+    }
+
+    for (player, tagger, frozen) in player_query.iter() {
+        if tagger.is_none() && frozen.is_none() {
+            if let Some(reward) = step.rewards.get_mut(player.id) {
+                *reward += SURVIVAL_REWARD * time.delta_seconds();
+            }
+        }
+    }
+
+    step.observation = Some(observer.observe(step.tick));
+}
+
+// The real match in a headless app, stepped one frame at a time by a training loop
+pub struct Env {
+    app: App,
+    events: Vec<StepEvent>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        let mut app = App::new();
+
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(headless::HeadlessPlugin)
+            .add_plugins(MatchPlugins)
+            .add_plugin(GymPlugin)
+            .add_state(GameState::MainMenu);
+
+        // Runs the startup systems
+        app.update();
+
+        Self { app, events: Vec::new() }
+    }
+
+    pub fn is_done(&self) -> bool {
+        *self.app.world.get_resource::<State<GameState>>().unwrap().current() != GameState::Game
+    }
+
+    // Only set once the match is done
+    pub fn winner(&self) -> Option<String> {
+        self.app.world.get_resource::<Winner>().map(|winner| winner.0.clone())
+    }
+
+    pub fn events(&self) -> &[StepEvent] {
+        &self.events
+    }
+
+    // Starts a new match, ending the one still running
+    pub fn reset(&mut self, seed: u64, rules: &mode::MatchRules) -> Result<agent::Observation, String> {
+        if rules.players > player::MAX_PLAYERS || !rules.mode.allows_players(rules.players) {
+            return Err(format!("{} can't be played by {} players", rules.mode.name(), rules.players));
+        }
+
+        if !self.is_done() {
+            self.push_state(GameState::MainMenu)?;
+            self.app.update();
+        }
+
+        {
+            let mut settings = self.app.world.get_resource_mut::<mode::MatchSettings>().unwrap();
+            settings.mode = rules.mode;
+            settings.players = rules.players;
+            settings.overtime = rules.overtime;
+        }
+
+        self.app.world.get_resource_mut::<Random>().unwrap().0 = StdRng::seed_from_u64(seed);
+        self.app.world.remove_resource::<Winner>();
+        *self.app.world.get_resource_mut::<GymStep>().unwrap() = GymStep::default();
+
+        // Like every match, the menu stays below it on the stack
+        self.push_state(GameState::Game)?;
+
+        for _ in 0..MAX_RESET_FRAMES {
+            self.app.update();
+
+            if self.app.world.get_resource::<GymStep>().unwrap().observation.is_some() {
+                break;
+            }
+        }
+
+        // The players only listen to the actions from now on
+        let players: Vec<Entity> = self.app.world
+            .query_filtered::<Entity, With<player::Keys>>()
+            .iter(&self.app.world)
+            .collect();
+
+        for entity in players {
+            self.app.world.entity_mut(entity).remove::<player::Keys>();
+        }
+
+        let (observation, _) = self.take_step()?;
+        Ok(observation)
+    }
+
+    // The actions are by player id, missing ones do nothing
+    pub fn step(&mut self, actions: &[player::Intent]) -> Result<(agent::Observation, Vec<f32>, bool), String> {
+        if !self.is_done() {
+            let mut query = self.app.world.query::<(&player::Player, &mut player::Intent)>();
+
+            for (player, mut intent) in query.iter_mut(&mut self.app.world) {
+                if player.id < player::MAX_PLAYERS {
+                    intent.update(actions.get(player.id).copied().unwrap_or_default());
+                }
+            }
+
+            self.app.update();
+        }
+
+        let (observation, rewards) = self.take_step()?;
+        Ok((observation, rewards, self.is_done()))
+    }
+
+    fn push_state(&mut self, state: GameState) -> Result<(), String> {
+        self.app.world.get_resource_mut::<State<GameState>>().unwrap()
+            .push(state)
+            .map_err(|err| err.to_string())
+    }
+
+    // Before the first step of a match there is nothing to observe, e.g. if a mode failed to set it up
+    fn take_step(&mut self) -> Result<(agent::Observation, Vec<f32>), String> {
+        let mut step = self.app.world.get_resource_mut::<GymStep>().unwrap();
+        let observation = step.observation.clone().ok_or("The match didn't start")?;
+        let rewards = step.rewards.iter_mut().map(std::mem::take).collect();
+        self.events = std::mem::take(&mut step.events);
+
+        Ok((observation, rewards))
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEPS: usize = 120;
+
+    // The tagger chases right and up, the runner flees left, with a dash now and then
+    fn scripted_actions(frame: usize) -> Vec<player::Intent> {
+        let mut tagger = player::Intent::default();
+        tagger.right = true;
+        tagger.up = frame % 40 < 20;
+        tagger.action = frame % 30 == 5;
+
+        let mut runner = player::Intent::default();
+        runner.left = true;
+        runner.down = frame % 50 < 10;
+
+        vec![tagger, runner]
+    }
+
+    fn play(seed: u64) -> Vec<String> {
+        let rules = mode::MatchRules { mode: mode::GameMode::Classic, players: 2, overtime: false };
+        let mut env = Env::new();
+        let mut frames = vec![serde_json::to_string(&env.reset(seed, &rules).unwrap()).unwrap()];
+
+        for frame in 0..STEPS {
+            let (observation, rewards, done) = env.step(&scripted_actions(frame)).unwrap();
+            frames.push(format!("{} {:?}", serde_json::to_string(&observation).unwrap(), rewards));
+
+            if done {
+                break;
+            }
+        }
+
+        frames
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_match() {
+        let first = play(7);
+        let second = play(7);
+
+        assert_eq!(first.len(), second.len());
+        for (frame, (first, second)) in first.iter().zip(&second).enumerate() {
+            assert_eq!(first, second, "The matches differ at frame {}", frame);
+        }
+    }
+
+    #[test]
+    fn reset_starts_over_with_the_same_seed() {
+        let rules = mode::MatchRules { mode: mode::GameMode::Classic, players: 2, overtime: false };
+        let mut env = Env::new();
+        let first = serde_json::to_string(&env.reset(3, &rules).unwrap()).unwrap();
+
+        for frame in 0..30 {
+            env.step(&scripted_actions(frame)).unwrap();
+        }
+
+        let second = serde_json::to_string(&env.reset(3, &rules).unwrap()).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn reset_rejects_a_mode_the_players_cant_play() {
+        let rules = mode::MatchRules { mode: mode::GameMode::Teams, players: 3, overtime: false };
+        let mut env = Env::new();
+
+        assert!(env.reset(3, &rules).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use bevy::{prelude::*, asset::AssetPlugin, input::InputPlugin, transform::TransformPlugin};
+use bevy_kira_audio::Audio;
+use heron::prelude::*;
+use bevy_tweening::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{ability, CatchSound, FontHandle, OrbSound, Random, TimerSound};
+
+// What a match needs without a window, a renderer or speakers, added next to MinimalPlugins
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Random(StdRng::from_entropy()))
+            .init_resource::<ability::Loadouts>()
+            .init_resource::<Audio>()
+            .add_plugin(TransformPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(AssetPlugin)
+            .add_asset::<ColorMaterial>()
+            .add_plugin(TweeningPlugin)
+            .add_plugin(PhysicsPlugin::default())
+            .add_startup_system(insert_default_handles)
+            .add_system_to_stage(CoreStage::PostUpdate, drop_sounds);
+    }
+}
+
+// Nothing is drawn or played, the handles only have to exist
+pub fn insert_default_handles(mut commands: Commands) {
+    commands.insert_resource(OrbSound(Handle::default()));
+    commands.insert_resource(CatchSound(Handle::default()));
+    commands.insert_resource(TimerSound(Handle::default()));
+    commands.insert_resource(FontHandle(Handle::default()));
+    commands.insert_resource(ability::AbilitySounds(
+        ability::Ability::all().map(|ability| (ability, Handle::default())).collect(),
+    ));
+}
+
+// Without the audio plugin nobody plays the queued sounds
+pub fn drop_sounds(mut audio: ResMut<Audio>) {
+    *audio = Audio::default();
+}
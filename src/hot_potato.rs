@@ -0,0 +1,230 @@
+use bevy::{prelude::*, utils::Duration};
+use bevy_kira_audio::Audio;
+use bevy_prototype_lyon::prelude::*;
+use rand::Rng;
+
+use crate::{GameState, game, player, ability};
+use crate::mode::{self, GameMode, TagEvent};
+
+pub const FUSE_DUR: f32 = 15.;
+pub const START_LIVES: u32 = 3;
+pub const FUSE_BAR_WIDTH: f32 = 40.;
+pub const FUSE_BAR_HEIGHT: f32 = 5.;
+pub const FUSE_COL: Color = Color::rgb(1., 120./255., 50./255.);
+
+// The fuse burns while its holder is the tagger, ticking faster as it runs down
+#[derive(Component, Clone)]
+pub struct Fuse {
+    pub timer: Timer,
+    tick: Timer,
+}
+
+impl Fuse {
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(seconds, false),
+            tick: Timer::from_seconds(1., false),
+        }
+    }
+
+    pub fn remaining(&self) -> f32 {
+        1. - self.timer.percent()
+    }
+}
+
+#[derive(Component)]
+pub struct Lives(pub u32);
+
+#[derive(Component)]
+pub struct FuseBar;
+
+pub struct HotPotatoPlugin;
+
+impl Plugin for HotPotatoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::HotPotato))
+                .with_system(setup_hot_potato)
+                .with_system(move_fuse_bar.after("move"))
+                .with_system(update_lives_labels.after("collisions")),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::HotPotato))
+                .with_system(pass_fuse.label("resolve").after("collisions"))
+                .with_system(burn_fuse.label("burn").after("resolve"))
+                // The last holder burning out wins over the time running out in the same frame,
+                // end_match ignores the second end once the first one is queued
+                .with_system(end_hot_potato_match.after("countdown").after("burn")),
+        );
+    }
+}
+
+// Hands out the lives and the first fuse once the players of the match are spawned
+pub fn setup_hot_potato(
+    mut commands: Commands,
+    query: Query<(Entity, Option<&player::Tagger>), Added<player::Player>>,
+) {
+    if query.is_empty() {
+        return;
+    }
+
+    for (entity, is_tagger) in query.iter() {
+        commands.entity(entity).insert(Lives(START_LIVES));
+
+        if is_tagger.is_some() {
+            commands.entity(entity).insert(Fuse::new(FUSE_DUR));
+        }
+    }
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::Rectangle {
+            extents: Vec2::new(FUSE_BAR_WIDTH, FUSE_BAR_HEIGHT),
+            origin: shapes::RectangleOrigin::BottomLeft,
+        },
+        DrawMode::Fill(FillMode::color(FUSE_COL)),
+        Transform::from_xyz(0., 0., 2.),
+    ))
+    .insert(FuseBar)
+    .insert(game::GameEntity);
+}
+
+// A tag swaps the roles like in the classic mode and the burning fuse goes with the tagger role
+pub fn pass_fuse(
+    mut commands: Commands,
+    mut events: EventReader<TagEvent>,
+    query: Query<&Fuse>,
+) {
+    let mut swapped: Vec<Entity> = Vec::new();
+
+    for event in events.iter() {
+        if swapped.contains(&event.runner) || swapped.contains(&event.tagger) {
+            continue;
+        }
+
+        player::become_tagger(&mut commands, event.runner);
+        player::become_runner(&mut commands, event.tagger);
+
+        if let Ok(fuse) = query.get(event.tagger) {
+            commands.entity(event.tagger).remove::<Fuse>();
+            commands.entity(event.runner).insert(fuse.clone());
+        }
+
+        swapped.push(event.runner);
+        swapped.push(event.tagger);
+    }
+}
+
+pub fn burn_fuse(
+    mut commands: Commands,
+    time: Res<Time>,
+    audio: Res<Audio>,
+    timer_sound: Res<crate::TimerSound>,
+    catch_sound: Res<crate::CatchSound>,
+    mut random: ResMut<crate::Random>,
+    mut app_state: ResMut<State<GameState>>,
+    mut holder_query: Query<(Entity, &mut Fuse, &mut Lives, &Transform)>,
+    player_query: Query<(Entity, &player::Player), Without<Fuse>>,
+) {
+    for (entity, mut fuse, mut lives, transform) in holder_query.iter_mut() {
+        fuse.timer.tick(time.delta());
+        fuse.tick.tick(time.delta());
+
+        if fuse.tick.just_finished() {
+            audio.play(timer_sound.0.clone());
+
+            let interval = fuse.remaining().max(0.1);
+            fuse.tick.set_duration(Duration::from_secs_f32(interval));
+            fuse.tick.reset();
+        }
+
+        if !fuse.timer.just_finished() {
+            continue;
+        }
+
+        audio.play(catch_sound.0.clone());
+        ability::spawn_burst(&mut commands, transform.translation, FUSE_COL);
+
+        lives.0 -= 1;
+
+        if lives.0 > 0 {
+            *fuse = Fuse::new(FUSE_DUR);
+            continue;
+        }
+
+        // Out of lives, the fuse goes to a random player who is still in the game
+        commands.entity(entity).despawn_recursive();
+
+        let remaining: Vec<(Entity, &player::Player)> = player_query.iter().collect();
+
+        if remaining.len() == 1 {
+            game::end_match(&mut commands, &mut app_state, remaining[0].1.name());
+        }
+        else if !remaining.is_empty() {
+            let (next_holder, _) = remaining[random.0.gen_range(0..remaining.len())];
+
+            player::become_tagger(&mut commands, next_holder);
+            commands.entity(next_holder).insert(Fuse::new(FUSE_DUR));
+        }
+    }
+}
+
+// The fuse bar floats above the holder and shrinks as the fuse burns
+pub fn move_fuse_bar(
+    holder_query: Query<(&Fuse, &Transform), Without<FuseBar>>,
+    mut bar_query: Query<(&mut Transform, &mut Visibility), With<FuseBar>>,
+) {
+    for (mut bar_transform, mut visibility) in bar_query.iter_mut() {
+        if let Some((fuse, transform)) = holder_query.iter().next() {
+            bar_transform.translation.x = transform.translation.x - FUSE_BAR_WIDTH / 2.;
+            bar_transform.translation.y = transform.translation.y + 25.;
+            bar_transform.scale.x = fuse.remaining();
+            visibility.is_visible = true;
+        } else {
+            visibility.is_visible = false;
+        }
+    }
+}
+
+pub fn update_lives_labels(
+    player_query: Query<(&player::Player, &Lives)>,
+    mut label_query: Query<(&game::PlayerLabel, &mut Text)>,
+) {
+    for (label, mut text) in label_query.iter_mut() {
+        let lives = player_query.iter()
+            .find(|(player, _)| player.id == label.0)
+            .map_or(0, |(_, lives)| lives.0);
+
+        let value = format!("{} ({})", player::player_name(label.0), lives);
+
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+// If the time is up the players with the most lives left win, unless the fuse already ended the match
+pub fn end_hot_potato_match(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    timer: Res<game::CountDownTimer>,
+    query: Query<(&player::Player, &Lives)>,
+) {
+    if timer.0.just_finished() {
+        let most_lives = query.iter().map(|(_, lives)| lives.0).max().unwrap_or(0);
+
+        let mut winners: Vec<&player::Player> = query.iter()
+            .filter(|(_, lives)| lives.0 == most_lives)
+            .map(|(player, _)| player)
+            .collect();
+        winners.sort_by_key(|player| player.id);
+
+        let winner = winners.iter()
+            .map(|player| player.name())
+            .collect::<Vec<String>>()
+            .join(" & ");
+
+        game::end_match(&mut commands, &mut app_state, winner);
+    }
+}
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use crate::{GameState, game, player};
+use crate::mode::{self, GameMode, TagEvent};
+
+pub struct InfectionPlugin;
+
+impl Plugin for InfectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::Infection))
+                .with_system(infect_runners.label("resolve").after("collisions"))
+                .with_system(end_infection_match.after("collisions").after("countdown")),
+        );
+    }
+}
+
+// Tagged runners join the taggers instead of trading roles with them
+pub fn infect_runners(
+    mut commands: Commands,
+    mut events: EventReader<TagEvent>,
+    query: Query<(), With<player::Runner>>,
+) {
+    let mut infected: Vec<Entity> = Vec::new();
+
+    for event in events.iter() {
+        if query.get(event.runner).is_ok() && !infected.contains(&event.runner) {
+            player::become_tagger(&mut commands, event.runner);
+            infected.push(event.runner);
+        }
+    }
+}
+
+// The last runner standing wins, if the time is up every runner who is still free wins
+pub fn end_infection_match(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    mut events: EventReader<TagEvent>,
+    timer: Res<game::CountDownTimer>,
+    runner_query: Query<(Entity, &player::Player), With<player::Runner>>,
+) {
+    let mut infected: Vec<Entity> = Vec::new();
+
+    for event in events.iter() {
+        if runner_query.get(event.runner).is_ok() && !infected.contains(&event.runner) {
+            infected.push(event.runner);
+        }
+    }
+
+    let mut survivors: Vec<&player::Player> = runner_query.iter()
+        .filter(|(entity, _)| !infected.contains(entity))
+        .map(|(_, player)| player)
+        .collect();
+
+    if survivors.is_empty() {
+        if let Some(last_runner) = infected.last() {
+            let (_, player) = runner_query.get(*last_runner).unwrap();
+            game::end_match(&mut commands, &mut app_state, player.name());
+        }
+    }
+    else if timer.0.just_finished() {
+        survivors.sort_by_key(|player| player.id);
+
+        let winner = survivors.iter()
+            .map(|player| player.name())
+            .collect::<Vec<String>>()
+            .join(" & ");
+
+        game::end_match(&mut commands, &mut app_state, winner);
+    }
+}
@@ -0,0 +1,235 @@
+use bevy::{prelude::*, utils::Duration};
+use bevy_prototype_lyon::prelude::*;
+use bevy_tweening::*;
+use heron::prelude::*;
+
+use crate::{GameState, game, player, orb};
+use crate::mode::{self, GameMode};
+
+pub const ZONE_RADIUS: f32 = 70.;
+pub const ZONE_MOVE_DUR: f32 = 12.;
+pub const ZONE_POINTS_PER_SEC: f32 = 1.;
+pub const ZONE_COL: Color = Color::rgb(1., 214./255., 80./255.);
+
+#[derive(Component)]
+pub struct CaptureZone {
+    inside: Vec<Entity>,
+}
+
+#[derive(Component)]
+pub struct Score(pub f32);
+
+pub struct ZoneTimer(pub Timer);
+
+pub struct KingOfTheHillPlugin;
+
+impl Plugin for KingOfTheHillPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::KingOfTheHill))
+                .with_system(setup_king_of_the_hill)
+                .with_system(track_zone_collisions.after("collisions"))
+                .with_system(relocate_zone)
+                .with_system(score_in_zone.after("resolve"))
+                .with_system(update_score_labels.after("collisions")),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::KingOfTheHill))
+                .with_system(mode::swap_roles.label("resolve").after("collisions"))
+                .with_system(end_king_of_the_hill_match.after("countdown")),
+        );
+    }
+}
+
+// Everyone starts without points once the players of the match are spawned,
+// the zone comes once the orbs are there so it can keep away from them
+pub fn setup_king_of_the_hill(
+    mut commands: Commands,
+    mut random: ResMut<crate::Random>,
+    arena: Res<game::Arena>,
+    query: Query<Entity, Added<player::Player>>,
+    zone_query: Query<(), With<CaptureZone>>,
+    orb_query: Query<&Transform, With<orb::Orb>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(Score(0.));
+    }
+
+    if !zone_query.is_empty() || orb_query.is_empty() {
+        return;
+    }
+
+    commands.insert_resource(ZoneTimer(Timer::from_seconds(ZONE_MOVE_DUR, true)));
+
+    let taken: Vec<Vec3> = orb_query.iter().map(|transform| transform.translation).collect();
+    spawn_zone(&mut commands, &mut random, &arena, &taken);
+}
+
+pub fn spawn_zone(
+    commands: &mut Commands,
+    random: &mut crate::Random,
+    arena: &game::Arena,
+    taken: &[Vec3],
+) {
+    let pos = orb::free_position(random, arena, ZONE_RADIUS, taken, ZONE_RADIUS + orb::ORB_RADIUS);
+    let mut fill_color = ZONE_COL;
+    fill_color.set_a(0.15);
+
+    let tween = Tween::new(
+        EaseFunction::QuadraticOut,
+        TweeningType::Once,
+        Duration::from_millis(300),
+        lens::TransformScaleLens {
+            start: Vec3::new(0., 0., 1.),
+            end: Vec3::new(1., 1., 1.),
+        },
+    );
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::Circle {
+            radius: ZONE_RADIUS,
+            center: Vec2::new(0., 0.),
+        },
+        DrawMode::Outlined {
+            outline_mode: StrokeMode::new(ZONE_COL, 3.),
+            fill_mode: FillMode::color(fill_color),
+        },
+        Transform {
+            translation: Vec3::new(pos.x, pos.y, 0.),
+            scale: Vec3::new(0., 0., 1.),
+            ..Default::default()
+        },
+    ))
+    .insert(Animator::new(tween))
+    .insert(RigidBody::Sensor)
+    .insert(CollisionShape::Sphere{radius: ZONE_RADIUS})
+    .insert(CollisionLayers::new(game::Layer::Zone, game::Layer::Runner).with_mask(game::Layer::Tagger))
+    .insert(CaptureZone { inside: Vec::new() })
+    .insert(game::GameEntity);
+}
+
+pub fn track_zone_collisions(
+    mut events: EventReader<CollisionEvent>,
+    mut zone_query: Query<&mut CaptureZone>,
+    player_query: Query<(), With<player::Player>>,
+) {
+    for event in events.iter() {
+        let (data1, data2, started) = match event {
+            CollisionEvent::Started(data1, data2) => (data1, data2, true),
+            CollisionEvent::Stopped(data1, data2) => (data1, data2, false),
+        };
+
+        let entity1 = data1.rigid_body_entity();
+        let entity2 = data2.rigid_body_entity();
+
+        let (mut zone, player) = if let (Ok(zone), Ok(_)) = (zone_query.get_mut(entity1), player_query.get(entity2)) {
+            (zone, entity2)
+        }
+        else if let (Ok(zone), Ok(_)) = (zone_query.get_mut(entity2), player_query.get(entity1)) {
+            (zone, entity1)
+        }
+        else {
+            continue;
+        };
+
+        if started {
+            zone.inside.push(player);
+        } else {
+            zone.inside.retain(|entity| *entity != player);
+        }
+    }
+}
+
+// Every so often the zone jumps to a new place that is clear of the orbs
+pub fn relocate_zone(
+    mut commands: Commands,
+    mut random: ResMut<crate::Random>,
+    time: Res<Time>,
+    arena: Res<game::Arena>,
+    timer: Option<ResMut<ZoneTimer>>,
+    zone_query: Query<(Entity, &Transform), With<CaptureZone>>,
+    orb_query: Query<&Transform, With<orb::Orb>>,
+) {
+    let mut timer = match timer {
+        Some(timer) => timer,
+        None => return,
+    };
+
+    // The timer of the last match is still around until the new zone is placed
+    if zone_query.is_empty() {
+        return;
+    }
+
+    timer.0.tick(time.delta());
+
+    if timer.0.just_finished() {
+        let mut taken: Vec<Vec3> = orb_query.iter().map(|transform| transform.translation).collect();
+
+        // The old place counts as taken too, so the zone really moves
+        for (entity, transform) in zone_query.iter() {
+            taken.push(transform.translation);
+            commands.entity(entity).despawn();
+        }
+
+        spawn_zone(&mut commands, &mut random, &arena, &taken);
+    }
+}
+
+// Only runners score, taggers in the zone are there to chase them out
+pub fn score_in_zone(
+    time: Res<Time>,
+    zone_query: Query<&CaptureZone>,
+    mut runner_query: Query<&mut Score, With<player::Runner>>,
+) {
+    for zone in zone_query.iter() {
+        for entity in zone.inside.iter() {
+            if let Ok(mut score) = runner_query.get_mut(*entity) {
+                score.0 += ZONE_POINTS_PER_SEC * time.delta_seconds();
+            }
+        }
+    }
+}
+
+pub fn update_score_labels(
+    player_query: Query<(&player::Player, &Score)>,
+    mut label_query: Query<(&game::PlayerLabel, &mut Text)>,
+) {
+    for (label, mut text) in label_query.iter_mut() {
+        let score = player_query.iter()
+            .find(|(player, _)| player.id == label.0)
+            .map_or(0., |(_, score)| score.0);
+
+        let value = format!("{} {}", player::player_name(label.0), score.floor());
+
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+// If the time is up the players with the most points win
+pub fn end_king_of_the_hill_match(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    timer: Res<game::CountDownTimer>,
+    query: Query<(&player::Player, &Score)>,
+) {
+    if timer.0.just_finished() {
+        let best_score = query.iter().map(|(_, score)| score.0.floor() as u32).max().unwrap_or(0);
+
+        let mut winners: Vec<&player::Player> = query.iter()
+            .filter(|(_, score)| score.0.floor() as u32 == best_score)
+            .map(|(player, _)| player)
+            .collect();
+        winners.sort_by_key(|player| player.id);
+
+        let winner = winners.iter()
+            .map(|player| player.name())
+            .collect::<Vec<String>>()
+            .join(" & ");
+
+        game::end_match(&mut commands, &mut app_state, winner);
+    }
+}
@@ -0,0 +1,331 @@
+use std::{collections::HashMap, net::SocketAddr};
+
+use bevy::prelude::*;
+use crate::{GameState, game, lobby, net, player};
+
+pub const HOST_TIMEOUT: f64 = 3.;
+
+#[derive(Component)]
+pub struct LanMenu;
+
+#[derive(Component)]
+pub struct HostList;
+
+#[derive(Component)]
+pub struct HostButton(SocketAddr);
+
+#[derive(Component)]
+pub struct LobbyButton;
+
+#[derive(Component)]
+pub struct BackButton;
+
+// The hosts heard from lately, sorted by their address
+#[derive(Default)]
+pub struct DiscoveredHosts(pub Vec<(SocketAddr, net::Announcement)>);
+
+pub struct LanMenuPlugin;
+
+impl Plugin for LanMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DiscoveredHosts>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::LanMenu)
+                    .with_system(setup_lan_menu),
+            )
+            .add_system_set(
+                SystemSet::on_resume(GameState::LanMenu)
+                    .with_system(setup_lan_menu),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::LanMenu)
+                    .with_system(discover_hosts.label("discover"))
+                    .with_system(update_host_list.after("discover"))
+                    .with_system(handle_lan_menu_interactions),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::LanMenu)
+                    .with_system(teardown_lan_menu_items),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::LanMenu)
+                    .with_system(teardown_lan_menu_items),
+            );
+    }
+}
+
+pub fn setup_lan_menu(
+  mut commands: Commands,
+  font: Res<crate::FontHandle>,
+  config: Res<net::DiscoveryConfig>,
+  mut hosts: ResMut<DiscoveredHosts>,
+) {
+    hosts.0.clear();
+
+    match net::Discovery::listen(config.listen) {
+        Ok(discovery) => commands.insert_resource(discovery),
+        Err(err) => warn!("Failed to listen for LAN games: {}", err),
+    }
+
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceEvenly,
+            ..Style::default()
+        },
+        visibility: Visibility {
+            is_visible: false,
+        },
+        ..NodeBundle::default()
+    })
+    .insert(LanMenu)
+    .with_children(|parent| {
+        let alignment = TextAlignment {
+            vertical: VerticalAlign::Center,
+            horizontal: HorizontalAlign::Center,
+        };
+
+        let btn_text_style = TextStyle {
+            font: font.0.clone(),
+            font_size: 30.0,
+            color: Color::WHITE,
+        };
+
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section("LAN games", TextStyle {
+                font: font.0.clone(),
+                font_size: 50.0,
+                color: game::TAGGER_COL,
+            }, alignment),
+            ..TextBundle::default()
+        });
+
+        // HOSTS, FILLED IN AS THEY ARE FOUND
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .insert(HostList);
+
+        for (button, text) in [(true, "Host or join by address"), (false, "Back")] {
+            let mut entity = parent.spawn_bundle(ButtonBundle {
+                style: Style {
+                    size: Size {
+                        width: Val::Px(if button {400.0} else {200.0}),
+                        height: Val::Px(50.0),
+                    },
+                    margin: Rect {
+                        bottom: Val::Px(12.),
+                        ..Default::default()
+                    },
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceAround,
+                    ..Style::default()
+                },
+                ..ButtonBundle::default()
+            });
+
+            if button {
+                entity.insert(LobbyButton);
+            } else {
+                entity.insert(BackButton);
+            }
+
+            entity.with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    style: Style::default(),
+                    text: Text::with_section(text, btn_text_style.clone(), alignment),
+                    ..TextBundle::default()
+                });
+            });
+        }
+    });
+}
+
+// Hosts that stopped announcing themselves started their match or left
+pub fn discover_hosts(
+    time: Res<Time>,
+    discovery: Option<Res<net::Discovery>>,
+    mut hosts: ResMut<DiscoveredHosts>,
+    mut last_seen: Local<HashMap<SocketAddr, (net::Announcement, f64)>>,
+) {
+    let now = time.seconds_since_startup();
+
+    if let Some(discovery) = discovery {
+        for (addr, announcement) in discovery.receive() {
+            last_seen.insert(addr, (announcement, now));
+        }
+    }
+
+    last_seen.retain(|_, (_, seen)| now - *seen < HOST_TIMEOUT);
+
+    let mut found: Vec<(SocketAddr, net::Announcement)> = last_seen.iter()
+        .map(|(addr, (announcement, _))| (*addr, announcement.clone()))
+        .collect();
+    found.sort_by_key(|(addr, _)| *addr);
+
+    // Only a different list is a change, the buttons are rebuilt on it
+    if found != hosts.0 {
+        hosts.0 = found;
+    }
+}
+
+pub fn update_host_list(
+    mut commands: Commands,
+    font: Res<crate::FontHandle>,
+    hosts: Res<DiscoveredHosts>,
+    list_query: Query<(Entity, ChangeTrackers<HostList>)>,
+) {
+    // A fresh list is filled in too, it is only spawned after the hosts were cleared
+    let list = match list_query.iter().next() {
+        Some((list, tracker)) if hosts.is_changed() || tracker.is_added() => list,
+        _ => return,
+    };
+
+    commands.entity(list).despawn_descendants();
+    commands.entity(list).with_children(|parent| {
+        let alignment = TextAlignment {
+            vertical: VerticalAlign::Center,
+            horizontal: HorizontalAlign::Center,
+        };
+
+        let style = TextStyle {
+            font: font.0.clone(),
+            font_size: 25.0,
+            color: Color::WHITE,
+        };
+
+        if hosts.0.is_empty() {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section("Looking for games...", style.clone(), alignment),
+                ..TextBundle::default()
+            });
+        }
+
+        for (addr, announcement) in hosts.0.iter() {
+            let mut text = format!("{}  {}  {}/{}", announcement.name, announcement.mode.name(), announcement.players, player::MAX_PLAYERS);
+
+            // Games of other versions are listed, but can't be joined
+            if announcement.version != net::VERSION {
+                text = format!("{}  (v{})", text, announcement.version);
+            }
+
+            parent.spawn_bundle(ButtonBundle {
+                style: Style {
+                    size: Size {
+                        width: Val::Px(600.0),
+                        height: Val::Px(50.0),
+                    },
+                    margin: Rect {
+                        bottom: Val::Px(12.),
+                        ..Default::default()
+                    },
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceAround,
+                    ..Style::default()
+                },
+                ..ButtonBundle::default()
+            })
+            .insert(HostButton(*addr))
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    style: Style::default(),
+                    text: Text::with_section(text, style.clone(), alignment),
+                    ..TextBundle::default()
+                });
+            });
+        }
+    });
+}
+
+pub fn handle_lan_menu_interactions(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    mut address: ResMut<lobby::LobbyAddress>,
+    mut info: ResMut<lobby::LobbyInfo>,
+    hosts: Res<DiscoveredHosts>,
+    mut host_btn_query: Query<(&Interaction, &mut UiColor, &HostButton), Changed<Interaction>>,
+    mut lobby_btn_query: Query<(&Interaction, &mut UiColor), (With<LobbyButton>, Without<HostButton>, Without<BackButton>)>,
+    mut back_btn_query: Query<(&Interaction, &mut UiColor), (With<BackButton>, Without<HostButton>, Without<LobbyButton>)>,
+) {
+    for (interaction, mut button, host_button) in host_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                let compatible = hosts.0.iter()
+                    .any(|(addr, announcement)| *addr == host_button.0 && announcement.version == net::VERSION);
+
+                if !compatible {
+                    continue;
+                }
+
+                address.0 = host_button.0.to_string();
+
+                match net::NetSession::join(host_button.0) {
+                    Ok(session) => commands.insert_resource(session),
+                    Err(err) => info.0 = format!("Failed to join: {}", err),
+                }
+
+                app_state.push(GameState::Lobby).unwrap();
+                return;
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+
+    for (interaction, mut button) in lobby_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::Lobby).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+
+    for (interaction, mut button) in back_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::MainMenu).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+// Stops listening, so another game on this machine can look for hosts
+pub fn teardown_lan_menu_items(
+    mut commands: Commands,
+    query: Query<Entity, With<LanMenu>>
+) {
+    commands.remove_resource::<net::Discovery>();
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
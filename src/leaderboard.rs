@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use crate::{GameState, game, time_trial};
+
+#[derive(Component)]
+pub struct LeaderboardMenu;
+
+#[derive(Component)]
+pub struct BackButton;
+
+pub struct LeaderboardMenuPlugin;
+
+impl Plugin for LeaderboardMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Leaderboard)
+                .with_system(setup_leaderboard_menu),
+        )
+        .add_system_set(
+            SystemSet::on_resume(GameState::Leaderboard)
+                .with_system(setup_leaderboard_menu),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Leaderboard)
+                .with_system(handle_leaderboard_menu_interactions),
+        )
+        .add_system_set(
+            SystemSet::on_pause(GameState::Leaderboard)
+                .with_system(teardown_leaderboard_menu_items),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Leaderboard)
+                .with_system(teardown_leaderboard_menu_items),
+        );
+    }
+}
+
+pub fn setup_leaderboard_menu(
+  mut commands: Commands,
+  font: Res<crate::FontHandle>,
+) {
+    let bests = time_trial::PersonalBests::load();
+
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceEvenly,
+            ..Style::default()
+        },
+        visibility: Visibility {
+            is_visible: false,
+        },
+        ..NodeBundle::default()
+    })
+    .insert(LeaderboardMenu)
+    .with_children(|parent| {
+        let alignment = TextAlignment {
+            vertical: VerticalAlign::Center,
+            horizontal: HorizontalAlign::Center,
+        };
+
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section("Time trial", TextStyle {
+                font: font.0.clone(),
+                font_size: 50.0,
+                color: game::TAGGER_COL,
+            }, alignment),
+            ..TextBundle::default()
+        });
+
+        // BEST TIMES
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            let style = TextStyle {
+                font: font.0.clone(),
+                font_size: 25.0,
+                color: Color::WHITE,
+            };
+
+            if bests.times.is_empty() {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section("No times yet", style.clone(), alignment),
+                    ..TextBundle::default()
+                });
+            }
+
+            for (place, time) in bests.times.iter().enumerate() {
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect {
+                            bottom: Val::Px(8.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text::with_section(format!("{:>2}.  {:.2}s", place + 1, time), style.clone(), alignment),
+                    ..TextBundle::default()
+                });
+            }
+        });
+
+        // BACK BUTTON
+        parent.spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                },
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceAround,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(BackButton)
+        .with_children(|parent| {
+            let style = TextStyle {
+                font: font.0.clone(),
+                font_size: 30.0,
+                color: Color::WHITE,
+            };
+
+            parent.spawn_bundle(TextBundle {
+                style: Style::default(),
+                text: Text::with_section("Back", style, alignment),
+                ..TextBundle::default()
+            });
+        });
+    });
+}
+
+pub fn handle_leaderboard_menu_interactions(
+    mut app_state: ResMut<State<GameState>>,
+    mut back_btn_query: Query<(&Interaction, &mut UiColor), With<BackButton>>,
+) {
+    for (interaction, mut button) in back_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::MainMenu).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn teardown_leaderboard_menu_items(
+    mut commands: Commands,
+    query: Query<Entity, With<LeaderboardMenu>>
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
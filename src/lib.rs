@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, app::PluginGroupBuilder};
+use rand::rngs::StdRng;
+use bevy_kira_audio::AudioSource;
+
+pub mod player;
+pub mod ability;
+pub mod bullet;
+pub mod orb;
+pub mod trap;
+pub mod mode;
+pub mod freeze_tag;
+pub mod infection;
+pub mod hot_potato;
+pub mod king_of_the_hill;
+pub mod capture_the_orb;
+pub mod overtime;
+pub mod teams;
+pub mod bot;
+pub mod survival;
+pub mod time_trial;
+pub mod practice;
+pub mod tutorial;
+pub mod challenge;
+pub mod net;
+pub mod rollback;
+pub mod replay;
+pub mod match_log;
+pub mod stats;
+pub mod spectate;
+pub mod agent;
+pub mod headless;
+pub mod gym;
+pub mod tournament;
+pub mod menu;
+pub mod controlls;
+pub mod leaderboard;
+pub mod stats_menu;
+pub mod challenge_menu;
+pub mod lobby;
+pub mod lan_menu;
+pub mod loadout;
+pub mod game;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum GameState {
+    MainMenu,
+    ControllsMenu,
+    LoadoutMenu,
+    Leaderboard,
+    MatchStats,
+    ChallengeMenu,
+    LanMenu,
+    Lobby,
+    Game,
+}
+
+pub struct Winner(pub String);
+
+pub struct Random(pub StdRng);
+
+pub struct OrbSound(pub Handle<AudioSource>);
+pub struct CatchSound(pub Handle<AudioSource>);
+pub struct TimerSound(pub Handle<AudioSource>);
+pub struct FontHandle(pub Handle<Font>);
+
+// Everything a match needs, the menus are left to the game itself
+pub struct MatchPlugins;
+
+impl PluginGroup for MatchPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(game::GamePlugin)
+            .add(mode::ModePlugin)
+            .add(freeze_tag::FreezeTagPlugin)
+            .add(infection::InfectionPlugin)
+            .add(hot_potato::HotPotatoPlugin)
+            .add(king_of_the_hill::KingOfTheHillPlugin)
+            .add(capture_the_orb::CaptureTheOrbPlugin)
+            .add(overtime::OvertimePlugin)
+            .add(teams::TeamsPlugin)
+            .add(bot::BotPlugin)
+            .add(survival::SurvivalPlugin)
+            .add(time_trial::TimeTrialPlugin)
+            .add(practice::PracticePlugin)
+            .add(tutorial::TutorialPlugin)
+            .add(challenge::ChallengePlugin)
+            .add(net::NetPlugin)
+            .add(rollback::RollbackPlugin)
+            .add(replay::ReplayPlugin)
+            .add(match_log::MatchLogPlugin)
+            .add(stats::StatsPlugin)
+            .add(agent::AgentPlugin);
+    }
+}
+
+// The options of the match plugins given on the command line. Only the binaries read them,
+// the plugins themselves just look for the resources
+pub fn insert_arg_resources(app: &mut App) {
+    if let Some(conditions) = rollback::LinkConditions::from_args() {
+        app.insert_resource(conditions);
+    }
+
+    if let Some(dir) = replay::RecordReplays::from_args() {
+        app.insert_resource(dir);
+    }
+
+    if let Some(file) = replay::PendingReplay::from_args() {
+        app.insert_resource(file);
+    }
+
+    if let Some(dir) = match_log::RecordEvents::from_args() {
+        app.insert_resource(dir);
+    }
+
+    if let Some(config) = agent::AgentConfig::from_args() {
+        app.insert_resource(config);
+    }
+}
+
+pub fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let orb_audio: Handle<AudioSource> = asset_server.load("orb.wav");
+    let catch_audio: Handle<AudioSource> = asset_server.load("catch.wav");
+    let timer_audio: Handle<AudioSource> = asset_server.load("timer.wav");
+
+    commands.insert_resource(OrbSound(orb_audio));
+    commands.insert_resource(CatchSound(catch_audio));
+    commands.insert_resource(TimerSound(timer_audio));
+
+    let ability_audio: HashMap<ability::Ability, Handle<AudioSource>> = ability::Ability::all()
+        .map(|ability| (ability, asset_server.load(ability.sound_file())))
+        .collect();
+    commands.insert_resource(ability::AbilitySounds(ability_audio));
+    
+    let font = asset_server.load("zorque.otf");
+    commands.insert_resource(FontHandle(font.clone()));
+
+    commands.spawn_bundle(UiCameraBundle::default());
+}
@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+use crate::{GameState, game};
+use crate::ability::{Loadout, Loadouts, Role};
+use crate::mode::MatchSettings;
+use crate::player::player_name;
+
+#[derive(Component)]
+pub struct LoadoutMenu;
+
+#[derive(Component)]
+pub struct AbilityButton {
+    player: usize,
+    role: Role,
+}
+
+#[derive(Component)]
+pub struct StartButton;
+
+#[derive(Component)]
+pub struct BackButton;
+
+pub struct LoadoutMenuPlugin;
+
+impl Plugin for LoadoutMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::LoadoutMenu)
+                .with_system(setup_loadout_menu),
+        )
+        .add_system_set(
+            SystemSet::on_resume(GameState::LoadoutMenu)
+                .with_system(setup_loadout_menu),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::LoadoutMenu)
+                .with_system(handle_loadout_menu_interactions)
+                .with_system(handle_ability_button_interactions),
+        )
+        .add_system_set(
+            SystemSet::on_pause(GameState::LoadoutMenu)
+                .with_system(teardown_loadout_menu_items),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::LoadoutMenu)
+                .with_system(teardown_loadout_menu_items),
+        );
+    }
+}
+
+pub fn setup_loadout_menu(
+  mut commands: Commands,
+  mut loadouts: ResMut<Loadouts>,
+  settings: Res<MatchSettings>,
+  font: Res<crate::FontHandle>,
+) {
+    loadouts.0.resize(settings.players, Loadout::default());
+
+    let btn_style = Style {
+        size: Size {
+            width: Val::Px(180.0),
+            height: Val::Px(50.0),
+        },
+        margin: Rect {
+            bottom: Val::Px(20.),
+            ..Default::default()
+        },
+        align_items: AlignItems::Center,
+        justify_content: JustifyContent::SpaceAround,
+        ..Style::default()
+    };
+
+    let btn_text_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 22.0,
+        color: Color::WHITE,
+    };
+
+    let title_text_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+
+    let alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceEvenly,
+            ..Style::default()
+        },
+        visibility: Visibility {
+            is_visible: false,
+        },
+        ..NodeBundle::default()
+    })
+    .insert(LoadoutMenu)
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section("Loadout", title_text_style.clone(), alignment),
+            ..TextBundle::default()
+        });
+
+        // ABILITY SLOTS OF EVERY PLAYER
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..Style::default()
+            },
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            for (player, loadout) in loadouts.0.iter().enumerate() {
+                parent.spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        margin: Rect {
+                            left: Val::Px(5.),
+                            right: Val::Px(5.),
+                            ..Default::default()
+                        },
+                        ..Style::default()
+                    },
+                    visibility: Visibility {
+                        is_visible: false,
+                    },
+                    ..NodeBundle::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        style: Style {
+                            margin: Rect {
+                                bottom: Val::Px(10.),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        text: Text::with_section(player_name(player), btn_text_style.clone(), alignment),
+                        ..TextBundle::default()
+                    });
+
+                    for role in [Role::Runner, Role::Tagger] {
+                        let role_text_style = TextStyle {
+                            color: role.color(),
+                            ..btn_text_style.clone()
+                        };
+
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(role.name(), role_text_style, alignment),
+                            ..TextBundle::default()
+                        });
+
+                        parent.spawn_bundle(ButtonBundle {
+                            style: btn_style.clone(),
+                            ..ButtonBundle::default()
+                        })
+                        .insert(AbilityButton { player, role })
+                        .with_children(|parent| {
+                            parent.spawn_bundle(TextBundle {
+                                style: Style::default(),
+                                text: Text::with_section(loadout.get(role).name(), btn_text_style.clone(), alignment),
+                                ..TextBundle::default()
+                            });
+                        });
+                    }
+                });
+            }
+        });
+
+        // START AND BACK BUTTONS
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(ButtonBundle {
+                style: btn_style.clone(),
+                ..ButtonBundle::default()
+            })
+            .insert(StartButton)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    style: Style::default(),
+                    text: Text::with_section("Start", btn_text_style.clone(), alignment),
+                    ..TextBundle::default()
+                });
+            });
+
+            parent.spawn_bundle(ButtonBundle {
+                style: btn_style.clone(),
+                ..ButtonBundle::default()
+            })
+            .insert(BackButton)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    style: Style::default(),
+                    text: Text::with_section("Back", btn_text_style.clone(), alignment),
+                    ..TextBundle::default()
+                });
+            });
+        });
+    });
+}
+
+pub fn handle_ability_button_interactions(
+    mut loadouts: ResMut<Loadouts>,
+    mut btn_query: Query<(&Interaction, &mut UiColor, &AbilityButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, mut button, ability_button, children) in btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                let loadout = &mut loadouts.0[ability_button.player];
+                loadout.cycle(ability_button.role);
+
+                for child in children.iter() {
+                    if let Ok(mut text) = text_query.get_mut(*child) {
+                        text.sections[0].value = loadout.get(ability_button.role).name().to_string();
+                    }
+                }
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn handle_loadout_menu_interactions(
+    mut app_state: ResMut<State<GameState>>,
+    mut start_btn_query: Query<(&Interaction, &mut UiColor), (With<StartButton>, Without<BackButton>)>,
+    mut back_btn_query: Query<(&Interaction, &mut UiColor), (With<BackButton>, Without<StartButton>)>,
+) {
+    for (interaction, mut button) in start_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::Game).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+
+    for (interaction, mut button) in back_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::MainMenu).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn teardown_loadout_menu_items(
+    mut commands: Commands,
+    query: Query<Entity, With<LoadoutMenu>>
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,316 @@
+use std::net::SocketAddr;
+
+use bevy::prelude::*;
+use crate::{GameState, game, mode, net, player};
+
+pub const BACKSPACE_KEY: KeyCode = KeyCode::Back;
+
+#[derive(Component)]
+pub struct Lobby;
+
+#[derive(Component)]
+pub struct AddressText;
+
+#[derive(Component)]
+pub struct StatusText;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum LobbyButton {
+    Host,
+    Join,
+    Watch,
+    Start,
+    Back,
+}
+
+impl LobbyButton {
+    pub const ALL: [LobbyButton; 5] = [LobbyButton::Host, LobbyButton::Join, LobbyButton::Watch, LobbyButton::Start, LobbyButton::Back];
+
+    fn text(&self) -> &'static str {
+        match self {
+            LobbyButton::Host => "Host",
+            LobbyButton::Join => "Join",
+            LobbyButton::Watch => "Watch",
+            LobbyButton::Start => "Start",
+            LobbyButton::Back => "Back",
+        }
+    }
+}
+
+// The address to join, its port is also the one a host listens on
+pub struct LobbyAddress(pub String);
+
+impl Default for LobbyAddress {
+    fn default() -> Self {
+        Self(format!("127.0.0.1:{}", net::DEFAULT_PORT))
+    }
+}
+
+impl LobbyAddress {
+    pub fn port(&self) -> u16 {
+        self.0.rsplit(':').next()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(net::DEFAULT_PORT)
+    }
+}
+
+// Shown while there is no session, e.g. why hosting failed
+pub struct LobbyInfo(pub String);
+
+impl Default for LobbyInfo {
+    fn default() -> Self {
+        Self("Host a game or join one by its address".to_string())
+    }
+}
+
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LobbyAddress>()
+            .init_resource::<LobbyInfo>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Lobby)
+                    .with_system(setup_lobby),
+            )
+            .add_system_set(
+                SystemSet::on_resume(GameState::Lobby)
+                    .with_system(setup_lobby),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Lobby)
+                    .with_system(type_address)
+                    .with_system(update_lobby_texts)
+                    .with_system(handle_lobby_interactions),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::Lobby)
+                    .with_system(teardown_lobby_items),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Lobby)
+                    .with_system(teardown_lobby_items),
+            );
+    }
+}
+
+pub fn setup_lobby(
+  mut commands: Commands,
+  font: Res<crate::FontHandle>,
+) {
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceEvenly,
+            ..Style::default()
+        },
+        visibility: Visibility {
+            is_visible: false,
+        },
+        ..NodeBundle::default()
+    })
+    .insert(Lobby)
+    .with_children(|parent| {
+        let alignment = TextAlignment {
+            vertical: VerticalAlign::Center,
+            horizontal: HorizontalAlign::Center,
+        };
+
+        let text_style = TextStyle {
+            font: font.0.clone(),
+            font_size: 30.0,
+            color: Color::WHITE,
+        };
+
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section("LAN game", TextStyle {
+                font: font.0.clone(),
+                font_size: 50.0,
+                color: game::TAGGER_COL,
+            }, alignment),
+            ..TextBundle::default()
+        });
+
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section("", TextStyle {
+                color: game::RUNNER_COL,
+                ..text_style.clone()
+            }, alignment),
+            ..TextBundle::default()
+        })
+        .insert(AddressText);
+
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section("", TextStyle {
+                font_size: 20.0,
+                ..text_style.clone()
+            }, alignment),
+            ..TextBundle::default()
+        })
+        .insert(StatusText);
+
+        // BUTTONS
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            for button in LobbyButton::ALL {
+                parent.spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Px(200.0),
+                            height: Val::Px(50.0),
+                        },
+                        margin: Rect {
+                            bottom: Val::Px(12.),
+                            ..Default::default()
+                        },
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceAround,
+                        ..Style::default()
+                    },
+                    ..ButtonBundle::default()
+                })
+                .insert(button)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        style: Style::default(),
+                        text: Text::with_section(button.text(), text_style.clone(), alignment),
+                        ..TextBundle::default()
+                    });
+                });
+            }
+        });
+    });
+}
+
+// The address can only be changed before hosting or joining
+pub fn type_address(
+    keys: Res<Input<KeyCode>>,
+    session: Option<Res<net::NetSession>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut address: ResMut<LobbyAddress>,
+) {
+    let typed: Vec<char> = characters.iter().map(|event| event.char).collect();
+
+    if session.is_some() {
+        return;
+    }
+
+    for char in typed {
+        if char.is_ascii_alphanumeric() || char == '.' || char == ':' {
+            address.0.push(char);
+        }
+    }
+
+    if keys.just_pressed(BACKSPACE_KEY) {
+        address.0.pop();
+    }
+}
+
+pub fn update_lobby_texts(
+    address: Res<LobbyAddress>,
+    info: Res<LobbyInfo>,
+    session: Option<Res<net::NetSession>>,
+    mut address_query: Query<&mut Text, (With<AddressText>, Without<StatusText>)>,
+    mut status_query: Query<&mut Text, (With<StatusText>, Without<AddressText>)>,
+) {
+    let status = match session.as_deref() {
+        Some(session) if session.is_host() => format!("Hosting on port {}, {} players", address.port(), session.players),
+        Some(net::NetSession { spectator: true, players, .. }) => format!("Watching, {} players", players),
+        Some(net::NetSession { local_id: Some(id), players, .. }) => format!("Joined as {}, {} players", player::player_name(*id), players),
+        Some(_) => "Waiting for the host".to_string(),
+        None => info.0.clone(),
+    };
+
+    for mut text in address_query.iter_mut() {
+        if text.sections[0].value != address.0 {
+            text.sections[0].value = address.0.clone();
+        }
+    }
+
+    for mut text in status_query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
+
+pub fn handle_lobby_interactions(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    mut settings: ResMut<mode::MatchSettings>,
+    mut random: ResMut<crate::Random>,
+    mut info: ResMut<LobbyInfo>,
+    address: Res<LobbyAddress>,
+    mut session: Option<ResMut<net::NetSession>>,
+    mut btn_query: Query<(&Interaction, &mut UiColor, &LobbyButton), Changed<Interaction>>,
+) {
+    for (interaction, mut color, button) in btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                match button {
+                    LobbyButton::Host if session.is_none() => {
+                        match net::NetSession::host(address.port()) {
+                            Ok(session) => commands.insert_resource(session),
+                            Err(err) => info.0 = format!("Failed to host: {}", err),
+                        }
+                    },
+                    LobbyButton::Join | LobbyButton::Watch if session.is_none() => {
+                        let connect = if *button == LobbyButton::Watch {net::NetSession::watch} else {net::NetSession::join};
+                        let session = address.0.parse::<SocketAddr>()
+                            .map_err(|err| err.to_string())
+                            .and_then(|addr| connect(addr).map_err(|err| err.to_string()));
+
+                        match session {
+                            Ok(session) => commands.insert_resource(session),
+                            Err(err) => info.0 = format!("Failed to join: {}", err),
+                        }
+                    },
+                    LobbyButton::Start => {
+                        if let Some(session) = session.as_deref_mut().filter(|session| session.is_host() && session.players > 1) {
+                            net::start_match(session, &mut settings, &mut random, &mut app_state);
+                        }
+                    },
+                    LobbyButton::Back => {
+                        if let Some(session) = session.as_deref().filter(|session| !session.is_host()) {
+                            session.broadcast(&net::Message::Leave);
+                        }
+
+                        commands.remove_resource::<net::NetSession>();
+                        app_state.push(GameState::MainMenu).unwrap();
+                    },
+                    _ => {},
+                }
+            },
+            Interaction::Hovered => {
+                color.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                color.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn teardown_lobby_items(
+    mut commands: Commands,
+    query: Query<Entity, With<Lobby>>
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,108 @@
+use std::{fs::{self, File}, io::{BufWriter, Write}, path::{Path, PathBuf}, time::SystemTime};
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::mode::GameMode;
+use crate::ability::{Ability, Role};
+
+// Something that happened in a match, at the match time it happened
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchEvent {
+    pub time: f32,
+    pub kind: MatchEventKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum MatchEventKind {
+    Start { mode: GameMode, players: usize, overtime: bool },
+    Shot { player: usize, ability: Ability, x: f32, y: f32, rotation: f32 },
+    DashStart { player: usize, x: f32, y: f32 },
+    DashEnd { player: usize, x: f32, y: f32 },
+    OrbSpawn { x: f32, y: f32 },
+    OrbTouch { player: usize, x: f32, y: f32 },
+    // Where the runner was caught
+    Tag { tagger: usize, runner: usize, x: f32, y: f32 },
+    RoleSwap { player: usize, role: Role, x: f32, y: f32 },
+    // Without a winner if the match was left
+    End { winner: Option<String> },
+}
+
+// Every match is logged into this directory, read from `--event-log <dir>`
+pub struct RecordEvents(pub PathBuf);
+
+impl RecordEvents {
+    pub fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+
+        args.iter()
+            .position(|arg| arg == "--event-log")
+            .and_then(|index| args.get(index + 1))
+            .map(|dir| Self(dir.into()))
+    }
+}
+
+pub struct MatchLogPlugin;
+
+impl Plugin for MatchLogPlugin {
+    fn build(&self, app: &mut App) {
+        // The match ends in the state transition, so the log is written after it
+        app.add_event::<MatchEvent>()
+            .add_system_to_stage(CoreStage::PostUpdate, write_match_log);
+    }
+}
+
+// One file per match, one event per line
+pub fn write_match_log(
+    dir: Option<Res<RecordEvents>>,
+    mut events: EventReader<MatchEvent>,
+    mut log: Local<Option<BufWriter<File>>>,
+) {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    for event in events.iter() {
+        if let MatchEventKind::Start { .. } = event.kind {
+            *log = create_log(&dir.0);
+        }
+
+        let file = match log.as_mut() {
+            Some(file) => file,
+            None => continue,
+        };
+
+        let result = serde_json::to_writer(&mut *file, event)
+            .map_err(std::io::Error::from)
+            .and_then(|_| file.write_all(b"\n"));
+
+        if let Err(err) = result {
+            warn!("Failed to write the match log: {}", err);
+        }
+
+        if let MatchEventKind::End { .. } = event.kind {
+            if let Some(Err(err)) = log.take().map(|mut file| file.flush()) {
+                warn!("Failed to write the match log: {}", err);
+            }
+        }
+    }
+}
+
+fn create_log(dir: &Path) -> Option<BufWriter<File>> {
+    let started = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis());
+    let path = dir.join(format!("events-{}.jsonl", started));
+
+    match fs::create_dir_all(dir).and_then(|_| File::create(&path)) {
+        Ok(file) => {
+            info!("Logging the match to {}", path.display());
+            Some(BufWriter::new(file))
+        },
+        Err(err) => {
+            warn!("Failed to create the match log {}: {}", path.display(), err);
+            None
+        },
+    }
+}
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
-use crate::{GameState, game};
+use crate::{GameState, game, mode};
+use crate::ability::Role;
 
 #[derive(Component)]
 pub struct MainMenu;
@@ -10,6 +11,61 @@ pub struct PlayButton;
 #[derive(Component)]
 pub struct ControllsButton;
 
+#[derive(Component)]
+pub struct LeaderboardButton;
+
+#[derive(Component)]
+pub struct TutorialButton;
+
+#[derive(Component)]
+pub struct ChallengesButton;
+
+#[derive(Component)]
+pub struct LanButton;
+
+// Next to the play button once a match was played
+#[derive(Component)]
+pub struct StatsButton;
+
+// Buttons cycling through the values of a match setting
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum SettingButton {
+    Mode,
+    Players,
+    Overtime,
+    Role,
+}
+
+impl SettingButton {
+    pub const ALL: [SettingButton; 4] = [SettingButton::Mode, SettingButton::Players, SettingButton::Overtime, SettingButton::Role];
+
+    fn text(&self, settings: &mode::MatchSettings) -> String {
+        match self {
+            SettingButton::Mode => settings.mode.name().to_string(),
+            SettingButton::Players => format!("{} players", settings.players),
+            SettingButton::Overtime => format!("Overtime: {}", if settings.overtime {"On"} else {"Off"}),
+            SettingButton::Role => format!("Practice: {}", settings.practice_role.name()),
+        }
+    }
+
+    fn cycle(&self, settings: &mut mode::MatchSettings) {
+        match self {
+            SettingButton::Mode => settings.cycle_mode(),
+            SettingButton::Players => settings.cycle_players(),
+            SettingButton::Overtime => settings.overtime = !settings.overtime,
+            SettingButton::Role => {
+                settings.practice_role = match settings.practice_role {
+                    Role::Tagger => Role::Runner,
+                    Role::Runner => Role::Tagger,
+                };
+            },
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct SettingText(SettingButton);
+
 pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
@@ -24,7 +80,12 @@ impl Plugin for MenuPlugin {
         )
         .add_system_set(
             SystemSet::on_update(GameState::MainMenu)
-                .with_system(handle_menu_interactions),
+                .with_system(handle_menu_interactions)
+                .with_system(handle_settings_interactions)
+                .with_system(handle_tutorial_interactions)
+                .with_system(handle_challenges_interactions)
+                .with_system(handle_lan_interactions)
+                .with_system(handle_stats_interactions),
         )
         .add_system_set(
             SystemSet::on_pause(GameState::MainMenu)
@@ -40,6 +101,7 @@ impl Plugin for MenuPlugin {
 pub fn setup_main_menu(
   mut commands: Commands,
   winner: Option<Res<crate::Winner>>,
+  settings: Res<mode::MatchSettings>,
   font: Res<crate::FontHandle>,
 ) {
   commands
@@ -124,11 +186,11 @@ pub fn setup_main_menu(
         .with_children(|parent| {
             let btn_style = Style {
                 size: Size {
-                    width: Val::Px(200.0),
+                    width: Val::Px(260.0),
                     height: Val::Px(50.0),
                 },
                 margin: Rect {
-                      bottom: Val::Px(20.),
+                      bottom: Val::Px(12.),
                       ..Default::default()
                 },
                 align_items: AlignItems::Center,
@@ -147,21 +209,84 @@ pub fn setup_main_menu(
                 horizontal: HorizontalAlign::Center,
             };
 
-            parent.spawn_bundle(ButtonBundle {
-              style: btn_style.clone(),
-              ..ButtonBundle::default()
+            // PLAY AGAIN AND THE STATS OF THE LAST MATCH SHARE A ROW
+            parent.spawn_bundle(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::SpaceBetween,
+                    size: Size {
+                        width: btn_style.size.width,
+                        height: Val::Auto,
+                    },
+                    ..Style::default()
+                },
+                visibility: Visibility {
+                    is_visible: false,
+                },
+                ..NodeBundle::default()
             })
-            .insert(PlayButton)
-            .with_children(|parent| {   
-                let btn_text = if winner.is_some() {"Play again"} else {"Play"};
+            .with_children(|parent| {
+                let play_width = if winner.is_some() {Val::Px(170.0)} else {btn_style.size.width};
 
-                parent.spawn_bundle(TextBundle {
-                    style: Style::default(),
-                    text: Text::with_section(btn_text, btn_text_style.clone(), btn_text_alignment.clone()),
-                    ..TextBundle::default()
+                parent.spawn_bundle(ButtonBundle {
+                  style: Style {
+                      size: Size {
+                          width: play_width,
+                          ..btn_style.size
+                      },
+                      ..btn_style.clone()
+                  },
+                  ..ButtonBundle::default()
+                })
+                .insert(PlayButton)
+                .with_children(|parent| {   
+                    let btn_text = if winner.is_some() {"Play again"} else {"Play"};
+
+                    parent.spawn_bundle(TextBundle {
+                        style: Style::default(),
+                        text: Text::with_section(btn_text, btn_text_style.clone(), btn_text_alignment.clone()),
+                        ..TextBundle::default()
+                    });
                 });
+
+                if winner.is_some() {
+                    parent.spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size {
+                                width: Val::Px(80.0),
+                                ..btn_style.size
+                            },
+                            ..btn_style.clone()
+                        },
+                        ..ButtonBundle::default()
+                    })
+                    .insert(StatsButton)
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            style: Style::default(),
+                            text: Text::with_section("Stats", TextStyle { font_size: 20.0, ..btn_text_style.clone() }, btn_text_alignment),
+                            ..TextBundle::default()
+                        });
+                    });
+                }
             });
     
+            for setting in SettingButton::ALL {
+                parent.spawn_bundle(ButtonBundle {
+                    style: btn_style.clone(),
+                    ..ButtonBundle::default()
+                })
+                .insert(setting)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        style: Style::default(),
+                        text: Text::with_section(setting.text(&settings), btn_text_style.clone(), btn_text_alignment),
+                        ..TextBundle::default()
+                    })
+                    .insert(SettingText(setting));
+                });
+            }
+    
             parent.spawn_bundle(ButtonBundle {
                 style: btn_style.clone(),
                 ..ButtonBundle::default()
@@ -174,19 +299,72 @@ pub fn setup_main_menu(
                       ..TextBundle::default()
                   });
               });
+
+            parent.spawn_bundle(ButtonBundle {
+                style: btn_style.clone(),
+                ..ButtonBundle::default()
+            })
+            .insert(LeaderboardButton)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    style: Style::default(),
+                    text: Text::with_section("Leaderboard", btn_text_style.clone(), btn_text_alignment),
+                    ..TextBundle::default()
+                });
+            });
+
+            parent.spawn_bundle(ButtonBundle {
+                style: btn_style.clone(),
+                ..ButtonBundle::default()
+            })
+            .insert(TutorialButton)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    style: Style::default(),
+                    text: Text::with_section("Tutorial", btn_text_style.clone(), btn_text_alignment),
+                    ..TextBundle::default()
+                });
+            });
+
+            parent.spawn_bundle(ButtonBundle {
+                style: btn_style.clone(),
+                ..ButtonBundle::default()
+            })
+            .insert(ChallengesButton)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    style: Style::default(),
+                    text: Text::with_section("Challenges", btn_text_style.clone(), btn_text_alignment),
+                    ..TextBundle::default()
+                });
+            });
+
+            parent.spawn_bundle(ButtonBundle {
+                style: btn_style.clone(),
+                ..ButtonBundle::default()
+            })
+            .insert(LanButton)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    style: Style::default(),
+                    text: Text::with_section("Join LAN game", btn_text_style.clone(), btn_text_alignment),
+                    ..TextBundle::default()
+                });
+            });
         });
     });
 }
 
 pub fn handle_menu_interactions(
     mut app_state: ResMut<State<GameState>>,
-    mut play_btn_query: Query<(&Interaction, &mut UiColor), (With<PlayButton>, Without<ControllsButton>)>,
-    mut controlls_btn_query: Query<(&Interaction, &mut UiColor), (With<ControllsButton>, Without<PlayButton>)>,
+    mut play_btn_query: Query<(&Interaction, &mut UiColor), (With<PlayButton>, Without<ControllsButton>, Without<LeaderboardButton>)>,
+    mut controlls_btn_query: Query<(&Interaction, &mut UiColor), (With<ControllsButton>, Without<PlayButton>, Without<LeaderboardButton>)>,
+    mut leaderboard_btn_query: Query<(&Interaction, &mut UiColor), (With<LeaderboardButton>, Without<PlayButton>, Without<ControllsButton>)>,
 ) {
     for (interaction, mut button) in play_btn_query.iter_mut() {
         match interaction {
             Interaction::Clicked => {
-                app_state.push(GameState::Game).unwrap();
+                app_state.push(GameState::LoadoutMenu).unwrap();
             },
             Interaction::Hovered => {
                 button.0 = game::ORB_OUTLINE_COLOR;
@@ -210,6 +388,127 @@ pub fn handle_menu_interactions(
             }
         }
     }
+
+    for (interaction, mut button) in leaderboard_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::Leaderboard).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+// The tutorial is a solo match of its own mode, it skips the loadout menu
+pub fn handle_tutorial_interactions(
+    mut app_state: ResMut<State<GameState>>,
+    mut settings: ResMut<mode::MatchSettings>,
+    mut btn_query: Query<(&Interaction, &mut UiColor), (With<TutorialButton>, Changed<Interaction>)>,
+) {
+    for (interaction, mut button) in btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                settings.mode = mode::GameMode::Tutorial;
+                settings.players = 1;
+                app_state.push(GameState::Game).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn handle_challenges_interactions(
+    mut app_state: ResMut<State<GameState>>,
+    mut btn_query: Query<(&Interaction, &mut UiColor), (With<ChallengesButton>, Changed<Interaction>)>,
+) {
+    for (interaction, mut button) in btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::ChallengeMenu).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn handle_lan_interactions(
+    mut app_state: ResMut<State<GameState>>,
+    mut btn_query: Query<(&Interaction, &mut UiColor), (With<LanButton>, Changed<Interaction>)>,
+) {
+    for (interaction, mut button) in btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::LanMenu).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn handle_stats_interactions(
+    mut app_state: ResMut<State<GameState>>,
+    mut btn_query: Query<(&Interaction, &mut UiColor), (With<StatsButton>, Changed<Interaction>)>,
+) {
+    for (interaction, mut button) in btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::MatchStats).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+// The settings are changed on click, not on every frame the button is held
+pub fn handle_settings_interactions(
+    mut settings: ResMut<mode::MatchSettings>,
+    mut btn_query: Query<(&Interaction, &mut UiColor, &SettingButton), Changed<Interaction>>,
+    mut text_query: Query<(&mut Text, &SettingText)>,
+) {
+    for (interaction, mut button, setting) in btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                setting.cycle(&mut settings);
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+
+    if settings.is_changed() {
+        for (mut text, setting) in text_query.iter_mut() {
+            text.sections[0].value = setting.0.text(&settings);
+        }
+    }
 }
 
 pub fn teardown_menu_items(
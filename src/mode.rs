@@ -0,0 +1,302 @@
+use bevy::{prelude::*, ecs::schedule::ShouldRun};
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, net, player};
+use crate::ability::Role;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic,
+    FreezeTag,
+    Infection,
+    HotPotato,
+    KingOfTheHill,
+    CaptureTheOrb,
+    Teams,
+    Survival,
+    TimeTrial,
+    Practice,
+    Tutorial,
+    Challenge,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 12] = [
+        GameMode::Classic,
+        GameMode::FreezeTag,
+        GameMode::Infection,
+        GameMode::HotPotato,
+        GameMode::KingOfTheHill,
+        GameMode::CaptureTheOrb,
+        GameMode::Teams,
+        GameMode::Survival,
+        GameMode::TimeTrial,
+        GameMode::Practice,
+        GameMode::Tutorial,
+        GameMode::Challenge,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::FreezeTag => "Freeze tag",
+            GameMode::Infection => "Infection",
+            GameMode::HotPotato => "Hot potato",
+            GameMode::KingOfTheHill => "King of the hill",
+            GameMode::CaptureTheOrb => "Capture the orb",
+            GameMode::Teams => "Teams",
+            GameMode::Survival => "Survival",
+            GameMode::TimeTrial => "Time trial",
+            GameMode::Practice => "Practice",
+            GameMode::Tutorial => "Tutorial",
+            GameMode::Challenge => "Challenge",
+        }
+    }
+
+    pub fn min_players(&self) -> usize {
+        match self {
+            GameMode::Classic => 2,
+            GameMode::FreezeTag => 3,
+            GameMode::Infection => 3,
+            GameMode::HotPotato => 2,
+            GameMode::KingOfTheHill => 2,
+            GameMode::CaptureTheOrb => 2,
+            GameMode::Teams => 4,
+            GameMode::Survival => 1,
+            GameMode::TimeTrial => 1,
+            GameMode::Practice => 1,
+            GameMode::Tutorial => 1,
+            GameMode::Challenge => 1,
+        }
+    }
+
+    pub fn tagger_count(&self, players: usize) -> usize {
+        match self {
+            GameMode::Classic => 1,
+            GameMode::FreezeTag => (players / 3).max(1),
+            GameMode::Infection => 1,
+            GameMode::HotPotato => 1,
+            GameMode::KingOfTheHill => 1,
+            GameMode::CaptureTheOrb => players,
+            GameMode::Teams => players / 2,
+            GameMode::Survival => 0,
+            GameMode::TimeTrial => 1,
+            GameMode::Practice => 1,
+            GameMode::Tutorial => 0,
+            GameMode::Challenge => 0,
+        }
+    }
+
+    pub fn shows_team_counts(&self) -> bool {
+        matches!(self, GameMode::Infection)
+    }
+
+    pub fn shows_team_scores(&self) -> bool {
+        matches!(self, GameMode::CaptureTheOrb | GameMode::Teams)
+    }
+
+    pub fn shows_status(&self) -> bool {
+        matches!(self, GameMode::Survival | GameMode::Practice | GameMode::Tutorial | GameMode::Challenge)
+    }
+
+    pub fn has_teams(&self) -> bool {
+        matches!(self, GameMode::CaptureTheOrb | GameMode::Teams)
+    }
+
+    // Survival goes on as long as somebody is left, the clock counts up instead
+    pub fn has_time_limit(&self) -> bool {
+        !matches!(self, GameMode::Survival | GameMode::TimeTrial | GameMode::Practice | GameMode::Tutorial)
+    }
+
+    // Modes played for a best time use the same random numbers every attempt
+    pub fn fixed_seed(&self) -> Option<u64> {
+        match self {
+            GameMode::TimeTrial => Some(crate::time_trial::TRIAL_SEED),
+            _ => None,
+        }
+    }
+
+    // Teams are only formed if everyone has an opponent, the time trial, practice, tutorial and challenges are solo
+    pub fn allows_players(&self, players: usize) -> bool {
+        match self {
+            GameMode::Teams => players >= self.min_players() && players.is_multiple_of(2),
+            GameMode::TimeTrial | GameMode::Practice | GameMode::Tutorial | GameMode::Challenge => players == 1,
+            _ => players >= self.min_players(),
+        }
+    }
+
+    // What a match that can't be played in its mode with this many players plays instead
+    pub fn fallback(players: usize) -> Option<Self> {
+        Self::ALL.iter().copied().find(|mode| mode.allows_players(players))
+    }
+
+    pub fn next(&self) -> Self {
+        let current = Self::ALL.iter().position(|mode| mode == self).unwrap();
+        Self::ALL[(current + 1) % Self::ALL.len()]
+    }
+}
+
+pub struct MatchSettings {
+    pub mode: GameMode,
+    pub players: usize,
+    pub overtime: bool,
+    pub practice_role: Role,
+}
+
+// A match as a rules file or a training environment describes it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MatchRules {
+    pub mode: GameMode,
+    pub players: usize,
+    #[serde(default)]
+    pub overtime: bool,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Classic,
+            players: 2,
+            overtime: false,
+            practice_role: Role::Tagger,
+        }
+    }
+}
+
+impl MatchSettings {
+    // In practice the single player plays the role they picked
+    pub fn tagger_count(&self) -> usize {
+        match (self.mode, self.practice_role) {
+            (GameMode::Practice, Role::Runner) => 0,
+            _ => self.mode.tagger_count(self.players),
+        }
+    }
+
+    pub fn cycle_mode(&mut self) {
+        self.mode = self.mode.next();
+
+        while !self.mode.allows_players(self.players) {
+            self.players = self.players % player::MAX_PLAYERS + 1;
+        }
+    }
+
+    pub fn cycle_players(&mut self) {
+        self.players = self.players % player::MAX_PLAYERS + 1;
+
+        while !self.mode.allows_players(self.players) {
+            self.players = self.players % player::MAX_PLAYERS + 1;
+        }
+    }
+}
+
+pub struct TeamScores(pub [u32; 2]);
+
+// Sent when a bullet of a tagger hits a runner, game modes decide what it means
+pub struct TagEvent {
+    pub runner: Entity,
+    pub tagger: Entity,
+}
+
+// Sent when a player bounces off an orb
+pub struct OrbEvent {
+    pub player: Entity,
+}
+
+// Run criteria for the systems of a single game mode while a match is played
+pub fn in_mode(mode: GameMode) -> impl FnMut(Res<State<GameState>>, Res<MatchSettings>) -> ShouldRun {
+    move |state: Res<State<GameState>>, settings: Res<MatchSettings>| {
+        if *state.current() == GameState::Game && settings.mode == mode {
+            ShouldRun::Yes
+        } else {
+            ShouldRun::No
+        }
+    }
+}
+
+// Like in_mode, but not on a network client, where the host swaps the roles and ends the match
+pub fn in_mode_as_host(mode: GameMode) -> impl FnMut(Res<State<GameState>>, Res<MatchSettings>, Option<Res<net::NetSession>>) -> ShouldRun {
+    let mut in_mode = in_mode(mode);
+
+    move |state: Res<State<GameState>>, settings: Res<MatchSettings>, session: Option<Res<net::NetSession>>| {
+        match session {
+            Some(session) if !session.is_host() => ShouldRun::No,
+            _ => in_mode(state, settings),
+        }
+    }
+}
+
+pub struct ModePlugin;
+
+impl Plugin for ModePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TagEvent>()
+            .add_event::<OrbEvent>()
+            .add_event::<player::ActionEvent>()
+            .init_resource::<MatchSettings>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(in_mode_as_host(GameMode::Classic))
+                    .with_system(swap_roles.after("collisions"))
+                    .with_system(end_classic_match.after("countdown")),
+            );
+    }
+}
+
+pub fn swap_roles(
+    mut commands: Commands,
+    mut events: EventReader<TagEvent>,
+) {
+    let mut swapped: Vec<Entity> = Vec::new();
+
+    for event in events.iter() {
+        // A second bullet landing in the same frame must not swap the roles back
+        if swapped.contains(&event.runner) || swapped.contains(&event.tagger) {
+            continue;
+        }
+
+        player::become_tagger(&mut commands, event.runner);
+        player::become_runner(&mut commands, event.tagger);
+
+        swapped.push(event.runner);
+        swapped.push(event.tagger);
+    }
+}
+
+// The names of the given players in the order of their ids, used to announce several winners
+pub fn player_names<'a>(players: impl Iterator<Item = &'a player::Player>) -> String {
+    let mut players: Vec<&player::Player> = players.collect();
+    players.sort_by_key(|player| player.id);
+
+    players.iter()
+        .map(|player| player.name())
+        .collect::<Vec<String>>()
+        .join(" & ")
+}
+
+pub fn team_name(team: player::Team) -> String {
+    format!("Team_{}", team.0 + 1)
+}
+
+// The names of the teams with the most points
+pub fn best_teams(scores: &TeamScores) -> String {
+    let best_score = scores.0.iter().copied().max().unwrap_or(0);
+
+    (0..scores.0.len())
+        .filter(|team| scores.0[*team] == best_score)
+        .map(|team| team_name(player::Team(team)))
+        .collect::<Vec<String>>()
+        .join(" & ")
+}
+
+// Whoever isn't the tagger when the time is up wins, unless the match goes into overtime
+pub fn end_classic_match(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    settings: Res<MatchSettings>,
+    timer: Res<crate::game::CountDownTimer>,
+    query: Query<&player::Player, With<player::Runner>>,
+) {
+    if timer.0.just_finished() && !settings.overtime {
+        crate::game::end_match(&mut commands, &mut app_state, player_names(query.iter()));
+    }
+}
@@ -0,0 +1,989 @@
+use std::{io, net::{Ipv4Addr, SocketAddr, UdpSocket}, time::Duration};
+
+use bevy::{prelude::*, ecs::{schedule::ShouldRun, system::SystemParam}};
+use rand::{Rng, rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, game, player, bullet, orb, rollback};
+use crate::mode::{GameMode, MatchSettings};
+
+pub const DEFAULT_PORT: u16 = 7777;
+pub const DISCOVERY_PORT: u16 = 7778;
+pub const ANNOUNCE_INTERVAL: f32 = 1.;
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const MAX_PACKET: usize = 4096;
+pub const SNAPSHOT_INTERVAL: u32 = 3;
+
+// Everything sent between the host and the clients, one message per datagram
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Message {
+    Join,
+    Watch,
+    Leave,
+    Welcome { id: usize },
+    Lobby { players: usize },
+    Start { mode: GameMode, players: usize, overtime: bool, seed: u64 },
+    Intent { id: usize, time: f32, intent: player::Intent },
+    // Orbs and bullets are left out of snapshots recorded before they were part of them
+    Snapshot {
+        time: f32,
+        players: Vec<PlayerState>,
+        #[serde(default)]
+        orbs: Option<Vec<(f32, f32)>>,
+        #[serde(default)]
+        bullets: Option<Vec<BulletState>>,
+    },
+    End { winner: String },
+    Announce(Announcement),
+}
+
+// What a host in the lobby tells everyone on the local network about its game
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Announcement {
+    pub name: String,
+    pub mode: GameMode,
+    pub players: usize,
+    pub port: u16,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlayerState {
+    pub id: usize,
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub velocity: f32,
+    pub tagger: bool,
+    // Seconds until the ability of the player is ready again
+    #[serde(default)]
+    pub cooldown: f32,
+}
+
+// Bullets of bots aren't sent, like the bots themselves
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BulletState {
+    pub shooter: usize,
+    pub x: f32,
+    pub y: f32,
+    pub dx: f32,
+    pub dy: f32,
+    pub radius: f32,
+    pub speed: f32,
+    pub mine: bool,
+}
+
+// Spectators get everything the clients get, but have no player
+pub enum Peer {
+    Host { clients: Vec<SocketAddr>, spectators: Vec<SocketAddr> },
+    Client { host: SocketAddr },
+}
+
+// The host is the first player, the clients follow in the order they joined
+pub struct NetSession {
+    socket: UdpSocket,
+    pub peer: Peer,
+    pub local_id: Option<usize>,
+    pub players: usize,
+    pub spectator: bool,
+    // The seed of the running match, a spectator joining late gets it too
+    pub seed: Option<u64>,
+    first_client_id: usize,
+    tick: u32,
+}
+
+impl NetSession {
+    pub fn host(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        socket.set_broadcast(true)?;
+
+        Ok(Self {
+            socket,
+            peer: Peer::Host { clients: Vec::new(), spectators: Vec::new() },
+            local_id: Some(0),
+            players: 1,
+            spectator: false,
+            seed: None,
+            first_client_id: 1,
+            tick: 0,
+        })
+    }
+
+    // A dedicated server doesn't play itself, the first client is the first player
+    pub fn host_dedicated(port: u16) -> io::Result<Self> {
+        Ok(Self {
+            local_id: None,
+            players: 0,
+            first_client_id: 0,
+            ..Self::host(port)?
+        })
+    }
+
+    pub fn join(host: SocketAddr) -> io::Result<Self> {
+        let session = Self::connect(host, false)?;
+        session.send(&Message::Join, host);
+
+        Ok(session)
+    }
+
+    // Watching works in the lobby as well as in a running match
+    pub fn watch(host: SocketAddr) -> io::Result<Self> {
+        let session = Self::connect(host, true)?;
+        session.send(&Message::Watch, host);
+
+        Ok(session)
+    }
+
+    fn connect(host: SocketAddr, spectator: bool) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            peer: Peer::Client { host },
+            local_id: None,
+            players: 0,
+            spectator,
+            seed: None,
+            first_client_id: 0,
+            tick: 0,
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.peer, Peer::Host { .. })
+    }
+
+    // Lost datagrams are not resent, the next intent or snapshot replaces them anyway
+    pub fn send(&self, message: &Message, addr: SocketAddr) {
+        let bytes = serde_json::to_vec(message).unwrap();
+
+        if let Err(err) = self.socket.send_to(&bytes, addr) {
+            warn!("Failed to send to {}: {}", addr, err);
+        }
+    }
+
+    // The host sends to every client, a client only to the host
+    pub fn broadcast(&self, message: &Message) {
+        match &self.peer {
+            Peer::Host { clients, spectators } => {
+                for client in clients.iter().chain(spectators.iter()) {
+                    self.send(message, *client);
+                }
+            },
+            Peer::Client { host } => self.send(message, *host),
+        }
+    }
+
+    // Usually goes to the broadcast address, which reaches this machine too. Failures are ignored,
+    // a game that can't be announced can still be joined by its address
+    pub fn announce(&self, announcement: Announcement, to: SocketAddr) {
+        let bytes = serde_json::to_vec(&Message::Announce(announcement)).unwrap();
+
+        // Without a network to broadcast on, games on this machine can still find it
+        if self.socket.send_to(&bytes, to).is_err() {
+            let _ = self.socket.send_to(&bytes, (Ipv4Addr::LOCALHOST, to.port()));
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.socket.local_addr().map_or(DEFAULT_PORT, |addr| addr.port())
+    }
+
+    pub fn receive(&self) -> Vec<(Message, SocketAddr)> {
+        let mut messages = Vec::new();
+        let mut buffer = [0; MAX_PACKET];
+
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, addr)) => {
+                    if let Ok(message) = serde_json::from_slice(&buffer[..len]) {
+                        messages.push((message, addr));
+                    }
+                },
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    warn!("Failed to receive: {}", err);
+                    break;
+                },
+            }
+        }
+
+        messages
+    }
+
+    // Sends the current list of players to every client, their ids move up when someone leaves
+    fn update_clients(&mut self) {
+        if let Peer::Host { clients, spectators } = &self.peer {
+            self.players = clients.len() + self.first_client_id;
+
+            for (index, client) in clients.iter().enumerate() {
+                self.send(&Message::Welcome { id: index + self.first_client_id }, *client);
+                self.send(&Message::Lobby { players: self.players }, *client);
+            }
+
+            for spectator in spectators.iter() {
+                self.send(&Message::Lobby { players: self.players }, *spectator);
+            }
+        }
+    }
+
+    fn add_spectator(&mut self, addr: SocketAddr) {
+        if let Peer::Host { spectators, .. } = &mut self.peer {
+            if !spectators.contains(&addr) {
+                spectators.push(addr);
+            }
+        }
+    }
+
+    // The id of the player a datagram came from, only the host checks it
+    fn sender_id(&self, addr: SocketAddr) -> Option<usize> {
+        match &self.peer {
+            Peer::Host { clients, .. } => clients.iter().position(|client| *client == addr).map(|index| index + self.first_client_id),
+            Peer::Client { .. } => None,
+        }
+    }
+}
+
+// Where hosts announce their games and where the LAN menu listens for them
+pub struct DiscoveryConfig {
+    pub listen: SocketAddr,
+    pub announce: SocketAddr,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            listen: SocketAddr::from((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT)),
+            announce: SocketAddr::from((Ipv4Addr::BROADCAST, DISCOVERY_PORT)),
+        }
+    }
+}
+
+// Listens for the announcements of hosts, only one game per machine can listen on a port at a time
+pub struct Discovery {
+    socket: UdpSocket,
+}
+
+impl Discovery {
+    pub fn listen(addr: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self { socket })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    // The address to join is the one the announcement came from, with the port it names
+    pub fn receive(&self) -> Vec<(SocketAddr, Announcement)> {
+        let mut announcements = Vec::new();
+        let mut buffer = [0; MAX_PACKET];
+
+        while let Ok((len, addr)) = self.socket.recv_from(&mut buffer) {
+            if let Ok(Message::Announce(announcement)) = serde_json::from_slice(&buffer[..len]) {
+                announcements.push((SocketAddr::new(addr.ip(), announcement.port), announcement));
+            }
+        }
+
+        announcements
+    }
+}
+
+pub fn host_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map_or("Bevy Tag".to_string(), |user| format!("{}'s game", user))
+}
+
+// Replaces the name announced for the game, e.g. for a dedicated server
+pub struct HostName(pub String);
+
+// The latest intent received for a player on another machine, it is applied every frame until the next one arrives
+#[derive(Component, Default)]
+pub struct Remote(pub player::Intent);
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DiscoveryConfig>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Lobby)
+                    .with_system(update_lobby)
+                    .with_system(announce_host),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(in_network_match)
+                    .with_system(assign_input_sources)
+                    .with_system(receive_match_messages.label("receive").before("intent"))
+                    .with_system(send_intents.after("intent").before("input"))
+                    .with_system(send_snapshot.after("collisions")),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::Game)
+                    .with_system(end_network_match),
+            );
+    }
+}
+
+pub fn in_network_match(state: Res<State<GameState>>, session: Option<Res<NetSession>>) -> ShouldRun {
+    if *state.current() == GameState::Game && session.is_some() {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+// Starts the match for everyone, the shared seed puts the orbs at the same places
+pub fn start_match(
+    session: &mut NetSession,
+    settings: &mut MatchSettings,
+    random: &mut crate::Random,
+    app_state: &mut State<GameState>,
+) {
+    let seed = random.0.gen();
+
+    settings.players = session.players;
+
+    if !settings.mode.allows_players(settings.players) {
+        if let Some(mode) = GameMode::fallback(settings.players) {
+            settings.mode = mode;
+        }
+    }
+
+    session.broadcast(&Message::Start {
+        mode: settings.mode,
+        players: settings.players,
+        overtime: settings.overtime,
+        seed,
+    });
+
+    seed_match(session, random, seed);
+    app_state.push(GameState::Game).unwrap();
+}
+
+// The host and every client start the match from the same seed
+pub fn seed_match(session: &mut NetSession, random: &mut crate::Random, seed: u64) {
+    session.seed = Some(seed);
+    random.0 = StdRng::seed_from_u64(seed);
+}
+
+pub fn update_lobby(
+    session: Option<ResMut<NetSession>>,
+    mut settings: ResMut<MatchSettings>,
+    mut random: ResMut<crate::Random>,
+    mut app_state: ResMut<State<GameState>>,
+) {
+    let mut session = match session {
+        Some(session) => session,
+        None => return,
+    };
+
+    for (message, addr) in session.receive() {
+        match message {
+            Message::Join if session.is_host() => {
+                let is_full = session.players >= player::MAX_PLAYERS;
+
+                if let Peer::Host { clients, .. } = &mut session.peer {
+                    if !clients.contains(&addr) && !is_full {
+                        clients.push(addr);
+                    }
+                }
+
+                session.update_clients();
+            },
+            Message::Watch if session.is_host() => {
+                session.add_spectator(addr);
+                session.send(&Message::Lobby { players: session.players }, addr);
+            },
+            Message::Leave if session.is_host() => {
+                if let Peer::Host { clients, spectators } = &mut session.peer {
+                    clients.retain(|client| *client != addr);
+                    spectators.retain(|spectator| *spectator != addr);
+                }
+
+                session.update_clients();
+            },
+            Message::Welcome { id } => session.local_id = Some(id),
+            Message::Lobby { players } => session.players = players,
+            Message::Start { mode, players, overtime, seed } if !session.is_host() => {
+                settings.mode = mode;
+                settings.players = players;
+                settings.overtime = overtime;
+                seed_match(&mut session, &mut random, seed);
+
+                app_state.push(GameState::Game).unwrap();
+                return;
+            },
+            _ => {},
+        }
+    }
+}
+
+// Hosts are only announced while waiting in the lobby, a running match can't be joined
+pub fn announce_host(
+    time: Res<Time>,
+    settings: Res<MatchSettings>,
+    discovery: Res<DiscoveryConfig>,
+    session: Option<Res<NetSession>>,
+    name: Option<Res<HostName>>,
+    mut since_announced: Local<f32>,
+) {
+    let session = match session {
+        Some(session) if session.is_host() => session,
+        _ => return,
+    };
+
+    *since_announced += time.delta_seconds();
+
+    // The first announcement goes out right away
+    if *since_announced < ANNOUNCE_INTERVAL && !session.is_added() {
+        return;
+    }
+
+    *since_announced = 0.;
+
+    session.announce(Announcement {
+        name: name.map_or_else(host_name, |name| name.0.clone()),
+        mode: settings.mode,
+        players: session.players,
+        port: session.port(),
+        version: VERSION.to_string(),
+    }, discovery.announce);
+}
+
+// The local player plays with the first set of keys, the others are driven by the network
+pub fn assign_input_sources(
+    mut commands: Commands,
+    session: Res<NetSession>,
+    query: Query<(Entity, &player::Player), Added<player::Player>>,
+) {
+    for (entity, player) in query.iter() {
+        if player.id >= player::MAX_PLAYERS {
+            continue;
+        }
+
+        if Some(player.id) == session.local_id {
+            commands.entity(entity).insert(player::PLAYER_KEYS[0]);
+        } else {
+            commands.entity(entity)
+                .remove::<player::Keys>()
+                .insert(Remote::default());
+        }
+    }
+}
+
+// Everything following the match as the host plays it, a client, a spectator or a replay
+#[derive(SystemParam)]
+pub struct MatchView<'w, 's> {
+    commands: Commands<'w, 's>,
+    app_state: ResMut<'w, State<GameState>>,
+    timer: ResMut<'w, game::CountDownTimer>,
+    match_time: ResMut<'w, game::MatchTime>,
+    inputs: EventWriter<'w, 's, rollback::RemoteInput>,
+    query: Query<'w, 's, (Entity, &'static mut player::Player, &'static mut Transform, Option<&'static player::Tagger>, Option<&'static mut player::Cooldown>)>,
+    orb_query: Query<'w, 's, (Entity, &'static Transform), (With<orb::Orb>, Without<player::Player>, Without<bullet::Bullet>)>,
+    bullet_query: Query<'w, 's, (Entity, &'static mut bullet::Bullet, &'static mut Transform, Option<&'static bullet::Mine>), Without<player::Player>>,
+}
+
+impl<'w, 's> MatchView<'w, 's> {
+    pub fn time(&self) -> f32 {
+        self.match_time.0
+    }
+
+    // Returns whether the match is over
+    pub fn apply(&mut self, message: Message) -> bool {
+        match message {
+            Message::Intent { id, time, intent } => {
+                self.inputs.send(rollback::RemoteInput { id, time, intent });
+            },
+            Message::Snapshot { time, players, orbs, bullets } => {
+                let elapsed = time.min(self.timer.0.duration().as_secs_f32());
+                self.timer.0.set_elapsed(Duration::from_secs_f32(elapsed));
+                self.match_time.0 = time;
+
+                // The host decides where everyone is and who is tagger, the others only follow
+                for state in players {
+                    for (entity, mut player, mut transform, tagger, cooldown) in self.query.iter_mut() {
+                        if player.id != state.id {
+                            continue;
+                        }
+
+                        player.set_motion(state.velocity, state.rotation);
+                        transform.translation.x = state.x;
+                        transform.translation.y = state.y;
+
+                        match (state.tagger, tagger.is_some()) {
+                            (true, false) => player::become_tagger(&mut self.commands, entity),
+                            (false, true) => player::become_runner(&mut self.commands, entity),
+                            _ => {},
+                        }
+
+                        match (state.cooldown > 0., cooldown) {
+                            (true, Some(mut cooldown)) => {
+                                let elapsed = (cooldown.0.duration().as_secs_f32() - state.cooldown).max(0.);
+                                cooldown.0.set_elapsed(Duration::from_secs_f32(elapsed));
+                            },
+                            (true, None) => {
+                                self.commands.entity(entity).insert(player::Cooldown(Timer::from_seconds(state.cooldown, false)));
+                            },
+                            (false, Some(_)) => {
+                                self.commands.entity(entity).remove::<player::Cooldown>();
+                            },
+                            (false, None) => {},
+                        }
+                    }
+                }
+
+                if let Some(orbs) = orbs {
+                    self.follow_orbs(&orbs);
+                }
+                if let Some(bullets) = bullets {
+                    self.follow_bullets(&bullets);
+                }
+            },
+            Message::End { winner } => {
+                game::end_match(&mut self.commands, &mut self.app_state, winner);
+                return true;
+            },
+            _ => {},
+        }
+
+        false
+    }
+
+    // Orbs are where the host has them, one picked up here but not on the host comes back
+    fn follow_orbs(&mut self, orbs: &[(f32, f32)]) {
+        let mut missing: Vec<Vec2> = orbs.iter().map(|(x, y)| Vec2::new(*x, *y)).collect();
+
+        for (entity, transform) in self.orb_query.iter() {
+            match missing.iter().position(|pos| pos.distance(transform.translation.truncate()) < 1.) {
+                Some(index) => {
+                    missing.swap_remove(index);
+                },
+                None => self.commands.entity(entity).despawn(),
+            }
+        }
+
+        // Which way an orb spins doesn't matter, it doesn't take a number from the match
+        for pos in missing {
+            let mut spin = crate::Random(StdRng::seed_from_u64(pos.x.to_bits() as u64));
+            orb::spawn_orb_at(&mut self.commands, &mut spin, pos.extend(1.));
+        }
+    }
+
+    // Every bullet takes the place of the closest one the host has of the same shooter,
+    // the ones left over on either side are removed or added
+    fn follow_bullets(&mut self, bullets: &[BulletState]) {
+        let shooters: Vec<(Entity, usize)> = self.query.iter()
+            .map(|(entity, player, _, _, _)| (entity, player.id))
+            .filter(|(_, id)| *id < player::MAX_PLAYERS)
+            .collect();
+        let mut missing: Vec<&BulletState> = bullets.iter().collect();
+
+        for (entity, mut bullet, mut transform, mine) in self.bullet_query.iter_mut() {
+            let shooter = match shooters.iter().find(|(shooter, _)| *shooter == bullet.shooter) {
+                Some((_, id)) => *id,
+                None => continue,
+            };
+
+            let pos = transform.translation.truncate();
+            let closest = missing.iter()
+                .enumerate()
+                .filter(|(_, state)| state.shooter == shooter && state.mine == mine.is_some())
+                .min_by(|(_, a), (_, b)| {
+                    let a = pos.distance(Vec2::new(a.x, a.y));
+                    let b = pos.distance(Vec2::new(b.x, b.y));
+                    a.partial_cmp(&b).unwrap()
+                })
+                .map(|(index, _)| index);
+
+            match closest {
+                Some(index) => {
+                    let state = missing.swap_remove(index);
+
+                    *bullet = bullet::Bullet::new(Vec3::new(state.dx, state.dy, 0.), bullet.shooter, state.radius, state.speed);
+                    transform.translation.x = state.x;
+                    transform.translation.y = state.y;
+                },
+                None => self.commands.entity(entity).despawn(),
+            }
+        }
+
+        for state in missing {
+            let shooter = match shooters.iter().find(|(_, id)| *id == state.shooter) {
+                Some((shooter, _)) => *shooter,
+                None => continue,
+            };
+
+            let pos = Vec3::new(state.x, state.y, 0.);
+
+            if state.mine {
+                bullet::spawn_mine(&mut self.commands, pos, shooter);
+            } else {
+                bullet::spawn_sized_bullet(&mut self.commands, pos, Vec3::new(state.dx, state.dy, 0.), shooter, state.radius, state.speed);
+            }
+        }
+    }
+}
+
+pub fn receive_match_messages(
+    mut session: ResMut<NetSession>,
+    settings: Res<MatchSettings>,
+    mut view: MatchView,
+) {
+    for (message, addr) in session.receive() {
+        // Only the host decides where everyone is and when the match is over
+        if matches!(message, Message::Snapshot { .. } | Message::End { .. }) {
+            if !session.is_host() && view.apply(message) {
+                return;
+            }
+
+            continue;
+        }
+
+        match message {
+            Message::Intent { id, .. } => {
+                // A client may only send the intent of its own player
+                if session.is_host() && session.sender_id(addr) != Some(id) {
+                    continue;
+                }
+
+                // Intents of the local player come back from the host, it already has them
+                if Some(id) != session.local_id {
+                    view.apply(message);
+                }
+            },
+            // A spectator joining late starts from the beginning, the next snapshot catches it up
+            Message::Watch if session.is_host() => {
+                session.add_spectator(addr);
+                session.send(&Message::Start {
+                    mode: settings.mode,
+                    players: settings.players,
+                    overtime: settings.overtime,
+                    seed: session.seed.unwrap_or_default(),
+                }, addr);
+            },
+            Message::Leave if session.is_host() => {
+                if let Peer::Host { spectators, .. } = &mut session.peer {
+                    spectators.retain(|spectator| *spectator != addr);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+pub fn send_intents(
+    session: Res<NetSession>,
+    match_time: Res<game::MatchTime>,
+    query: Query<(&player::Player, &player::Intent)>,
+) {
+    for (player, intent) in query.iter() {
+        if player.id >= player::MAX_PLAYERS {
+            continue;
+        }
+
+        // The host passes on everyone's intent, a client only its own
+        if session.is_host() || Some(player.id) == session.local_id {
+            session.broadcast(&Message::Intent { id: player.id, time: match_time.0, intent: *intent });
+        }
+    }
+}
+
+// Everything a snapshot tells about the match. Bots aren't sent, every machine drives its own
+#[derive(SystemParam)]
+pub struct SnapshotQuery<'w, 's> {
+    players: Query<'w, 's, (&'static player::Player, &'static Transform, Option<&'static player::Tagger>, Option<&'static player::Cooldown>)>,
+    orbs: Query<'w, 's, &'static Transform, With<orb::Orb>>,
+    bullets: Query<'w, 's, (&'static bullet::Bullet, &'static Transform, Option<&'static bullet::Mine>)>,
+}
+
+impl<'w, 's> SnapshotQuery<'w, 's> {
+    pub fn snapshot(&self, time: f32) -> Message {
+        let players = self.players.iter()
+            .filter(|(player, _, _, _)| player.id < player::MAX_PLAYERS)
+            .map(|(player, transform, tagger, cooldown)| PlayerState {
+                id: player.id,
+                x: transform.translation.x,
+                y: transform.translation.y,
+                rotation: player.rotation(),
+                velocity: player.velocity,
+                tagger: tagger.is_some(),
+                cooldown: cooldown.map_or(0., |cooldown| cooldown.0.duration().as_secs_f32() - cooldown.0.elapsed_secs()),
+            })
+            .collect();
+
+        let orbs = self.orbs.iter()
+            .map(|transform| (transform.translation.x, transform.translation.y))
+            .collect();
+
+        let bullets = self.bullets.iter()
+            .filter_map(|(bullet, transform, mine)| {
+                let shooter = self.players.get(bullet.shooter).ok()?.0.id;
+
+                (shooter < player::MAX_PLAYERS).then(|| BulletState {
+                    shooter,
+                    x: transform.translation.x,
+                    y: transform.translation.y,
+                    dx: bullet.dir_vec().x,
+                    dy: bullet.dir_vec().y,
+                    radius: bullet.radius(),
+                    speed: bullet.speed(),
+                    mine: mine.is_some(),
+                })
+            })
+            .collect();
+
+        Message::Snapshot { time, players, orbs: Some(orbs), bullets: Some(bullets) }
+    }
+}
+
+pub fn send_snapshot(
+    mut session: ResMut<NetSession>,
+    match_time: Res<game::MatchTime>,
+    query: SnapshotQuery,
+) {
+    if !session.is_host() {
+        return;
+    }
+
+    session.tick += 1;
+
+    if !session.tick.is_multiple_of(SNAPSHOT_INTERVAL) {
+        return;
+    }
+
+    session.broadcast(&query.snapshot(match_time.0));
+}
+
+// The host tells the clients who won, afterwards everyone is back on their own machine
+pub fn end_network_match(
+    mut commands: Commands,
+    session: Option<Res<NetSession>>,
+    winner: Option<Res<crate::Winner>>,
+) {
+    let session = match session {
+        Some(session) => session,
+        None => return,
+    };
+
+    if let (true, Some(winner)) = (session.is_host(), winner) {
+        session.broadcast(&Message::End { winner: winner.0.clone() });
+    }
+
+    commands.remove_resource::<NetSession>();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, thread, time::Duration};
+
+    use bevy::{prelude::*, ecs::{event::Events, system::SystemState}};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::mode::{GameMode, MatchSettings};
+
+    // Loopback datagrams take a moment, every check is retried for up to a second
+    fn update_until(app: &mut App, mut done: impl FnMut(&mut App) -> bool) {
+        for _ in 0..100 {
+            app.update();
+
+            if done(app) {
+                return;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        panic!("Nothing arrived over the loopback");
+    }
+
+    fn lobby_app(session: NetSession) -> App {
+        let mut app = App::new();
+
+        app.insert_resource(session)
+            .insert_resource(crate::Random(StdRng::seed_from_u64(0)))
+            .init_resource::<MatchSettings>()
+            .add_state(GameState::Lobby)
+            .add_system(update_lobby);
+
+        app
+    }
+
+    fn session(app: &App) -> &NetSession {
+        app.world.get_resource::<NetSession>().unwrap()
+    }
+
+    // A host and a client that went through the handshake
+    fn connect() -> (App, App) {
+        let host = NetSession::host(0).unwrap();
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, host.port()));
+
+        let mut host = lobby_app(host);
+        let mut client = lobby_app(NetSession::join(addr).unwrap());
+
+        update_until(&mut host, |app| session(app).players == 2);
+        update_until(&mut client, |app| session(app).local_id == Some(1) && session(app).players == 2);
+
+        (host, client)
+    }
+
+    #[test]
+    fn join_is_welcomed_into_the_lobby() {
+        let (host, client) = connect();
+
+        assert_eq!(session(&host).local_id, Some(0));
+        assert!(!session(&client).is_host());
+        assert_eq!(session(&client).local_id, Some(1));
+        assert_eq!(session(&client).players, 2);
+    }
+
+    #[test]
+    fn start_reaches_the_client() {
+        let (mut host, mut client) = connect();
+
+        host.world.get_resource_mut::<MatchSettings>().unwrap().overtime = true;
+
+        let mut state: SystemState<(ResMut<NetSession>, ResMut<MatchSettings>, ResMut<crate::Random>, ResMut<State<GameState>>)> =
+            SystemState::new(&mut host.world);
+        let (mut host_session, mut settings, mut random, mut app_state) = state.get_mut(&mut host.world);
+        start_match(&mut host_session, &mut settings, &mut random, &mut app_state);
+
+        update_until(&mut client, |app| *app.world.get_resource::<State<GameState>>().unwrap().current() == GameState::Game);
+
+        let settings = client.world.get_resource::<MatchSettings>().unwrap();
+        assert_eq!(settings.mode, GameMode::Classic);
+        assert_eq!(settings.players, 2);
+        assert!(settings.overtime);
+        assert_eq!(session(&client).seed, session(&host).seed);
+
+        let host_number: u64 = host.world.get_resource_mut::<crate::Random>().unwrap().0.gen();
+        let client_number: u64 = client.world.get_resource_mut::<crate::Random>().unwrap().0.gen();
+        assert_eq!(host_number, client_number, "The host and the client don't share the random numbers");
+    }
+
+    #[test]
+    fn start_falls_back_to_a_mode_for_the_players() {
+        let (mut host, _client) = connect();
+
+        host.world.get_resource_mut::<MatchSettings>().unwrap().mode = GameMode::TimeTrial;
+
+        let mut state: SystemState<(ResMut<NetSession>, ResMut<MatchSettings>, ResMut<crate::Random>, ResMut<State<GameState>>)> =
+            SystemState::new(&mut host.world);
+        let (mut host_session, mut settings, mut random, mut app_state) = state.get_mut(&mut host.world);
+        start_match(&mut host_session, &mut settings, &mut random, &mut app_state);
+
+        let settings = host.world.get_resource::<MatchSettings>().unwrap();
+        assert_eq!(Some(settings.mode), GameMode::fallback(2));
+        assert!(settings.mode.allows_players(2));
+    }
+
+    #[test]
+    fn intent_of_another_player_is_rejected() {
+        let (mut host, client) = connect();
+        let host_session = host.world.remove_resource::<NetSession>().unwrap();
+
+        let mut host = App::new();
+        host.insert_resource(host_session)
+            .init_resource::<MatchSettings>()
+            .insert_resource(game::CountDownTimer::new(60.))
+            .insert_resource(game::MatchTime(0.))
+            .add_event::<rollback::RemoteInput>()
+            .add_state(GameState::Game)
+            .add_system(receive_match_messages);
+
+        // The client is player 1, it can't speak for the host
+        let client = client.world.get_resource::<NetSession>().unwrap();
+        client.broadcast(&Message::Intent { id: 0, time: 0., intent: player::Intent::default() });
+        client.broadcast(&Message::Intent { id: 1, time: 0., intent: player::Intent::default() });
+
+        let mut reader = host.world.get_resource::<Events<rollback::RemoteInput>>().unwrap().get_reader();
+        let mut ids = Vec::new();
+
+        update_until(&mut host, |app| {
+            let events = app.world.get_resource::<Events<rollback::RemoteInput>>().unwrap();
+            ids.extend(reader.iter(events).map(|input| input.id));
+            !ids.is_empty()
+        });
+
+        assert_eq!(ids, vec![1]);
+    }
+
+    #[test]
+    fn snapshot_survives_the_round_trip() {
+        let (host, client) = connect();
+
+        session(&host).broadcast(&Message::Snapshot {
+            time: 12.5,
+            players: vec![PlayerState { id: 1, x: -40., y: 25.5, rotation: 1.25, velocity: 300., tagger: true, cooldown: 0.75 }],
+            orbs: Some(vec![(100., -200.)]),
+            bullets: Some(vec![BulletState { shooter: 0, x: 5., y: 6., dx: 0., dy: 1., radius: 5., speed: 800., mine: false }]),
+        });
+
+        let mut received = None;
+
+        for _ in 0..100 {
+            received = session(&client).receive().into_iter().find_map(|(message, _)| match message {
+                Message::Snapshot { time, players, orbs, bullets } => Some((time, players, orbs, bullets)),
+                _ => None,
+            });
+
+            if received.is_some() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let (time, players, orbs, bullets) = received.expect("The snapshot didn't arrive");
+        assert_eq!(time, 12.5);
+        assert_eq!(players.len(), 1);
+
+        let state = &players[0];
+        assert_eq!((state.id, state.x, state.y, state.rotation), (1, -40., 25.5, 1.25));
+        assert_eq!((state.velocity, state.tagger, state.cooldown), (300., true, 0.75));
+        assert_eq!(orbs, Some(vec![(100., -200.)]));
+
+        let bullets = bullets.expect("The bullets didn't arrive");
+        assert_eq!(bullets.len(), 1);
+        assert_eq!((bullets[0].shooter, bullets[0].x, bullets[0].y, bullets[0].dy, bullets[0].mine), (0, 5., 6., 1., false));
+    }
+
+    #[test]
+    fn announcement_names_the_address_to_join() {
+        let host = NetSession::host(0).unwrap();
+        let discovery = Discovery::listen(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).unwrap();
+
+        let announcement = Announcement {
+            name: "Test game".to_string(),
+            mode: GameMode::HotPotato,
+            players: 1,
+            port: host.port(),
+            version: VERSION.to_string(),
+        };
+        host.announce(announcement.clone(), discovery.local_addr().unwrap());
+
+        let mut received = Vec::new();
+
+        for _ in 0..100 {
+            received = discovery.receive();
+
+            if !received.is_empty() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0, SocketAddr::from((Ipv4Addr::LOCALHOST, host.port())));
+        assert!(received[0].1 == announcement);
+    }
+}
@@ -7,87 +7,151 @@ use heron::prelude::*;
 use rand::Rng;
 
 use crate::game;
+use crate::match_log::{MatchEvent, MatchEventKind};
 
-const ORB_RADIUS: f32 = 38.;
+pub const ORB_RADIUS: f32 = 38.;
+pub const MAX_PLACEMENT_ATTEMPTS: usize = 100;
 
 
 #[derive(Component)]
 pub struct Orb;
 
+pub fn orb_shape() -> shapes::SvgPathShape {
+    shapes::SvgPathShape {
+        svg_doc_size_in_px: Vec2::new(ORB_RADIUS * 2., ORB_RADIUS * 2.),
+        svg_path_string: "M 15 11 L 25 13 Q 30 14 31 12 L 36 2 Q 38 -2 40 2 L 45 12 Q 46 14 51 13 L 62 11 Q 66 10 65 14 L 63 25 Q 62 30 64 31 L 74 36 Q 78 38 74 40 L 64 45 Q 62 46 63 50 L 65 61 Q 66 66 61 65 L 51 63 Q 46 62 45 64 L 40 74 Q 38 78 36 74 L 31 64 Q 30 62 25 63 L 15 65 Q 10 66 11 61 L 13 51 Q 14 46 12 45 L 2 40 Q -2 38 2 36 L 12 31 Q 14 30 13 25 L 11 15 Q 10 10 15 11 Z".to_owned(),
+    }
+}
+
+// Picks a random point of the arena that is at least min_dist away from every taken point.
+// A crowded arena may have none, then the candidate furthest from the taken points is used
+pub fn free_position(
+    random: &mut crate::Random,
+    arena: &game::Arena,
+    margin: f32,
+    taken: &[Vec3],
+    min_dist: f32,
+) -> Vec3 {
+    let x_range = (arena.min.x + margin)..(arena.max.x - margin);
+    let y_range = (arena.min.y + margin)..(arena.max.y - margin);
+
+    let mut best = Vec3::ZERO;
+    let mut best_dist = f32::NEG_INFINITY;
+
+    for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+        let candidate = Vec3::new(random.0.gen_range(x_range.clone()), random.0.gen_range(y_range.clone()), 0.);
+        let dist = taken.iter()
+            .map(|pos| (*pos - candidate.truncate().extend(pos.z)).length())
+            .fold(f32::INFINITY, f32::min);
+
+        if dist >= min_dist {
+            return candidate;
+        }
+
+        if dist > best_dist {
+            best = candidate;
+            best_dist = dist;
+        }
+    }
+
+    best
+}
+
+// Orbs only at these places, once hit they are gone for the rest of the match
+pub struct OrbLayout(pub Vec<Vec2>);
+
 pub fn spawn_orb(
     mut commands: Commands,
     mut random: ResMut<crate::Random>,
-    windows: Res<Windows>,
+    arena: Res<game::Arena>,
+    overtime: Option<Res<crate::overtime::Overtime>>,
+    layout: Option<Res<OrbLayout>>,
+    match_time: Res<game::MatchTime>,
+    mut match_events: EventWriter<MatchEvent>,
+    session: Option<Res<crate::net::NetSession>>,
     query: Query<&Transform, With<Orb>>,
+    zone_query: Query<&Transform, (With<crate::king_of_the_hill::CaptureZone>, Without<Orb>)>,
 ) {
-    let primary_window = windows.get_primary().unwrap();
-    let window_width = primary_window.width();
-    let window_height = primary_window.height();
+    // The orbs removed by the shrinking arena don't come back
+    if overtime.is_some() {
+        return;
+    }
+
+    // A client only has the orbs the host sends it
+    if session.is_some_and(|session| !session.is_host()) {
+        return;
+    }
 
-    let mut orbs: Vec<Transform> = query.iter().cloned().collect();
+    if let Some(layout) = layout {
+        if layout.is_added() {
+            for pos in layout.0.iter() {
+                spawn_orb_at(&mut commands, &mut random, Vec3::new(pos.x, pos.y, 1.));
+                match_events.send(MatchEvent { time: match_time.0, kind: MatchEventKind::OrbSpawn { x: pos.x, y: pos.y } });
+            }
+        }
+
+        return;
+    }
+
+    let mut orbs: Vec<Vec3> = query.iter().map(|orb| orb.translation).collect();
     let orb_count = query.iter().count();
 
-    let desired_orb_count = ((window_width * (window_height - 50.)) / (ORB_RADIUS * 7.).powf(2.)).floor() as usize;
+    // New orbs stay out of the capture zone as well
+    orbs.extend(zone_query.iter().map(|zone| zone.translation));
+
+    let desired_orb_count = ((arena.size().x * arena.size().y) / (ORB_RADIUS * 7.).powf(2.)).floor() as usize;
     let num_of_orbs_needed =  desired_orb_count - orb_count; 
     if num_of_orbs_needed == 0 {return}
 
-    let shape = shapes::SvgPathShape {
-        svg_doc_size_in_px: Vec2::new(ORB_RADIUS * 2., ORB_RADIUS * 2.),
-        svg_path_string: "M 15 11 L 25 13 Q 30 14 31 12 L 36 2 Q 38 -2 40 2 L 45 12 Q 46 14 51 13 L 62 11 Q 66 10 65 14 L 63 25 Q 62 30 64 31 L 74 36 Q 78 38 74 40 L 64 45 Q 62 46 63 50 L 65 61 Q 66 66 61 65 L 51 63 Q 46 62 45 64 L 40 74 Q 38 78 36 74 L 31 64 Q 30 62 25 63 L 15 65 Q 10 66 11 61 L 13 51 Q 14 46 12 45 L 2 40 Q -2 38 2 36 L 12 31 Q 14 30 13 25 L 11 15 Q 10 10 15 11 Z".to_owned(),
-    };
-    let x_dist = window_width / 2.;
-    let y_dist = window_height / 2. - 50.;
-
     for _ in 0..num_of_orbs_needed {
-        let mut x = random.0.gen_range((-x_dist + ORB_RADIUS*2.)..(x_dist - ORB_RADIUS*2.));
-        let mut y = random.0.gen_range((-y_dist + ORB_RADIUS*2.)..(y_dist - ORB_RADIUS*2.));
-
-        while orbs.iter().any(|orb| (orb.translation - Vec3::new(x, y, 1.)).length() < ORB_RADIUS*4.) {
-            x = random.0.gen_range((-x_dist + ORB_RADIUS*2.)..(x_dist - ORB_RADIUS*2.));
-            y = random.0.gen_range((-y_dist + ORB_RADIUS*2.)..(y_dist - ORB_RADIUS*2.));
-        }
+        let pos = free_position(&mut random, &arena, ORB_RADIUS*2., &orbs, ORB_RADIUS*4.);
+        let translation = Vec3::new(pos.x, pos.y, 1.);
 
-        let transform = Transform {
-            translation: Vec3::new(x, y, 1.),
-            scale: Vec3::new(0., 0., 1.),
-            ..Default::default()
-        };
-
-        let rotation_dir = random.0.gen_range((-1. as f32)..(1. as f32)).signum();
-
-        let rotation_tween = Tween::new(
-            EaseMethod::Linear,
-            TweeningType::Loop,
-            Duration::from_millis(2000),
-            lens::TransformRotationLens {
-                start: Quat::from_rotation_z(0.),
-                end: Quat::from_rotation_z(rotation_dir * 6.28),
-            },
-        );
-        let scale_tween = Tween::new(
-            EaseFunction::QuadraticOut,
-            TweeningType::Once,
-            Duration::from_millis(300),
-            lens::TransformScaleLens {
-                start: Vec3::new(0., 0., 1.),
-                end: Vec3::new(1., 1., 1.),
-            },
-        );
-
-        commands.spawn_bundle(GeometryBuilder::build_as(
-            &shape,
-            DrawMode::Outlined {
-                outline_mode: StrokeMode::new(game::ORB_OUTLINE_COLOR, 5.),
-                fill_mode: FillMode::color(game::ORB_FILL_COLOR)
-            },
-            transform,
-        ))
-        .insert(Animator::new(scale_tween.then(rotation_tween)))
-        .insert(RigidBody::Static)
-        .insert(CollisionShape::Sphere{radius: ORB_RADIUS})
-        .insert(Orb)
-        .insert(game::GameEntity);
-
-        orbs.push(transform);
+        spawn_orb_at(&mut commands, &mut random, translation);
+        orbs.push(translation);
+        match_events.send(MatchEvent { time: match_time.0, kind: MatchEventKind::OrbSpawn { x: pos.x, y: pos.y } });
     }
 }
+
+pub fn spawn_orb_at(commands: &mut Commands, random: &mut crate::Random, translation: Vec3) {
+    let transform = Transform {
+        translation,
+        scale: Vec3::new(0., 0., 1.),
+        ..Default::default()
+    };
+
+    let rotation_dir = random.0.gen_range((-1. as f32)..(1. as f32)).signum();
+
+    let rotation_tween = Tween::new(
+        EaseMethod::Linear,
+        TweeningType::Loop,
+        Duration::from_millis(2000),
+        lens::TransformRotationLens {
+            start: Quat::from_rotation_z(0.),
+            end: Quat::from_rotation_z(rotation_dir * 6.28),
+        },
+    );
+    let scale_tween = Tween::new(
+        EaseFunction::QuadraticOut,
+        TweeningType::Once,
+        Duration::from_millis(300),
+        lens::TransformScaleLens {
+            start: Vec3::new(0., 0., 1.),
+            end: Vec3::new(1., 1., 1.),
+        },
+    );
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &orb_shape(),
+        DrawMode::Outlined {
+            outline_mode: StrokeMode::new(game::ORB_OUTLINE_COLOR, 5.),
+            fill_mode: FillMode::color(game::ORB_FILL_COLOR)
+        },
+        transform,
+    ))
+    .insert(Animator::new(scale_tween.then(rotation_tween)))
+    .insert(RigidBody::Static)
+    .insert(CollisionShape::Sphere{radius: ORB_RADIUS})
+    .insert(Orb)
+    .insert(game::GameEntity);
+}
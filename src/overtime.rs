@@ -0,0 +1,315 @@
+use bevy::{prelude::*, utils::Duration};
+use bevy_kira_audio::Audio;
+use bevy_prototype_lyon::prelude::*;
+use bevy_tweening::*;
+use heron::prelude::*;
+
+use crate::{GameState, game, player, orb, ability};
+use crate::mode::{self, GameMode, MatchSettings, TagEvent};
+
+pub const OVERTIME_DUR: f32 = 30.;
+pub const MIN_ARENA_SCALE: f32 = 0.25;
+pub const STUN_DUR: f32 = 1.;
+pub const WALL_THICKNESS: f32 = 20.;
+pub const BOUNDARY_COL: Color = Color::RED;
+
+// Sudden death after the clock ran out, the arena keeps shrinking until the first tag
+pub struct Overtime {
+    timer: Timer,
+    full_arena: game::Arena,
+}
+
+#[derive(Component)]
+pub struct Boundary;
+
+// One of the four sides of the boundary, the direction points away from the arena
+#[derive(Component)]
+pub struct BoundaryWall(Vec2);
+
+pub struct OvertimePlugin;
+
+impl Plugin for OvertimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::Classic))
+                .with_system(start_overtime.after("countdown"))
+                .with_system(shrink_arena.label("shrink").before("move"))
+                .with_system(remove_orbs_outside.after("shrink"))
+                .with_system(stun_on_boundary.after("collisions")),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::Classic))
+                .with_system(end_overtime.after("collisions")),
+        );
+    }
+}
+
+pub fn start_overtime(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    timer: Res<game::CountDownTimer>,
+    arena: Res<game::Arena>,
+    audio: Res<Audio>,
+    timer_sound: Res<crate::TimerSound>,
+) {
+    if !settings.overtime || !timer.0.just_finished() {
+        return;
+    }
+
+    audio.play(timer_sound.0.clone());
+
+    commands.insert_resource(Overtime {
+        timer: Timer::from_seconds(OVERTIME_DUR, false),
+        full_arena: *arena,
+    });
+
+    let size = arena.size();
+    let center = arena.center();
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::Rectangle {
+            extents: size,
+            origin: shapes::RectangleOrigin::Center,
+        },
+        DrawMode::Stroke(StrokeMode::new(BOUNDARY_COL, 3.)),
+        Transform::from_xyz(center.x, center.y, 2.),
+    ))
+    .insert(Boundary)
+    .insert(game::GameEntity);
+
+    // The walls are as long as the full arena, so they cover the corners while moving inwards
+    for dir in [Vec2::X, -Vec2::X, Vec2::Y, -Vec2::Y] {
+        let half_extends = if dir.x != 0. {
+            Vec3::new(WALL_THICKNESS / 2., size.y / 2. + WALL_THICKNESS, 0.)
+        } else {
+            Vec3::new(size.x / 2. + WALL_THICKNESS, WALL_THICKNESS / 2., 0.)
+        };
+
+        commands.spawn_bundle((
+            Transform::from_translation(wall_position(&arena, dir)),
+            GlobalTransform::default(),
+        ))
+        .insert(RigidBody::Sensor)
+        .insert(CollisionShape::Cuboid { half_extends, border_radius: None })
+        .insert(CollisionLayers::new(game::Layer::Boundary, game::Layer::Runner).with_mask(game::Layer::Tagger))
+        .insert(BoundaryWall(dir))
+        .insert(game::GameEntity);
+    }
+}
+
+// The walls are placed right outside of the arena, so a player standing on the edge touches them
+fn wall_position(arena: &game::Arena, dir: Vec2) -> Vec3 {
+    let offset = dir * (arena.size() / 2. + Vec2::splat(WALL_THICKNESS / 2.));
+    let pos = arena.center() + offset;
+
+    Vec3::new(pos.x, pos.y, 0.)
+}
+
+pub fn shrink_arena(
+    time: Res<Time>,
+    overtime: Option<ResMut<Overtime>>,
+    mut arena: ResMut<game::Arena>,
+    mut boundary_query: Query<&mut Path, With<Boundary>>,
+    mut wall_query: Query<(&mut Transform, &BoundaryWall)>,
+) {
+    let mut overtime = match overtime {
+        Some(overtime) => overtime,
+        None => return,
+    };
+
+    overtime.timer.tick(time.delta());
+
+    let scale = 1. - (1. - MIN_ARENA_SCALE) * overtime.timer.percent();
+    *arena = overtime.full_arena.scaled(scale);
+
+    for mut path in boundary_query.iter_mut() {
+        *path = ShapePath::build_as(&shapes::Rectangle {
+            extents: arena.size(),
+            origin: shapes::RectangleOrigin::Center,
+        });
+    }
+
+    for (mut transform, wall) in wall_query.iter_mut() {
+        transform.translation = wall_position(&arena, wall.0);
+    }
+}
+
+pub fn remove_orbs_outside(
+    mut commands: Commands,
+    arena: Res<game::Arena>,
+    query: Query<(Entity, &Transform), With<orb::Orb>>,
+) {
+    for (entity, transform) in query.iter() {
+        if arena.contains(transform.translation) {
+            continue;
+        }
+
+        let tween = Tween::new(
+            EaseFunction::QuadraticIn,
+            TweeningType::Once,
+            Duration::from_millis(300),
+            lens::TransformScaleLens {
+                start: Vec3::new(1., 1., 1.),
+                end: Vec3::new(0., 0., 1.),
+            },
+        )
+        .with_completed_event(true, 0);
+
+        commands.entity(entity)
+            .remove::<orb::Orb>()
+            .remove::<CollisionShape>()
+            .remove::<RigidBody>()
+            .insert(Animator::new(tween))
+            .insert(ability::Effect);
+    }
+}
+
+pub fn stun_on_boundary(
+    mut commands: Commands,
+    mut events: EventReader<CollisionEvent>,
+    audio: Res<Audio>,
+    orb_sound: Res<crate::OrbSound>,
+    wall_query: Query<(), With<BoundaryWall>>,
+    mut player_query: Query<&mut player::Player, Without<player::Stunned>>,
+) {
+    for event in events.iter() {
+        if let CollisionEvent::Started(data1, data2) = event {
+            let entity1 = data1.rigid_body_entity();
+            let entity2 = data2.rigid_body_entity();
+
+            let player_entity = if wall_query.get(entity1).is_ok() {
+                entity2
+            } else if wall_query.get(entity2).is_ok() {
+                entity1
+            } else {
+                continue;
+            };
+
+            if let Ok(mut player) = player_query.get_mut(player_entity) {
+                audio.play(orb_sound.0.clone());
+                player::stun(&mut commands, player_entity, &mut player, STUN_DUR);
+            }
+        }
+    }
+}
+
+// The first tag of the overtime decides the match, if nobody gets tagged the runners win
+pub fn end_overtime(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    mut events: EventReader<TagEvent>,
+    overtime: Option<Res<Overtime>>,
+    player_query: Query<&player::Player>,
+    runner_query: Query<&player::Player, With<player::Runner>>,
+) {
+    // Tags from before the overtime are read too, so they don't count once it starts
+    let taggers: Vec<Entity> = events.iter().map(|event| event.tagger).collect();
+
+    let overtime = match overtime {
+        Some(overtime) => overtime,
+        None => return,
+    };
+
+    if let Some(tagger) = taggers.first().and_then(|tagger| player_query.get(*tagger).ok()) {
+        game::end_match(&mut commands, &mut app_state, tagger.name());
+    }
+    else if overtime.timer.just_finished() {
+        game::end_match(&mut commands, &mut app_state, mode::player_names(runner_query.iter()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{MatchPlugins, Random, gym, headless};
+
+    // Frames of overtime played, long enough for the walls to sweep over the orbs next to the edges
+    const OVERTIME_FRAMES: usize = 300;
+
+    fn overtime_app() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(headless::HeadlessPlugin)
+            .add_plugins(MatchPlugins)
+            .add_plugin(gym::GymPlugin)
+            .add_state(GameState::MainMenu);
+
+        app.update();
+
+        {
+            let mut settings = app.world.get_resource_mut::<MatchSettings>().unwrap();
+            settings.mode = GameMode::Classic;
+            settings.players = 2;
+            settings.overtime = true;
+        }
+
+        app.world.get_resource_mut::<Random>().unwrap().0 = StdRng::seed_from_u64(5);
+        app.world.get_resource_mut::<State<GameState>>().unwrap().push(GameState::Game).unwrap();
+        app.update();
+
+        app
+    }
+
+    // Moves orbs right inside of every edge, in the way of the walls once they move
+    fn move_orbs_to_edges(app: &mut App) {
+        let arena = *app.world.get_resource::<game::Arena>().unwrap();
+        let inset = orb::ORB_RADIUS / 2.;
+        let positions = [
+            Vec3::new(arena.max.x - inset, 0., 1.),
+            Vec3::new(arena.min.x + inset, 0., 1.),
+            Vec3::new(0., arena.max.y - inset, 1.),
+            Vec3::new(0., arena.min.y + inset, 1.),
+        ];
+
+        let mut query = app.world.query_filtered::<&mut Transform, With<orb::Orb>>();
+        let mut moved = 0;
+
+        for (mut transform, pos) in query.iter_mut(&mut app.world).zip(positions) {
+            transform.translation = pos;
+            moved += 1;
+        }
+
+        assert_eq!(moved, positions.len(), "There aren't enough orbs on the board");
+    }
+
+    fn wall_count(app: &mut App) -> usize {
+        app.world.query_filtered::<(), With<BoundaryWall>>().iter(&app.world).count()
+    }
+
+    #[test]
+    fn walls_survive_sweeping_over_orbs() {
+        let mut app = overtime_app();
+
+        // Lets the orbs grow to their full size before the clock runs out
+        for _ in 0..30 {
+            app.update();
+        }
+
+        move_orbs_to_edges(&mut app);
+
+        {
+            let mut timer = app.world.get_resource_mut::<game::CountDownTimer>().unwrap();
+            let duration = timer.0.duration();
+            timer.0.set_elapsed(duration - Duration::from_millis(1));
+        }
+
+        app.update();
+        assert!(app.world.get_resource::<Overtime>().is_some(), "The overtime didn't start");
+        assert_eq!(wall_count(&mut app), 4);
+
+        for frame in 0..OVERTIME_FRAMES {
+            app.update();
+
+            if *app.world.get_resource::<State<GameState>>().unwrap().current() != GameState::Game {
+                break;
+            }
+
+            assert_eq!(wall_count(&mut app), 4, "A wall is gone after {} frames of overtime", frame);
+        }
+    }
+}
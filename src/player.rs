@@ -1,10 +1,12 @@
-use bevy::{prelude::*, utils::Duration};
+use bevy::prelude::*;
 use bevy_kira_audio::Audio;
 use bevy_prototype_lyon::prelude::*;
 use heron::prelude::*;
 use bevy_tweening::*;
-use crate::bullet;
-use crate::game;
+use serde::{Deserialize, Serialize};
+use crate::ability::{self, Ability, Role};
+use crate::{bullet, game, mode, stats, trap};
+use crate::match_log::{MatchEvent, MatchEventKind};
 
 pub const MAX_SPEED: f32 = 400.;
 pub const LIN_VEL: f32 = 1600.;
@@ -14,22 +16,11 @@ pub const DASH_SPEED: f32 = 1600.;
 pub const DASH_DUR: f32 = 0.1;
 pub const COOLDOWN_DUR: f32 = 1.0;
 
-pub struct DashTimer(pub Timer);
-impl DashTimer {
-    pub fn new(seconds: f32) -> Self {Self(Timer::from_seconds(seconds, false))}
-}
-
-pub struct CooldownTimer(pub Timer);
-impl CooldownTimer {
-    pub fn new(seconds: f32) -> Self {Self(Timer::from_seconds(seconds, false))}
-}
-
-
 #[derive(Component)]
-pub struct Dash;
+pub struct Dash(pub Timer);
 
 #[derive(Component)]
-pub struct Cooldown;
+pub struct Cooldown(pub Timer);
 
 #[derive(Component)]
 pub struct Tagger;
@@ -37,12 +28,18 @@ pub struct Tagger;
 #[derive(Component)]
 pub struct Runner;
 
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct Team(pub usize);
+
+// A stunned player can't move or use their ability for a moment, but can still be tagged
 #[derive(Component)]
-pub struct Player1;
+pub struct Stunned(pub Timer);
 
+// A frozen player can neither move nor use their ability
 #[derive(Component)]
-pub struct Player2;
+pub struct Frozen;
 
+#[derive(Component, Clone, Copy)]
 pub struct Keys {
     up: KeyCode,
     down: KeyCode,
@@ -51,259 +48,564 @@ pub struct Keys {
     action: KeyCode,
 }
 
+impl Keys {
+    pub fn read(&self, keys: &Input<KeyCode>) -> Intent {
+        Intent {
+            up: keys.pressed(self.up),
+            down: keys.pressed(self.down),
+            left: keys.pressed(self.left),
+            right: keys.pressed(self.right),
+            action: keys.pressed(self.action),
+            ..Default::default()
+        }
+    }
+}
+
+pub const MAX_PLAYERS: usize = 6;
+
+pub const PLAYER_KEYS: [Keys; MAX_PLAYERS] = [
+    Keys {
+        up: KeyCode::W,
+        down: KeyCode::S,
+        left: KeyCode::A,
+        right: KeyCode::D,
+        action: KeyCode::Space,
+    },
+    Keys {
+        up: KeyCode::Up,
+        down: KeyCode::Down,
+        left: KeyCode::Left,
+        right: KeyCode::Right,
+        action: KeyCode::Return,
+    },
+    Keys {
+        up: KeyCode::I,
+        down: KeyCode::K,
+        left: KeyCode::J,
+        right: KeyCode::L,
+        action: KeyCode::O,
+    },
+    Keys {
+        up: KeyCode::Numpad8,
+        down: KeyCode::Numpad5,
+        left: KeyCode::Numpad4,
+        right: KeyCode::Numpad6,
+        action: KeyCode::Numpad0,
+    },
+    Keys {
+        up: KeyCode::T,
+        down: KeyCode::G,
+        left: KeyCode::F,
+        right: KeyCode::H,
+        action: KeyCode::R,
+    },
+    Keys {
+        up: KeyCode::Home,
+        down: KeyCode::End,
+        left: KeyCode::Delete,
+        right: KeyCode::PageDown,
+        action: KeyCode::Insert,
+    },
+];
+
+// What a player wants to do in the current frame, read from the keyboard or decided by a bot
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Intent {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub action: bool,
+    #[serde(skip)]
+    prev_action: bool,
+}
+
+impl Intent {
+    // Replaces the intent of the last frame, remembering its action for the just pressed/released checks
+    pub fn update(&mut self, next: Intent) {
+        let prev_action = self.action;
+
+        *self = next;
+        self.prev_action = prev_action;
+    }
+
+    pub fn action_just_pressed(&self) -> bool {
+        self.action && !self.prev_action
+    }
+
+    pub fn action_just_released(&self) -> bool {
+        !self.action && self.prev_action
+    }
+}
+
+// The things a player can do, sent every frame a movement key is held and once per ability use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind {
+    Forward,
+    Backward,
+    TurnLeft,
+    TurnRight,
+    Ability(Ability),
+    Dodge,
+}
+
+pub struct ActionEvent {
+    pub player: Entity,
+    pub kind: ActionKind,
+}
+
+// Scales the top speed, used to make the bots of later waves faster
+#[derive(Component)]
+pub struct SpeedFactor(pub f32);
+
 #[derive(Component)]
 pub struct Player {
+    pub id: usize,
     pub velocity: f32,
     dir_vec: Vec3,
     rotation: f32,
-    keys: Keys,
 }
 
 impl Player {
-    pub fn new(keys: Keys) -> Self {
+    pub fn new(id: usize) -> Self {
         Self {
+            id,
             dir_vec: Vec3::ZERO,
             velocity: 0.,
             rotation: 0.,
-            keys,
         }
     }
-}
 
-struct DrawColorLens {
-    start: Vec3, // Vec3 because it can be subtracted and mulitplied
-    end: Vec3, // Vec3 because it can be subtracted and mulitplied
-}
+    pub fn name(&self) -> String {
+        player_name(self.id)
+    }
 
-impl Lens<DrawMode> for DrawColorLens {
-    fn lerp(&mut self, target: &mut DrawMode, ratio: f32) -> () {
-        if let DrawMode::Fill(ref mut fill_mode) = *target {
-            let diff = (self.end - self.start) * ratio;
-            let curr = self.start + diff;
+    pub fn heading(&self) -> Vec3 {
+        get_direction_vec(self.rotation)
+    }
 
-            fill_mode.color = Color::rgb(curr.x, curr.y, curr.z);
-        }
+    pub fn look_at(&mut self, dir: Vec2) {
+        self.rotation = (-dir.x).atan2(dir.y);
     }
-}
 
-pub fn spawn_players(
-    mut commands: Commands,
-    windows: Res<Windows>,
-) {
-    // DEFINING SHARED PLAYER PROPERTIES
-    let shape = shapes::SvgPathShape {
-        svg_doc_size_in_px: Vec2::new(31.7, 44.),
-        svg_path_string: "M 1.2681 34.1401 Q -1.6782 43.8592 4.2145 43.8592 L 27.7855 43.8592 Q 33.6782 43.8592 30.7319 34.1401 L 21.8927 8.2224 Q 16 -7.9761 10.1073 8.2224".to_owned()
-    };
+    pub fn reset_motion(&mut self) {
+        self.velocity = 0.;
+        self.rotation = 0.;
+    }
 
-    let primary_window = windows.get_primary().unwrap();
-    let y_pos = 30. - (primary_window.height() / 2.);
-    let x_dist = primary_window.width() / 4.;
-    
-    // SPAWNING PLAYER 1
-    commands.spawn_bundle(GeometryBuilder::build_as(
-        &shape,
-        DrawMode::Fill(FillMode::color(game::TAGGER_COL)),
-        Transform {
-            translation: Vec3::new(-x_dist, y_pos, 1.),
-            ..Default::default()
-        },
-    ))
-    .insert(RigidBody::KinematicPositionBased)
-    .insert(CollisionShape::Capsule{radius: 10., half_segment: 12.})
-    .insert(CollisionLayers::new(game::Layer::Tagger, game::Layer::Tagger))
-    .insert(Player::new(Keys {
-        up: KeyCode::W,
-        down: KeyCode::S,
-        left: KeyCode::A,
-        right: KeyCode::D,
-        action: KeyCode::Space,
-    }))
-    .insert(Tagger)
-    .insert(Player1)
-    .insert(game::GameEntity);
-
-    // SPAWNING PLAYER 2
-    commands.spawn_bundle(GeometryBuilder::build_as(
-        &shape,
-        DrawMode::Fill(FillMode::color(game::RUNNER_COL)),
-        Transform {
-            translation: Vec3::new(x_dist, y_pos, 1.),
-            ..Default::default()
-        },
-    ))
-    .insert(RigidBody::KinematicPositionBased)
-    .insert(CollisionShape::Capsule{radius: 10., half_segment: 10.})
-    .insert(CollisionLayers::new(game::Layer::Runner, game::Layer::Runner))
-    .insert(Player::new(Keys {
-        up: KeyCode::Up,
-        down: KeyCode::Down,
-        left: KeyCode::Left,
-        right: KeyCode::Right,
-        action: KeyCode::Return,
-    }))
-    .insert(Runner)
-    .insert(Player2)
-    .insert(game::GameEntity);
-}
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
 
-pub fn handle_move_inputs(
-    keys: Res<Input<KeyCode>>,
-    time: Res<Time>,
-    mut query: Query<(&mut Player, Option<&Dash>)>,
-) {
-    for (mut player, is_dashing) in query.iter_mut() {
-        if is_dashing.is_none() {
+    pub fn set_motion(&mut self, velocity: f32, rotation: f32) {
+        self.velocity = velocity;
+        self.rotation = rotation;
+    }
+
+    // The speed and rotation change of one frame, the rollback re-simulates frames with it as well
+    pub fn steer(&mut self, intent: &Intent, is_dashing: bool, max_speed: f32, delta: f32) {
+        if !is_dashing {
             // Handling forwards/backwards movement
-            if keys.pressed(player.keys.up) {
-                player.velocity += LIN_VEL * time.delta_seconds();
+            if intent.up {
+                self.velocity += LIN_VEL * delta;
             }
-            if keys.pressed(player.keys.down) {
-                player.velocity += -LIN_VEL * time.delta_seconds();
+            if intent.down {
+                self.velocity += -LIN_VEL * delta;
             }
-    
+
             // Slowing the player down if not moving
-            if !keys.pressed(player.keys.up) && !keys.pressed(player.keys.down) {
-                player.velocity *= 1. - (DUMP_VEL * time.delta_seconds())
+            if !intent.up && !intent.down {
+                self.velocity *= 1. - (DUMP_VEL * delta)
             }
 
             // Making sure it doesn't accelerate to high
-            player.velocity = player.velocity.clamp(-MAX_SPEED, MAX_SPEED);
+            self.velocity = self.velocity.clamp(-max_speed, max_speed);
         }
 
         // Handling right/left movement
-        if keys.pressed(player.keys.right) {
-            player.rotation -= ANG_VEL * time.delta_seconds();
+        if intent.right {
+            self.rotation -= ANG_VEL * delta;
         }
-        if keys.pressed(player.keys.left) {
-            player.rotation += ANG_VEL * time.delta_seconds();
+        if intent.left {
+            self.rotation += ANG_VEL * delta;
         }
     }
+
+    // Where the player ends up after moving for one frame
+    pub fn advance(&mut self, translation: Vec3, arena: &game::Arena, delta: f32) -> Vec3 {
+        self.dir_vec = get_direction_vec(self.rotation);
+
+        arena.clamp(translation + self.dir_vec * self.velocity * delta)
+    }
 }
 
+pub fn player_name(id: usize) -> String {
+    format!("Player_{}", id + 1)
+}
 
-pub fn handle_action_button_for_tagger(
+// The players on the left of the top bar are in the first team, the rest in the second
+pub fn team_of(id: usize, players: usize) -> Team {
+    Team(if id < players.div_ceil(2) {0} else {1})
+}
+
+pub fn spawn_players(
     mut commands: Commands,
+    arena: Res<game::Arena>,
+    loadouts: Res<ability::Loadouts>,
+    settings: Res<mode::MatchSettings>,
+) {
+    let y_pos = arena.min.y + 30.;
+    let x_step = arena.size().x / settings.players as f32;
+    let tagger_count = settings.tagger_count();
+
+    // SPAWNING THE PLAYERS, THE FIRST ONES START AS TAGGERS
+    for (id, keys) in PLAYER_KEYS.iter().enumerate().take(settings.players) {
+        let x_pos = arena.min.x + x_step * (id as f32 + 0.5);
+        let role = if id < tagger_count {Role::Tagger} else {Role::Runner};
+
+        // Players that skipped the loadout menu, like the ones of a network match, play the default one
+        let loadout = loadouts.0.get(id).copied().unwrap_or_default();
+        let entity = spawn_player(&mut commands, id, Vec3::new(x_pos, y_pos, 1.), role, loadout);
+
+        commands.entity(entity).insert(*keys);
+
+        if settings.mode.has_teams() {
+            commands.entity(entity).insert(team_of(id, settings.players));
+        }
+    }
+}
+
+// Everything a player needs to move, collide and use its abilities, no matter what drives it
+pub fn spawn_player(
+    commands: &mut Commands,
+    id: usize,
+    translation: Vec3,
+    role: Role,
+    loadout: ability::Loadout,
+) -> Entity {
+    let mut entity = commands.spawn_bundle(GeometryBuilder::build_as(
+        &player_shape(),
+        DrawMode::Fill(FillMode::color(role.color())),
+        Transform::from_translation(translation),
+    ));
+
+    entity
+        .insert(RigidBody::KinematicPositionBased)
+        .insert(CollisionShape::Capsule{radius: 10., half_segment: 10.})
+        .insert(Player::new(id))
+        .insert(Intent::default())
+        .insert(loadout)
+        .insert(role_layers(role))
+        .insert(game::GameEntity);
+
+    match role {
+        Role::Tagger => entity.insert(Tagger),
+        Role::Runner => entity.insert(Runner),
+    };
+
+    entity.id()
+}
+
+// Both roles are seen by the capture zone and the boundary, only taggers run into slow fields
+pub fn role_layers(role: Role) -> CollisionLayers {
+    let layers = match role {
+        Role::Tagger => CollisionLayers::new(game::Layer::Tagger, game::Layer::Tagger).with_mask(game::Layer::Trap),
+        Role::Runner => CollisionLayers::new(game::Layer::Runner, game::Layer::Runner),
+    };
+
+    layers.with_mask(game::Layer::Zone).with_mask(game::Layer::Boundary)
+}
+
+// Drops everything tied to the current role, so the player starts the new one fresh
+fn reset_role_state(commands: &mut Commands, entity: Entity) {
+    commands.entity(entity)
+        .remove::<Runner>()
+        .remove::<Tagger>()
+        .remove::<Dash>()
+        .remove::<Cooldown>()
+        .remove::<ability::Charging>()
+        .remove::<Animator<Transform>>()
+        .remove::<Animator<DrawMode>>()
+        .remove::<CollisionLayers>();
+}
+
+pub fn become_tagger(commands: &mut Commands, entity: Entity) {
+    reset_role_state(commands, entity);
+
+    commands.entity(entity)
+        .insert(Tagger)
+        .insert(role_layers(Role::Tagger))
+        .insert(DrawMode::Fill(FillMode::color(game::TAGGER_COL)));
+}
+
+pub fn become_runner(commands: &mut Commands, entity: Entity) {
+    reset_role_state(commands, entity);
+
+    commands.entity(entity)
+        .insert(Runner)
+        .insert(role_layers(Role::Runner))
+        .insert(DrawMode::Fill(FillMode::color(game::RUNNER_COL)));
+}
+
+pub fn read_key_intents(
     keys: Res<Input<KeyCode>>,
+    mut query: Query<(&Keys, &mut Intent)>,
+) {
+    for (player_keys, mut intent) in query.iter_mut() {
+        intent.update(player_keys.read(&keys));
+    }
+}
+
+pub fn handle_move_inputs(
+    time: Res<Time>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut query: Query<(Entity, &mut Player, &Intent, Option<&Dash>, Option<&trap::Slowed>, Option<&SpeedFactor>), (Without<Frozen>, Without<Stunned>)>,
+) {
+    for (entity, mut player, intent, is_dashing, is_slowed, speed_factor) in query.iter_mut() {
+        let held = [
+            (intent.up, ActionKind::Forward),
+            (intent.down, ActionKind::Backward),
+            (intent.left, ActionKind::TurnLeft),
+            (intent.right, ActionKind::TurnRight),
+        ];
+
+        for (_, kind) in held.into_iter().filter(|(pressed, _)| *pressed) {
+            action_events.send(ActionEvent { player: entity, kind });
+        }
+
+        let max_speed = max_speed(is_slowed.is_some(), speed_factor);
+        player.steer(intent, is_dashing.is_some(), max_speed, time.delta_seconds());
+    }
+}
+
+pub fn max_speed(is_slowed: bool, speed_factor: Option<&SpeedFactor>) -> f32 {
+    let max_speed = if is_slowed {MAX_SPEED * trap::SLOW_FACTOR} else {MAX_SPEED};
+    max_speed * speed_factor.map_or(1., |factor| factor.0)
+}
+
+pub fn handle_action_button_for_tagger(
+    mut commands: Commands,
     audio: Res<Audio>,
-    shoot_sound: Res<crate::ShootSound>,
-    query: Query<(Entity, &Player, &Transform), With<Tagger>>,
+    ability_sounds: Res<ability::AbilitySounds>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut match_events: EventWriter<MatchEvent>,
+    match_time: Res<game::MatchTime>,
+    query: Query<(Entity, &Player, &Intent, &Transform, &ability::Loadout, Option<&ability::Charging>), (With<Tagger>, Without<Cooldown>, Without<Frozen>, Without<Stunned>)>,
+    mine_query: Query<&bullet::Bullet, With<bullet::Mine>>,
 ) {
-    let (entity, player, transform) = query.single();
-
-    if keys.just_pressed(player.keys.action) {
-        audio.play(shoot_sound.0.clone());
-        
-        bullet::spawn_bullet(
-            &mut commands,
-            transform.translation.clone(),
-            get_direction_vec(player.rotation),
-            entity
-        );
+    for (entity, player, intent, transform, loadout, charging) in query.iter() {
+        let ability = loadout.tagger;
+
+        let used = match ability {
+            Ability::ChargedShot => match charging {
+                Some(charging) if intent.action_just_released() => {
+                    ability::release_charge(&mut commands, entity, player, transform, charging);
+                    true
+                },
+                None if intent.action_just_pressed() => {
+                    ability::start_charge(&mut commands, entity);
+                    false
+                },
+                _ => false,
+            },
+            Ability::Mine if intent.action_just_pressed() => {
+                let mine_count = mine_query.iter().filter(|mine| mine.shooter == entity).count();
+
+                if mine_count < bullet::MAX_MINES {
+                    bullet::spawn_mine(&mut commands, transform.translation, entity);
+                    true
+                } else {
+                    false
+                }
+            },
+            Ability::RadarPulse if intent.action_just_pressed() => {
+                ability::radar_pulse(&mut commands, transform);
+                true
+            },
+            _ if intent.action_just_pressed() => {
+                ability::shoot(&mut commands, entity, player, transform);
+                true
+            },
+            _ => false,
+        };
+
+        if used {
+            audio.play(ability_sounds.get(ability));
+            action_events.send(ActionEvent { player: entity, kind: ActionKind::Ability(ability) });
+            ability::start_cooldown(&mut commands, entity, ability.cooldown(), Role::Tagger);
+
+            // Everything but the pulse puts a bullet out
+            if ability != Ability::RadarPulse {
+                match_events.send(MatchEvent {
+                    time: match_time.0,
+                    kind: MatchEventKind::Shot { player: player.id, ability, x: transform.translation.x, y: transform.translation.y, rotation: player.rotation },
+                });
+            }
+        }
     }
 }
 
 pub fn handle_action_button_for_runner(
     mut commands: Commands,
-    keys: Res<Input<KeyCode>>,
     audio: Res<Audio>,
-    dash_sound: Res<crate::DashSound>,
-    mut query: Query<(Entity, &mut Player), (With<Runner>, Without<Dash>, Without<Cooldown>)>,
+    ability_sounds: Res<ability::AbilitySounds>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut match_events: EventWriter<MatchEvent>,
+    match_time: Res<game::MatchTime>,
+    mut query: Query<(Entity, &mut Player, &Intent, &mut Transform, &ability::Loadout, &DrawMode), (With<Runner>, Without<Dash>, Without<Cooldown>, Without<Frozen>, Without<Stunned>)>,
+    trap_query: Query<&trap::Trap>,
 ) {
-    if let Ok((entity, mut player)) = query.get_single_mut() {        
-        if keys.just_pressed(player.keys.action) {
-            commands.entity(entity).insert(Dash);
-            player.velocity = DASH_SPEED;
-            audio.play(dash_sound.0.clone());
-
-            let tween = Tween::new(
-                EaseFunction::QuadraticInOut,
-                TweeningType::PingPong,
-                Duration::from_millis((DASH_DUR*500.) as u64),
-                lens::TransformScaleLens {
-                    start: Vec3::new(1., 1., 1.),
-                    end: Vec3::new(0.2, 1., 1.),
+    for (entity, mut player, intent, mut transform, loadout, draw_mode) in query.iter_mut() {
+        if intent.action_just_pressed() {
+            let ability = loadout.runner;
+            let trap_count = trap_query.iter().filter(|trap| trap.owner == entity).count();
+
+            let used = match ability {
+                Ability::Shield => {
+                    ability::shield(&mut commands, entity);
+                    true
+                },
+                Ability::Blink => {
+                    ability::blink(&mut commands, &mut transform, &player);
+                    true
+                },
+                Ability::Decoy => {
+                    ability::decoy(&mut commands, &player, &transform, *draw_mode);
+                    true
                 },
-            );
-            commands.entity(entity).insert(Animator::new(tween));
-
-            let tween = Tween::new(
-                EaseFunction::CircularIn,
-                TweeningType::Once,
-                Duration::from_secs((COOLDOWN_DUR + DASH_DUR) as u64),
-                DrawColorLens {
-                    start: Vec3::new(1., 1., 1.),
-                    end: Vec3::new(107./255., 186./255., 93./255.)
+                Ability::SlowField | Ability::Barrier if trap_count >= trap::MAX_TRAPS => false,
+                Ability::SlowField => {
+                    trap::spawn_slow_field(&mut commands, entity, &player, &transform);
+                    true
                 },
-            );
-            commands.entity(entity).insert(Animator::new(tween));
+                Ability::Barrier => {
+                    trap::spawn_barrier(&mut commands, entity, &player, &transform);
+                    true
+                },
+                _ => {
+                    ability::dash(&mut commands, entity, &mut player);
+                    match_events.send(MatchEvent {
+                        time: match_time.0,
+                        kind: MatchEventKind::DashStart { player: player.id, x: transform.translation.x, y: transform.translation.y },
+                    });
+                    true
+                },
+            };
+
+            if used {
+                audio.play(ability_sounds.get(ability));
+                action_events.send(ActionEvent { player: entity, kind: ActionKind::Ability(ability) });
+                ability::start_cooldown(&mut commands, entity, ability.cooldown(), Role::Runner);
+            }
         }
     }
 }
 
 pub fn move_players(
     mut query: Query<(&mut Transform, &mut Player)>,
-    windows: Res<Windows>,
+    arena: Res<game::Arena>,
     time: Res<Time>,
+    mut stats: ResMut<stats::MatchStats>,
 ) {
-    let primary_window = windows.get_primary().unwrap();
-    let x_dist = primary_window.width() / 2.;
-    let y_dist = primary_window.height() / 2.;
-
     for (mut transform, mut player) in query.iter_mut() {
-        player.dir_vec = get_direction_vec(player.rotation);
+        let translation = player.advance(transform.translation, &arena, time.delta_seconds());
 
-        transform.translation += player.dir_vec * player.velocity * time.delta_seconds();
-        
-        if transform.translation.x.abs() > x_dist {
-            transform.translation.x = transform.translation.x.signum() * x_dist;
-        }
-        if transform.translation.y < -y_dist {
-            transform.translation.y = -y_dist;
-        }
-        else if transform.translation.y > y_dist-50. {
-            transform.translation.y = y_dist-50.;
+        if let Some(stats) = stats.player(player.id) {
+            stats.distance += translation.truncate().distance(transform.translation.truncate());
         }
 
+        transform.translation = translation;
         transform.rotation = Quat::from_rotation_z(player.rotation);
     }
 }
 
+pub fn stun(commands: &mut Commands, entity: Entity, player: &mut Player, seconds: f32) {
+    player.velocity = 0.;
+    commands.entity(entity).insert(Stunned(Timer::from_seconds(seconds, false)));
+}
+
+pub fn handle_stun_timer(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Stunned)>
+) {
+    for (entity, mut stunned) in query.iter_mut() {
+        stunned.0.tick(time.delta());
+
+        if stunned.0.just_finished() {
+            commands.entity(entity).remove::<Stunned>();
+        }
+    }
+}
+
 pub fn handle_dash_timer_for_runner(
     mut commands: Commands,
     time: Res<Time>,
-    mut timer: ResMut<DashTimer>,
-    query: Query<Entity, With<Dash>>
+    match_time: Res<game::MatchTime>,
+    mut match_events: EventWriter<MatchEvent>,
+    mut query: Query<(Entity, &Player, &Transform, &mut Dash)>
 ) { 
-    if let Ok(entity) = query.get_single() {
-        timer.0.tick(time.delta());
+    for (entity, player, transform, mut dash) in query.iter_mut() {
+        dash.0.tick(time.delta());
     
-        if timer.0.just_finished() {
-            commands.entity(entity).insert(Cooldown);
+        if dash.0.just_finished() {
             commands.entity(entity).remove::<Dash>();
             commands.entity(entity).remove::<Animator<Transform>>();
-            timer.0.reset();
+
+            match_events.send(MatchEvent {
+                time: match_time.0,
+                kind: MatchEventKind::DashEnd { player: player.id, x: transform.translation.x, y: transform.translation.y },
+            });
         }
     }
 }
 
-pub fn handle_cooldown_timer_for_runner(
+// The roles the players start with aren't swaps
+pub fn log_role_swaps(
+    match_time: Res<game::MatchTime>,
+    mut match_events: EventWriter<MatchEvent>,
+    query: Query<(&Player, ChangeTrackers<Player>, &Transform, Option<&Tagger>), Or<(Added<Tagger>, Added<Runner>)>>,
+) {
+    for (player, tracker, transform, tagger) in query.iter() {
+        if tracker.is_added() {
+            continue;
+        }
+
+        match_events.send(MatchEvent {
+            time: match_time.0,
+            kind: MatchEventKind::RoleSwap {
+                player: player.id,
+                role: if tagger.is_some() {Role::Tagger} else {Role::Runner},
+                x: transform.translation.x,
+                y: transform.translation.y,
+            },
+        });
+    }
+}
+
+pub fn handle_cooldown_timer(
     mut commands: Commands,
     time: Res<Time>,
-    mut timer: ResMut<CooldownTimer>,
-    query: Query<Entity, With<Cooldown>>
+    mut query: Query<(Entity, &mut Cooldown)>
 ) { 
-    if let Ok(entity) = query.get_single() {
-        timer.0.tick(time.delta());
+    for (entity, mut cooldown) in query.iter_mut() {
+        cooldown.0.tick(time.delta());
     
-        if timer.0.just_finished() {
+        if cooldown.0.just_finished() {
             commands.entity(entity).remove::<Cooldown>();
             commands.entity(entity).remove::<Animator<DrawMode>>();
-            timer.0.reset();
         }
     }
 }
 
+pub fn player_shape() -> shapes::SvgPathShape {
+    shapes::SvgPathShape {
+        svg_doc_size_in_px: Vec2::new(31.7, 44.),
+        svg_path_string: "M 1.2681 34.1401 Q -1.6782 43.8592 4.2145 43.8592 L 27.7855 43.8592 Q 33.6782 43.8592 30.7319 34.1401 L 21.8927 8.2224 Q 16 -7.9761 10.1073 8.2224".to_owned()
+    }
+}
+
 pub fn get_direction_vec(angle: f32) -> Vec3 {
     let x = -angle.sin();
     let y = angle.cos();
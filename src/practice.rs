@@ -0,0 +1,255 @@
+use bevy::{prelude::*, math::const_vec2};
+
+use crate::{GameState, game, player, ability, bullet, time_trial};
+use crate::ability::Role;
+use crate::mode::{self, GameMode, MatchSettings, TagEvent};
+
+pub const DUMMY_FIRE_INTERVAL: f32 = 1.5;
+pub const DUMMY_SPEED: f32 = 0.4;
+pub const RESET_KEY: KeyCode = KeyCode::Back;
+pub const LEAVE_KEY: KeyCode = KeyCode::Escape;
+
+pub const STANDING_RUNNERS: [Vec2; 3] = [
+    const_vec2!([-250., 150.]),
+    const_vec2!([0., 250.]),
+    const_vec2!([250., 150.]),
+];
+pub const PATROLS: [[Vec2; 2]; 2] = [
+    [const_vec2!([-300., -100.]), const_vec2!([300., -100.])],
+    [const_vec2!([200., 50.]), const_vec2!([-200., 50.])],
+];
+pub const DUMMY_TAGGERS: [Vec2; 4] = [
+    const_vec2!([-300., 250.]),
+    const_vec2!([300., 250.]),
+    const_vec2!([-300., -100.]),
+    const_vec2!([300., -100.]),
+];
+
+// Runners to shoot at or taggers to dodge, they never swap roles
+#[derive(Component)]
+pub struct Dummy;
+
+// Where the entity goes back to when the positions are reset
+#[derive(Component)]
+pub struct Home(Vec3);
+
+pub struct PracticeStats {
+    pub shots: u32,
+    pub hits: u32,
+    pub dodges: u32,
+}
+
+impl PracticeStats {
+    pub fn misses(&self) -> u32 {
+        self.shots.saturating_sub(self.hits)
+    }
+}
+
+pub struct PracticePlugin;
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::Practice))
+                .with_system(setup_practice)
+                .with_system(time_trial::drive_scripted_runner.label("intent"))
+                .with_system(fire_dummy_taggers.after("move"))
+                .with_system(count_shots)
+                .with_system(count_hits.after("collisions"))
+                .with_system(count_dodges.after("collisions"))
+                .with_system(reset_positions.before("move"))
+                .with_system(update_practice_status.after("collisions"))
+                .with_system(leave_practice),
+        );
+    }
+}
+
+// Puts up the dummies for the role the player picked once the player is spawned
+pub fn setup_practice(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    query: Query<(Entity, &Transform), (Added<player::Player>, Without<Dummy>)>,
+) {
+    if query.is_empty() {
+        return;
+    }
+
+    for (entity, transform) in query.iter() {
+        commands.entity(entity).insert(Home(transform.translation));
+    }
+
+    commands.insert_resource(PracticeStats { shots: 0, hits: 0, dodges: 0 });
+
+    match settings.practice_role {
+        Role::Tagger => {
+            for pos in STANDING_RUNNERS {
+                spawn_dummy(&mut commands, pos, Role::Runner);
+            }
+
+            for patrol in PATROLS {
+                let dummy = spawn_dummy(&mut commands, patrol[0], Role::Runner);
+
+                commands.entity(dummy)
+                    .insert(player::SpeedFactor(DUMMY_SPEED))
+                    .insert(time_trial::ScriptedRunner::new(patrol.to_vec()));
+            }
+        },
+        Role::Runner => {
+            for pos in DUMMY_TAGGERS {
+                spawn_dummy(&mut commands, pos, Role::Tagger);
+            }
+        },
+    }
+}
+
+pub fn spawn_dummy(commands: &mut Commands, pos: Vec2, role: Role) -> Entity {
+    let translation = Vec3::new(pos.x, pos.y, 1.);
+    let entity = player::spawn_player(commands, player::MAX_PLAYERS, translation, role, ability::Loadout::default());
+
+    commands.entity(entity)
+        .insert(Home(translation))
+        .insert(Dummy);
+
+    entity
+}
+
+// The dummy taggers turn towards the player and fire whenever their cooldown is over
+pub fn fire_dummy_taggers(
+    mut commands: Commands,
+    mut dummy_query: Query<(Entity, &mut player::Player, &Transform), (With<Dummy>, With<player::Tagger>, Without<player::Cooldown>)>,
+    target_query: Query<&Transform, (With<player::Runner>, Without<Dummy>)>,
+) {
+    let target = match target_query.iter().next() {
+        Some(target) => target.translation,
+        None => return,
+    };
+
+    for (entity, mut dummy, transform) in dummy_query.iter_mut() {
+        let dir = (target - transform.translation).truncate().normalize_or_zero();
+        dummy.look_at(dir);
+
+        bullet::spawn_bullet(&mut commands, transform.translation, dir.extend(0.), entity);
+        ability::start_cooldown(&mut commands, entity, DUMMY_FIRE_INTERVAL, Role::Tagger);
+    }
+}
+
+// Tagger practice counts the shots of the player, runner practice the ones of the dummies at the player
+pub fn count_shots(
+    settings: Res<MatchSettings>,
+    stats: Option<ResMut<PracticeStats>>,
+    query: Query<&bullet::Bullet, Added<bullet::Bullet>>,
+    dummy_query: Query<(), With<Dummy>>,
+) {
+    let mut stats = match stats {
+        Some(stats) => stats,
+        None => return,
+    };
+
+    let by_dummies = settings.practice_role == Role::Runner;
+
+    stats.shots += query.iter()
+        .filter(|bullet| dummy_query.get(bullet.shooter).is_ok() == by_dummies)
+        .count() as u32;
+}
+
+// Nobody swaps roles in practice, a hit only shows up in the counter.
+// Only tags the player is part of count, the dummies also tag each other
+pub fn count_hits(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    mut events: EventReader<TagEvent>,
+    stats: Option<ResMut<PracticeStats>>,
+    query: Query<&Transform>,
+    dummy_query: Query<(), With<Dummy>>,
+) {
+    let runners: Vec<Entity> = events.iter()
+        .filter(|event| match settings.practice_role {
+            Role::Tagger => dummy_query.get(event.tagger).is_err(),
+            Role::Runner => dummy_query.get(event.runner).is_err(),
+        })
+        .map(|event| event.runner)
+        .collect();
+
+    let mut stats = match stats {
+        Some(stats) => stats,
+        None => return,
+    };
+
+    for runner in runners {
+        if let Ok(transform) = query.get(runner) {
+            ability::spawn_burst(&mut commands, transform.translation, game::RUNNER_COL);
+        }
+
+        stats.hits += 1;
+    }
+}
+
+// A bullet the player dashes through is a dodge, one that just flies past isn't
+pub fn count_dodges(
+    mut events: EventReader<player::ActionEvent>,
+    stats: Option<ResMut<PracticeStats>>,
+    dummy_query: Query<(), With<Dummy>>,
+) {
+    let dodges = events.iter()
+        .filter(|event| event.kind == player::ActionKind::Dodge && dummy_query.get(event.player).is_err())
+        .count() as u32;
+
+    if let Some(mut stats) = stats {
+        stats.dodges += dodges;
+    }
+}
+
+pub fn reset_positions(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut query: Query<(&mut player::Player, &mut Transform, &Home)>,
+    bullet_query: Query<Entity, With<bullet::Bullet>>,
+) {
+    if !keys.just_pressed(RESET_KEY) {
+        return;
+    }
+
+    for (mut player, mut transform, home) in query.iter_mut() {
+        player.reset_motion();
+        transform.translation = home.0;
+    }
+
+    for entity in bullet_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn update_practice_status(
+    settings: Res<MatchSettings>,
+    stats: Option<Res<PracticeStats>>,
+    mut query: Query<&mut Text, With<game::ModeStatus>>,
+) {
+    let stats = match stats {
+        Some(stats) => stats,
+        None => return,
+    };
+
+    let value = match settings.practice_role {
+        Role::Tagger => format!("Hits {}  Misses {}", stats.hits, stats.misses()),
+        Role::Runner => format!("Dodged {}  Hit {}", stats.dodges, stats.hits),
+    };
+
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+// Practice has no end, the player leaves whenever they want
+pub fn leave_practice(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    keys: Res<Input<KeyCode>>,
+) {
+    // A change of state already on its way wins, the key can be pressed again
+    if keys.just_pressed(LEAVE_KEY) && app_state.push(GameState::MainMenu).is_ok() {
+        commands.remove_resource::<crate::Winner>();
+    }
+}
@@ -0,0 +1,306 @@
+use std::{collections::VecDeque, fs::{self, File}, io::{BufRead, BufReader, BufWriter, Write}, path::PathBuf, time::SystemTime};
+
+use bevy::prelude::*;
+use rand::{Rng, rngs::StdRng, SeedableRng};
+
+use crate::{GameState, game, mode, net, player, rollback};
+
+// Every match is written into this directory, read from `--record <dir>`
+pub struct RecordReplays(pub PathBuf);
+
+impl RecordReplays {
+    pub fn from_args() -> Option<Self> {
+        arg_value("--record").map(|dir| Self(dir.into()))
+    }
+}
+
+// Played instead of showing the main menu, read from `--replay <file>`
+pub struct PendingReplay(pub PathBuf);
+
+impl PendingReplay {
+    pub fn from_args() -> Option<Self> {
+        arg_value("--replay").map(|file| Self(file.into()))
+    }
+}
+
+// A replay holds the messages a spectator would get, one per line
+pub struct Recorder {
+    file: BufWriter<File>,
+    tick: u32,
+    // Only changed intents are written, a replayed player keeps doing what it did last
+    intents: Vec<Option<player::Intent>>,
+}
+
+impl Recorder {
+    fn write(&mut self, message: &net::Message) {
+        let result = serde_json::to_writer(&mut self.file, message)
+            .map_err(std::io::Error::from)
+            .and_then(|_| self.file.write_all(b"\n"));
+
+        if let Err(err) = result {
+            warn!("Failed to write the replay: {}", err);
+        }
+    }
+}
+
+pub struct Playback {
+    messages: VecDeque<net::Message>,
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::MainMenu)
+                .with_system(start_replay),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Game)
+                .with_system(start_recording.after("setup_game")),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Game)
+                .with_system(hand_over_players)
+                .with_system(play_replay.label("receive").before("intent"))
+                .with_system(record_intents.after("intent").before("input"))
+                .with_system(record_snapshot.after("collisions")),
+        )
+        .add_system_set(
+            SystemSet::on_pause(GameState::Game)
+                .with_system(finish_replay),
+        );
+    }
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+}
+
+// The file is read at once, a replay only holds a single match
+pub fn load_replay(path: &PathBuf) -> Result<VecDeque<net::Message>, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+
+    BufReader::new(file).lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| {
+            let line = line.map_err(|err| err.to_string())?;
+            serde_json::from_str(&line).map_err(|err| err.to_string())
+        })
+        .collect()
+}
+
+pub fn start_replay(
+    mut commands: Commands,
+    pending: Option<Res<PendingReplay>>,
+    mut settings: ResMut<mode::MatchSettings>,
+    mut random: ResMut<crate::Random>,
+    mut app_state: ResMut<State<GameState>>,
+) {
+    let pending = match pending {
+        Some(pending) => pending,
+        None => return,
+    };
+
+    commands.remove_resource::<PendingReplay>();
+
+    let mut messages = match load_replay(&pending.0) {
+        Ok(messages) => messages,
+        Err(err) => {
+            warn!("Failed to load the replay {}: {}", pending.0.display(), err);
+            return;
+        },
+    };
+
+    // The match is set up like on the machine it was recorded on
+    match messages.pop_front() {
+        Some(net::Message::Start { mode, players, overtime, seed }) => {
+            settings.mode = mode;
+            settings.players = players;
+            settings.overtime = overtime;
+            random.0 = StdRng::seed_from_u64(seed);
+
+            commands.insert_resource(Playback { messages });
+            app_state.push(GameState::Game).unwrap();
+        },
+        _ => warn!("The replay {} doesn't start with a match", pending.0.display()),
+    }
+}
+
+// A local match gets a seed of its own, so the replay puts the orbs at the same places
+pub fn start_recording(
+    mut commands: Commands,
+    dir: Option<Res<RecordReplays>>,
+    playback: Option<Res<Playback>>,
+    session: Option<Res<net::NetSession>>,
+    settings: Res<mode::MatchSettings>,
+    mut random: ResMut<crate::Random>,
+) {
+    let dir = match dir {
+        Some(dir) if playback.is_none() => dir,
+        _ => return,
+    };
+
+    let seed = match session.and_then(|session| session.seed) {
+        Some(seed) => seed,
+        None => {
+            let seed = settings.mode.fixed_seed().unwrap_or_else(|| random.0.gen());
+            random.0 = StdRng::seed_from_u64(seed);
+            seed
+        },
+    };
+
+    let started = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let path = dir.0.join(format!("replay-{}.jsonl", started));
+
+    let file = fs::create_dir_all(&dir.0).and_then(|_| File::create(&path));
+
+    let mut recorder = match file {
+        Ok(file) => Recorder {
+            file: BufWriter::new(file),
+            tick: 0,
+            intents: Vec::new(),
+        },
+        Err(err) => {
+            warn!("Failed to create the replay {}: {}", path.display(), err);
+            return;
+        },
+    };
+
+    recorder.write(&net::Message::Start {
+        mode: settings.mode,
+        players: settings.players,
+        overtime: settings.overtime,
+        seed,
+    });
+
+    info!("Recording the match to {}", path.display());
+    commands.insert_resource(recorder);
+}
+
+pub fn record_intents(
+    recorder: Option<ResMut<Recorder>>,
+    match_time: Res<game::MatchTime>,
+    query: Query<(&player::Player, &player::Intent)>,
+) {
+    let mut recorder = match recorder {
+        Some(recorder) => recorder,
+        None => return,
+    };
+
+    for (player, intent) in query.iter() {
+        if player.id >= player::MAX_PLAYERS {
+            continue;
+        }
+
+        if recorder.intents.len() <= player.id {
+            recorder.intents.resize(player.id + 1, None);
+        }
+
+        if recorder.intents[player.id].is_some_and(|last| rollback::same_keys(&last, intent)) {
+            continue;
+        }
+
+        recorder.intents[player.id] = Some(*intent);
+        recorder.write(&net::Message::Intent { id: player.id, time: match_time.0, intent: *intent });
+    }
+}
+
+// Snapshots are written as often as a host sends them
+pub fn record_snapshot(
+    recorder: Option<ResMut<Recorder>>,
+    match_time: Res<game::MatchTime>,
+    query: net::SnapshotQuery,
+) {
+    let mut recorder = match recorder {
+        Some(recorder) => recorder,
+        None => return,
+    };
+
+    recorder.tick += 1;
+
+    if !recorder.tick.is_multiple_of(net::SNAPSHOT_INTERVAL) {
+        return;
+    }
+
+    recorder.write(&query.snapshot(match_time.0));
+}
+
+// Every player of a replay is driven by what was recorded
+pub fn hand_over_players(
+    mut commands: Commands,
+    playback: Option<Res<Playback>>,
+    query: Query<(Entity, &player::Player), Added<player::Player>>,
+) {
+    if playback.is_none() {
+        return;
+    }
+
+    for (entity, player) in query.iter() {
+        if player.id >= player::MAX_PLAYERS {
+            continue;
+        }
+
+        commands.entity(entity)
+            .remove::<player::Keys>()
+            .insert(net::Remote::default());
+    }
+}
+
+// Messages are played once the match reaches the time they were recorded at
+pub fn play_replay(
+    playback: Option<ResMut<Playback>>,
+    mut view: net::MatchView,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    while let Some(message) = playback.messages.front() {
+        let is_due = match message {
+            net::Message::Intent { time, .. } | net::Message::Snapshot { time, .. } => *time <= view.time(),
+            _ => true,
+        };
+
+        if !is_due {
+            break;
+        }
+
+        let message = playback.messages.pop_front().unwrap();
+
+        if view.apply(message) {
+            return;
+        }
+    }
+}
+
+pub fn finish_replay(
+    mut commands: Commands,
+    recorder: Option<ResMut<Recorder>>,
+    winner: Option<Res<crate::Winner>>,
+) {
+    commands.remove_resource::<Playback>();
+
+    let mut recorder = match recorder {
+        Some(recorder) => recorder,
+        None => return,
+    };
+
+    if let Some(winner) = winner {
+        recorder.write(&net::Message::End { winner: winner.0.clone() });
+    }
+
+    if let Err(err) = recorder.file.flush() {
+        warn!("Failed to write the replay: {}", err);
+    }
+
+    commands.remove_resource::<Recorder>();
+}
@@ -0,0 +1,959 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::{prelude::*, ecs::{schedule::ShouldRun, system::{CommandQueue, Resource}, event::{Events, ManualEventReader}, world::EntityMut}};
+use bevy_kira_audio::Audio;
+use heron::{prelude::*, CollisionData};
+use rand::{Rng, rngs::StdRng, SeedableRng};
+
+use crate::{GameState, Random, game, player, bullet, orb, ability, mode, stats, net, trap, replay};
+use crate::match_log::MatchEvent;
+
+pub const MAX_ROLLBACK_FRAMES: usize = 30;
+
+// An input of a player on another machine, stamped with the match time it was made at
+#[derive(Clone, Copy)]
+pub struct RemoteInput {
+    pub id: usize,
+    pub time: f32,
+    pub intent: player::Intent,
+}
+
+#[derive(Clone)]
+pub struct PlayerFrame {
+    pub entity: Entity,
+    pub id: usize,
+    pub translation: Vec3,
+    pub velocity: f32,
+    pub rotation: f32,
+    // The intent left from the frame before, and the one the frame was played with
+    pub previous_intent: player::Intent,
+    pub intent: player::Intent,
+    pub is_tagger: bool,
+    pub dash: Option<Timer>,
+    pub cooldown: Option<Timer>,
+    pub stunned: Option<Timer>,
+    pub charging: Option<Timer>,
+    pub shielded: bool,
+}
+
+#[derive(Clone)]
+pub struct BulletFrame {
+    pub entity: Entity,
+    pub bullet: bullet::Bullet,
+    pub translation: Vec3,
+    pub mine: Option<Timer>,
+}
+
+// What a frame started from, along with the inputs and time step it was played with.
+// Decoys, traps and pulses aren't put back, the ones made in the frames played again are removed
+#[derive(Clone)]
+pub struct Snapshot {
+    pub time: f32,
+    pub clock: Time,
+    pub countdown: Timer,
+    pub random: StdRng,
+    pub players: Vec<PlayerFrame>,
+    pub bullets: Vec<BulletFrame>,
+    pub orbs: Vec<(Entity, Vec3)>,
+    pub extras: Vec<Entity>,
+    // The tags and orb pickups the modes were told about in the frame
+    pub tags: Vec<(Entity, Entity)>,
+    pub pickups: Vec<Entity>,
+}
+
+impl Snapshot {
+    pub fn take(world: &mut World) -> Self {
+        let players = world.query::<(Entity, &player::Player, &Transform, &player::Intent, Option<&player::Tagger>, Option<&player::Dash>, Option<&player::Cooldown>, Option<&player::Stunned>, Option<&ability::Charging>, Option<&ability::Shield>)>()
+            .iter(world)
+            .map(|(entity, player, transform, intent, tagger, dash, cooldown, stunned, charging, shield)| PlayerFrame {
+                entity,
+                id: player.id,
+                translation: transform.translation,
+                velocity: player.velocity,
+                rotation: player.rotation(),
+                previous_intent: *intent,
+                intent: *intent,
+                is_tagger: tagger.is_some(),
+                dash: dash.map(|dash| dash.0.clone()),
+                cooldown: cooldown.map(|cooldown| cooldown.0.clone()),
+                stunned: stunned.map(|stunned| stunned.0.clone()),
+                charging: charging.map(|charging| charging.0.clone()),
+                shielded: shield.is_some(),
+            })
+            .collect();
+
+        let bullets = world.query::<(Entity, &bullet::Bullet, &Transform, Option<&bullet::Mine>)>()
+            .iter(world)
+            .map(|(entity, bullet, transform, mine)| BulletFrame {
+                entity,
+                bullet: bullet.clone(),
+                translation: transform.translation,
+                mine: mine.map(|mine| mine.0.clone()),
+            })
+            .collect();
+
+        let orbs = world.query_filtered::<(Entity, &Transform), With<orb::Orb>>()
+            .iter(world)
+            .map(|(entity, transform)| (entity, transform.translation))
+            .collect();
+
+        let extras = world.query_filtered::<Entity, Or<(With<ability::Decoy>, With<trap::Trap>, With<ability::Pulse>)>>()
+            .iter(world)
+            .collect();
+
+        Self {
+            time: world.get_resource::<game::MatchTime>().unwrap().0,
+            clock: world.get_resource::<Time>().unwrap().clone(),
+            countdown: world.get_resource::<game::CountDownTimer>().unwrap().0.clone(),
+            random: world.get_resource::<Random>().unwrap().0.clone(),
+            players,
+            bullets,
+            orbs,
+            extras,
+            tags: Vec::new(),
+            pickups: Vec::new(),
+        }
+    }
+
+    // Takes the state a frame played again ended with, the frame's own inputs and time step stay
+    fn start_from(&mut self, mut state: Snapshot) {
+        for frame in state.players.iter_mut() {
+            if let Some(before) = self.players.iter().find(|before| before.entity == frame.entity) {
+                frame.intent = before.intent;
+                frame.is_tagger = before.is_tagger;
+            }
+        }
+
+        self.random = state.random;
+        self.players = state.players;
+        self.bullets = state.bullets;
+        self.orbs = state.orbs;
+        self.extras = state.extras;
+    }
+
+    // Puts the players, bullets and orbs back the way they were when the frame started
+    fn restore(&self, world: &mut World) {
+        for frame in self.players.iter() {
+            let mut entity = match world.get_entity_mut(frame.entity) {
+                Some(entity) => entity,
+                None => continue,
+            };
+
+            if let Some(mut player) = entity.get_mut::<player::Player>() {
+                player.set_motion(frame.velocity, frame.rotation);
+            }
+            if let Some(mut transform) = entity.get_mut::<Transform>() {
+                transform.translation = frame.translation;
+                transform.rotation = Quat::from_rotation_z(frame.rotation);
+            }
+            if let Some(mut intent) = entity.get_mut::<player::Intent>() {
+                *intent = frame.previous_intent;
+            }
+
+            restore_component(&mut entity, frame.dash.clone().map(player::Dash));
+            restore_component(&mut entity, frame.cooldown.clone().map(player::Cooldown));
+            restore_component(&mut entity, frame.stunned.clone().map(player::Stunned));
+            restore_component(&mut entity, frame.charging.clone().map(ability::Charging));
+        }
+
+        let bullets: Vec<Entity> = world.query_filtered::<Entity, With<bullet::Bullet>>().iter(world).collect();
+        let orbs: Vec<Entity> = world.query_filtered::<Entity, With<orb::Orb>>().iter(world).collect();
+        let extras: Vec<Entity> = world.query_filtered::<Entity, Or<(With<ability::Decoy>, With<trap::Trap>, With<ability::Pulse>)>>()
+            .iter(world)
+            .collect();
+
+        for frame in self.bullets.iter() {
+            if let Some(mut transform) = world.get_mut::<Transform>(frame.entity) {
+                transform.translation = frame.translation;
+            }
+            if let (Some(mut mine), Some(timer)) = (world.get_mut::<bullet::Mine>(frame.entity), &frame.mine) {
+                mine.0 = timer.clone();
+            }
+        }
+
+        // The orbs only spin the other way with another random number, it doesn't take one from the match
+        let mut spin = Random(self.random.clone());
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+
+        for frame in self.players.iter().filter(|frame| !frame.shielded) {
+            if let Some(shield) = world.get::<ability::Shield>(frame.entity) {
+                ability::break_shield(&mut commands, frame.entity, shield);
+            }
+        }
+
+        for entity in bullets.iter().filter(|entity| !self.bullets.iter().any(|frame| frame.entity == **entity)) {
+            commands.entity(*entity).despawn();
+        }
+        for frame in self.bullets.iter().filter(|frame| !bullets.contains(&frame.entity)) {
+            bullet::respawn_bullet(&mut commands, &frame.bullet, frame.translation, frame.mine.clone());
+        }
+
+        for entity in orbs.iter().filter(|entity| !self.orbs.iter().any(|(orb, _)| orb == *entity)) {
+            commands.entity(*entity).despawn();
+        }
+        for (_, translation) in self.orbs.iter().filter(|(orb, _)| !orbs.contains(orb)) {
+            orb::spawn_orb_at(&mut commands, &mut spin, *translation);
+        }
+
+        for entity in extras.iter().filter(|entity| !self.extras.contains(entity)) {
+            commands.entity(*entity).despawn_recursive();
+        }
+
+        queue.apply(world);
+
+        world.get_resource_mut::<Random>().unwrap().0 = self.random.clone();
+    }
+}
+
+fn restore_component<C: Component>(entity: &mut EntityMut, component: Option<C>) {
+    match component {
+        Some(component) => {
+            entity.insert(component);
+        },
+        None => {
+            entity.remove::<C>();
+        },
+    }
+}
+
+#[derive(Default)]
+pub struct RollbackBuffer {
+    snapshots: VecDeque<Snapshot>,
+    // The stamp of the newest input of every player, older ones arriving late are dropped
+    confirmed: Vec<f32>,
+    // The first frame an input arriving this frame changed, played again at the end of it
+    rewind: Option<usize>,
+}
+
+// The systems a frame is played again with. They get their own events and sounds while they run,
+// so the live systems don't see a bullet hit or a shot twice
+pub struct Resimulation {
+    stage: SystemStage,
+    collisions: Events<CollisionEvent>,
+    tags: Events<mode::TagEvent>,
+    orbs: Events<mode::OrbEvent>,
+    actions: Events<player::ActionEvent>,
+    match_events: Events<MatchEvent>,
+    tag_reader: ManualEventReader<mode::TagEvent>,
+    orb_reader: ManualEventReader<mode::OrbEvent>,
+}
+
+impl Default for Resimulation {
+    fn default() -> Self {
+        let tags = Events::default();
+        let orbs = Events::default();
+
+        Self {
+            stage: SystemStage::parallel()
+                .with_system(player::handle_move_inputs.label("input"))
+                .with_system(player::move_players.label("move").after("input"))
+                .with_system(ability::move_decoys.label("move").after("input"))
+                .with_system(bullet::move_bullets.label("move").after("input"))
+                .with_system(player::handle_action_button_for_runner.label("action").after("move"))
+                .with_system(player::handle_action_button_for_tagger.label("action").after("move"))
+                .with_system(player::handle_dash_timer_for_runner)
+                .with_system(player::handle_cooldown_timer)
+                .with_system(player::handle_stun_timer)
+                .with_system(ability::handle_shield_timer)
+                .with_system(ability::handle_charge_timer)
+                .with_system(bullet::handle_mine_timer)
+                .with_system(orb::spawn_orb.with_run_criteria(without_orb_layout))
+                .with_system(detect_overlaps.label("overlaps").after("action"))
+                .with_system(game::detect_collisions.after("overlaps")),
+            collisions: Events::default(),
+            tag_reader: tags.get_reader(),
+            orb_reader: orbs.get_reader(),
+            tags,
+            orbs,
+            actions: Events::default(),
+            match_events: Events::default(),
+        }
+    }
+}
+
+impl Resimulation {
+    // Trades the live events for its own ones, calling it again trades them back
+    fn swap_events(&mut self, world: &mut World) {
+        swap_events(world, &mut self.collisions);
+        swap_events(world, &mut self.tags);
+        swap_events(world, &mut self.orbs);
+        swap_events(world, &mut self.actions);
+        swap_events(world, &mut self.match_events);
+    }
+}
+
+fn swap_events<T: Resource>(world: &mut World, events: &mut Events<T>) {
+    if let Some(mut live) = world.get_resource_mut::<Events<T>>() {
+        std::mem::swap(&mut *live, events);
+    }
+}
+
+fn update_events<T: Resource>(world: &mut World) {
+    if let Some(mut events) = world.get_resource_mut::<Events<T>>() {
+        events.update();
+    }
+}
+
+// The fixed orbs of a layout are placed once, a frame played again doesn't place them a second time
+pub fn without_orb_layout(layout: Option<Res<orb::OrbLayout>>) -> ShouldRun {
+    if layout.is_none() {ShouldRun::Yes} else {ShouldRun::No}
+}
+
+// Which bodies overlapped after the last frame played again
+pub struct Touching(pub HashSet<(Entity, Entity)>);
+
+// Local test harness, makes the inputs of every player but the first arrive late or not at all
+pub struct LinkConditions {
+    pub latency: f32,
+    pub loss: f32,
+    // Which inputs are lost, the same seed loses the same ones
+    pub seed: u64,
+}
+
+impl LinkConditions {
+    // Read from `--latency <ms>`, `--loss <fraction>` and `--link-seed <seed>` on the command line
+    pub fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let value = |name: &str| args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+            .and_then(|value| value.parse::<f32>().ok());
+
+        let latency = value("--latency");
+        let loss = value("--loss");
+
+        if latency.is_none() && loss.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            latency: latency.unwrap_or(0.) / 1000.,
+            loss: loss.unwrap_or(0.).clamp(0., 1.),
+            seed: args.iter()
+                .position(|arg| arg == "--link-seed")
+                .and_then(|index| args.get(index + 1))
+                .and_then(|seed| seed.parse().ok())
+                .unwrap_or(0),
+        })
+    }
+}
+
+pub struct SimulatedLink {
+    in_flight: Vec<(f32, RemoteInput)>,
+    random: StdRng,
+}
+
+impl SimulatedLink {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            in_flight: Vec::new(),
+            random: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+// The keys of a player whose inputs go over the simulated link
+#[derive(Component)]
+pub struct DelayedKeys(pub player::Keys);
+
+pub struct RollbackPlugin;
+
+impl Plugin for RollbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RemoteInput>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game)
+                    .with_system(setup_rollback.after("setup_game")),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(in_rollback_match)
+                    .with_system(save_snapshot.exclusive_system().at_start())
+                    .with_system(delay_local_inputs)
+                    .with_system(run_simulated_link.label("receive").before("intent"))
+                    .with_system(reconcile_remote_players.label("intent").after("receive"))
+                    .with_system(record_frame.after("collisions"))
+                    .with_system(roll_back.exclusive_system().at_end()),
+            )
+            .add_system_set(
+                SystemSet::on_pause(GameState::Game)
+                    .with_system(teardown_rollback),
+            );
+    }
+}
+
+pub fn in_rollback_match(
+    state: Res<State<GameState>>,
+    session: Option<Res<net::NetSession>>,
+    link: Option<Res<SimulatedLink>>,
+    playback: Option<Res<replay::Playback>>,
+) -> ShouldRun {
+    if *state.current() == GameState::Game && (session.is_some() || link.is_some() || playback.is_some()) {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+// The harness only runs in local matches, a network match has real latency
+pub fn setup_rollback(
+    mut commands: Commands,
+    conditions: Option<Res<LinkConditions>>,
+    session: Option<Res<net::NetSession>>,
+) {
+    commands.insert_resource(RollbackBuffer::default());
+    commands.insert_resource(Resimulation::default());
+
+    if let (Some(conditions), None) = (conditions, session) {
+        commands.insert_resource(SimulatedLink::new(conditions.seed));
+    }
+}
+
+pub fn teardown_rollback(mut commands: Commands) {
+    commands.remove_resource::<RollbackBuffer>();
+    commands.remove_resource::<Resimulation>();
+    commands.remove_resource::<SimulatedLink>();
+}
+
+// Every player but the first is treated like one on another machine
+pub fn delay_local_inputs(
+    mut commands: Commands,
+    link: Option<Res<SimulatedLink>>,
+    query: Query<(Entity, &player::Player, &player::Keys), Added<player::Player>>,
+) {
+    if link.is_none() {
+        return;
+    }
+
+    for (entity, player, keys) in query.iter() {
+        if player.id == 0 {
+            continue;
+        }
+
+        commands.entity(entity)
+            .remove::<player::Keys>()
+            .insert(DelayedKeys(*keys))
+            .insert(net::Remote::default());
+    }
+}
+
+pub fn run_simulated_link(
+    keys: Res<Input<KeyCode>>,
+    conditions: Option<Res<LinkConditions>>,
+    match_time: Res<game::MatchTime>,
+    link: Option<ResMut<SimulatedLink>>,
+    mut events: EventWriter<RemoteInput>,
+    query: Query<(&player::Player, &DelayedKeys)>,
+) {
+    let (conditions, mut link) = match (conditions, link) {
+        (Some(conditions), Some(link)) => (conditions, link),
+        _ => return,
+    };
+
+    // Lost inputs aren't sent again, like over the network the next one replaces them
+    for (player, delayed_keys) in query.iter() {
+        if link.random.gen::<f32>() < conditions.loss {
+            continue;
+        }
+
+        let input = RemoteInput {
+            id: player.id,
+            time: match_time.0,
+            intent: delayed_keys.0.read(&keys),
+        };
+        link.in_flight.push((match_time.0 + conditions.latency, input));
+    }
+
+    let (arrived, in_flight) = link.in_flight.drain(..).partition(|(arrival, _)| *arrival <= match_time.0);
+    link.in_flight = in_flight;
+
+    for (_, input) in arrived {
+        events.send(input);
+    }
+}
+
+// Remote players keep doing what they did last until their next input arrives,
+// when it shows they did something else the frames since are played again at the end of this one
+pub fn reconcile_remote_players(
+    mut events: EventReader<RemoteInput>,
+    buffer: Option<ResMut<RollbackBuffer>>,
+    mut player_query: Query<(&player::Player, &mut player::Intent, Option<&mut net::Remote>)>,
+) {
+    let inputs: Vec<RemoteInput> = events.iter().copied().collect();
+
+    let mut buffer = match buffer {
+        Some(buffer) => buffer,
+        None => return,
+    };
+
+    let mut earliest: Option<usize> = None;
+
+    for input in inputs {
+        if buffer.confirmed.len() <= input.id {
+            buffer.confirmed.resize(input.id + 1, f32::MIN);
+        }
+
+        if input.time < buffer.confirmed[input.id] {
+            continue;
+        }
+
+        buffer.confirmed[input.id] = input.time;
+
+        for (player, _, remote) in player_query.iter_mut() {
+            if let (true, Some(mut remote)) = (player.id == input.id, remote) {
+                remote.0 = input.intent;
+            }
+        }
+
+        // An input counts from the frame it was made in. The newest snapshot is this frame, which gets it below
+        let played = buffer.snapshots.len().saturating_sub(1);
+
+        for (index, snapshot) in buffer.snapshots.iter_mut().enumerate().take(played) {
+            if snapshot.time < input.time {
+                continue;
+            }
+
+            for frame in snapshot.players.iter_mut().filter(|frame| frame.id == input.id) {
+                if !same_keys(&frame.intent, &input.intent) {
+                    frame.intent = input.intent;
+                    earliest = Some(earliest.map_or(index, |earliest| earliest.min(index)));
+                }
+            }
+        }
+    }
+
+    if let Some(earliest) = earliest {
+        buffer.rewind = Some(buffer.rewind.map_or(earliest, |rewind| rewind.min(earliest)));
+    }
+
+    for (_, mut intent, remote) in player_query.iter_mut() {
+        if let Some(remote) = remote {
+            intent.update(remote.0);
+        }
+    }
+}
+
+// Whether the action was pressed the frame before doesn't make a different input
+pub fn same_keys(a: &player::Intent, b: &player::Intent) -> bool {
+    (a.up, a.down, a.left, a.right, a.action) == (b.up, b.down, b.left, b.right, b.action)
+}
+
+// Runs before anything else of the frame, so it holds what the frame started from
+pub fn save_snapshot(world: &mut World) {
+    if !world.contains_resource::<RollbackBuffer>() {
+        return;
+    }
+
+    let snapshot = Snapshot::take(world);
+    let mut buffer = world.get_resource_mut::<RollbackBuffer>().unwrap();
+
+    buffer.snapshots.push_back(snapshot);
+
+    if buffer.snapshots.len() > MAX_ROLLBACK_FRAMES {
+        buffer.snapshots.pop_front();
+    }
+}
+
+pub fn record_frame(
+    buffer: Option<ResMut<RollbackBuffer>>,
+    mut tag_events: EventReader<mode::TagEvent>,
+    mut orb_events: EventReader<mode::OrbEvent>,
+    query: Query<&player::Intent>,
+) {
+    let tags: Vec<(Entity, Entity)> = tag_events.iter().map(|tag| (tag.runner, tag.tagger)).collect();
+    let pickups: Vec<Entity> = orb_events.iter().map(|pickup| pickup.player).collect();
+
+    let mut buffer = match buffer {
+        Some(buffer) => buffer,
+        None => return,
+    };
+
+    let snapshot = match buffer.snapshots.back_mut() {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+
+    for frame in snapshot.players.iter_mut() {
+        if let Ok(intent) = query.get(frame.entity) {
+            frame.intent = *intent;
+        }
+    }
+
+    snapshot.tags = tags;
+    snapshot.pickups = pickups;
+}
+
+// Goes back to the first frame a late input changed and plays every frame since again with the inputs now known.
+// Who is tagger is up to the mode, frames from before the last role swap stay as they were played
+pub fn roll_back(world: &mut World) {
+    let rewind = match world.get_resource_mut::<RollbackBuffer>() {
+        Some(mut buffer) => buffer.rewind.take(),
+        None => return,
+    };
+
+    let earliest = match rewind {
+        Some(earliest) => earliest,
+        None => return,
+    };
+
+    let roles: Vec<(Entity, bool)> = world.query_filtered::<(Entity, Option<&player::Tagger>), With<player::Player>>()
+        .iter(world)
+        .map(|(entity, tagger)| (entity, tagger.is_some()))
+        .collect();
+
+    world.resource_scope(|world, mut buffer: Mut<RollbackBuffer>| {
+        let same_roles = |snapshot: &Snapshot| snapshot.players.iter()
+            .all(|frame| roles.iter().any(|(entity, is_tagger)| *entity == frame.entity && *is_tagger == frame.is_tagger));
+
+        let first = buffer.snapshots.iter()
+            .rposition(|snapshot| !same_roles(snapshot))
+            .map_or(earliest, |swapped| earliest.max(swapped + 1));
+
+        if first >= buffer.snapshots.len() {
+            return;
+        }
+
+        debug!("Rolling back the last {} frames", buffer.snapshots.len() - first);
+
+        world.resource_scope(|world, mut resimulation: Mut<Resimulation>| {
+            resimulate(world, &mut buffer, &mut resimulation, first);
+        });
+    });
+}
+
+fn resimulate(world: &mut World, buffer: &mut RollbackBuffer, resimulation: &mut Resimulation, first: usize) {
+    let clock = world.get_resource::<Time>().unwrap().clone();
+    let countdown = world.get_resource::<game::CountDownTimer>().unwrap().0.clone();
+    let match_time = world.get_resource::<game::MatchTime>().unwrap().0;
+
+    // The sounds and distance stats were had when the frames were first played
+    let audio = world.remove_resource::<Audio>();
+    let stats = world.remove_resource::<stats::MatchStats>();
+    world.insert_resource(Audio::default());
+    world.insert_resource(stats::MatchStats::default());
+
+    buffer.snapshots[first].restore(world);
+    let restored = Snapshot::take(world);
+    buffer.snapshots[first].start_from(restored);
+
+    let touching = overlapping_pairs(world.query::<(Entity, &Transform, &CollisionShape, Option<&CollisionLayers>)>().iter(world));
+    world.insert_resource(Touching(touching));
+
+    resimulation.swap_events(world);
+
+    let mut seen_tags: Vec<(Entity, Entity)> = buffer.snapshots.range(first..).flat_map(|snapshot| snapshot.tags.clone()).collect();
+    let mut seen_pickups: Vec<Entity> = buffer.snapshots.range(first..).flat_map(|snapshot| snapshot.pickups.clone()).collect();
+    let mut new_tags = Vec::new();
+    let mut new_pickups = Vec::new();
+
+    for index in first..buffer.snapshots.len() {
+        let snapshot = &buffer.snapshots[index];
+
+        world.insert_resource(snapshot.clock.clone());
+        world.get_resource_mut::<game::CountDownTimer>().unwrap().0 = snapshot.countdown.clone();
+        world.get_resource_mut::<game::MatchTime>().unwrap().0 = snapshot.time;
+
+        for frame in snapshot.players.iter() {
+            if let Some(mut intent) = world.get_mut::<player::Intent>(frame.entity) {
+                intent.update(frame.intent);
+            }
+        }
+
+        resimulation.stage.run(world);
+
+        // Only what didn't happen the first time is passed on to the modes
+        let events = world.get_resource::<Events<mode::TagEvent>>().unwrap();
+        for tag in resimulation.tag_reader.iter(events) {
+            match seen_tags.iter().position(|seen| *seen == (tag.runner, tag.tagger)) {
+                Some(seen) => {
+                    seen_tags.remove(seen);
+                },
+                None => {
+                    new_tags.push((index, tag.runner, tag.tagger));
+                },
+            }
+        }
+
+        let events = world.get_resource::<Events<mode::OrbEvent>>().unwrap();
+        for pickup in resimulation.orb_reader.iter(events) {
+            match seen_pickups.iter().position(|seen| *seen == pickup.player) {
+                Some(seen) => {
+                    seen_pickups.remove(seen);
+                },
+                None => {
+                    new_pickups.push((index, pickup.player));
+                },
+            }
+        }
+
+        update_events::<CollisionEvent>(world);
+        update_events::<mode::TagEvent>(world);
+        update_events::<mode::OrbEvent>(world);
+        update_events::<player::ActionEvent>(world);
+        update_events::<MatchEvent>(world);
+
+        if index + 1 < buffer.snapshots.len() {
+            let state = Snapshot::take(world);
+            buffer.snapshots[index + 1].start_from(state);
+        }
+    }
+
+    resimulation.swap_events(world);
+    world.remove_resource::<Touching>();
+
+    world.insert_resource(clock);
+    world.get_resource_mut::<game::CountDownTimer>().unwrap().0 = countdown;
+    world.get_resource_mut::<game::MatchTime>().unwrap().0 = match_time;
+
+    world.remove_resource::<Audio>();
+    world.remove_resource::<stats::MatchStats>();
+    if let Some(audio) = audio {
+        world.insert_resource(audio);
+    }
+    if let Some(stats) = stats {
+        world.insert_resource(stats);
+    }
+
+    for (index, runner, tagger) in new_tags {
+        buffer.snapshots[index].tags.push((runner, tagger));
+        world.get_resource_mut::<Events<mode::TagEvent>>().unwrap().send(mode::TagEvent { runner, tagger });
+    }
+    for (index, player) in new_pickups {
+        buffer.snapshots[index].pickups.push(player);
+        world.get_resource_mut::<Events<mode::OrbEvent>>().unwrap().send(mode::OrbEvent { player });
+    }
+}
+
+// The physics doesn't step while frames are played again, so the collisions it would report come from the shapes
+pub fn detect_overlaps(
+    mut touching: ResMut<Touching>,
+    mut events: EventWriter<CollisionEvent>,
+    query: Query<(Entity, &Transform, &CollisionShape, Option<&CollisionLayers>)>,
+) {
+    let overlaps = overlapping_pairs(query.iter());
+    let layers = |entity: Entity| query.get(entity).ok()
+        .and_then(|(_, _, _, layers)| layers.copied())
+        .unwrap_or_default();
+
+    for (entity1, entity2) in overlaps.difference(&touching.0) {
+        events.send(CollisionEvent::Started(
+            CollisionData::new(*entity1, *entity1, layers(*entity1), Vec::new()),
+            CollisionData::new(*entity2, *entity2, layers(*entity2), Vec::new()),
+        ));
+    }
+
+    touching.0 = overlaps;
+}
+
+// Circles and capsules only, the players and decoys don't react to each other so two capsules are never checked
+pub fn overlapping_pairs<'a>(bodies: impl Iterator<Item = (Entity, &'a Transform, &'a CollisionShape, Option<&'a CollisionLayers>)>) -> HashSet<(Entity, Entity)> {
+    let bodies: Vec<(Entity, Vec2, Vec2, f32, CollisionLayers)> = bodies
+        .filter_map(|(entity, transform, shape, layers)| {
+            let (half_segment, radius) = match *shape {
+                CollisionShape::Sphere { radius } => (0., radius),
+                CollisionShape::Capsule { half_segment, radius } => (half_segment, radius),
+                _ => return None,
+            };
+
+            let center = transform.translation.truncate();
+            let axis = (transform.rotation * Vec3::Y * half_segment).truncate();
+
+            Some((entity, center - axis, center + axis, radius, layers.copied().unwrap_or_default()))
+        })
+        .collect();
+
+    let mut pairs = HashSet::new();
+
+    for (index, (entity1, start1, end1, radius1, layers1)) in bodies.iter().enumerate() {
+        for (entity2, start2, end2, radius2, layers2) in bodies.iter().skip(index + 1) {
+            if !layers1.interacts_with(*layers2) {
+                continue;
+            }
+
+            let distance = if start1 == end1 {
+                segment_distance(*start1, *start2, *end2)
+            } else if start2 == end2 {
+                segment_distance(*start2, *start1, *end1)
+            } else {
+                continue;
+            };
+
+            if distance < radius1 + radius2 {
+                pairs.insert((*entity1, *entity2));
+            }
+        }
+    }
+
+    pairs
+}
+
+fn segment_distance(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let along = if segment.length_squared() > 0. {
+        ((point - start).dot(segment) / segment.length_squared()).clamp(0., 1.)
+    } else {
+        0.
+    };
+
+    point.distance(start + segment * along)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, ecs::event::Events};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{MatchPlugins, Random, gym, headless, mode};
+
+    const FRAMES: usize = 90;
+    const LATENCY_FRAMES: usize = 5;
+
+    // Player 1 drives around, changing its mind every few frames. It waits until there is a first frame to go back to
+    fn scripted_intent(frame: usize) -> player::Intent {
+        let mut intent = player::Intent::default();
+        intent.up = (10..70).contains(&frame);
+        intent.down = frame >= 70;
+        intent.left = (15..25).contains(&frame);
+        intent.right = (40..50).contains(&frame);
+        intent
+    }
+
+    fn is_lost(frame: usize) -> bool {
+        frame % 7 == 3
+    }
+
+    // A two player match where one player only moves by the inputs sent to it, player 0 is the tagger
+    fn match_app(remote: usize) -> App {
+        let mut app = App::new();
+
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(headless::HeadlessPlugin)
+            .add_plugins(MatchPlugins)
+            .add_plugin(gym::GymPlugin)
+            .add_state(GameState::MainMenu);
+
+        app.update();
+
+        app.world.get_resource_mut::<mode::MatchSettings>().unwrap().players = 2;
+        app.world.get_resource_mut::<Random>().unwrap().0 = StdRng::seed_from_u64(7);
+        app.world.get_resource_mut::<State<GameState>>().unwrap().push(GameState::Game).unwrap();
+        app.update();
+
+        app.insert_resource(SimulatedLink::new(0));
+
+        let mut query = app.world.query::<(Entity, &player::Player)>();
+        let remote: Vec<Entity> = query.iter(&app.world)
+            .filter(|(_, player)| player.id == remote)
+            .map(|(entity, _)| entity)
+            .collect();
+
+        for entity in remote {
+            let mut entity = app.world.entity_mut(entity);
+            entity.remove::<player::Keys>();
+            entity.insert(net::Remote::default());
+        }
+
+        app
+    }
+
+    fn input(app: &App, id: usize, intent: player::Intent) -> RemoteInput {
+        RemoteInput {
+            id,
+            time: app.world.get_resource::<game::MatchTime>().unwrap().0,
+            intent,
+        }
+    }
+
+    fn send(app: &mut App, input: RemoteInput) {
+        app.world.get_resource_mut::<Events<RemoteInput>>().unwrap().send(input);
+    }
+
+    fn position(app: &mut App) -> Vec3 {
+        let mut query = app.world.query::<(&player::Player, &Transform)>();
+        query.iter(&app.world)
+            .find(|(player, _)| player.id == 1)
+            .map(|(_, transform)| transform.translation)
+            .unwrap()
+    }
+
+    #[test]
+    fn late_inputs_end_up_where_instant_ones_do() {
+        let mut instant = match_app(1);
+        let mut delayed = match_app(1);
+        let mut in_flight: Vec<(usize, RemoteInput)> = Vec::new();
+
+        for frame in 0..FRAMES + LATENCY_FRAMES {
+            if frame < FRAMES && !is_lost(frame) {
+                let input = input(&instant, 1, scripted_intent(frame));
+                send(&mut instant, input);
+
+                in_flight.push((frame + LATENCY_FRAMES, self::input(&delayed, 1, scripted_intent(frame))));
+            }
+
+            let (arrived, rest): (Vec<_>, Vec<_>) = in_flight.into_iter().partition(|(arrival, _)| *arrival <= frame);
+            in_flight = rest;
+
+            for (_, input) in arrived {
+                send(&mut delayed, input);
+            }
+
+            instant.update();
+            delayed.update();
+        }
+
+        let expected = position(&mut instant);
+        let corrected = position(&mut delayed);
+
+        assert!(expected.distance(position(&mut match_app(1))) > 1., "The remote player didn't move");
+        assert!(corrected.distance(expected) < 0.01, "{} should be {}", corrected, expected);
+    }
+
+    fn bullets(app: &mut App) -> Vec<Vec3> {
+        let mut query = app.world.query_filtered::<&Transform, With<bullet::Bullet>>();
+        query.iter(&app.world).map(|transform| transform.translation).collect()
+    }
+
+    #[test]
+    fn a_late_input_fires_the_shot_it_made() {
+        const SHOT_FRAME: usize = 10;
+
+        let mut instant = match_app(0);
+        let mut delayed = match_app(0);
+        let mut in_flight: Vec<(usize, RemoteInput)> = Vec::new();
+
+        for frame in 0..SHOT_FRAME + LATENCY_FRAMES * 2 {
+            let mut intent = player::Intent::default();
+            intent.action = (SHOT_FRAME..SHOT_FRAME + 3).contains(&frame);
+
+            let input = input(&instant, 0, intent);
+            send(&mut instant, input);
+            in_flight.push((frame + LATENCY_FRAMES, self::input(&delayed, 0, intent)));
+
+            let (arrived, rest): (Vec<_>, Vec<_>) = in_flight.into_iter().partition(|(arrival, _)| *arrival <= frame);
+            in_flight = rest;
+
+            for (_, input) in arrived {
+                send(&mut delayed, input);
+            }
+
+            instant.update();
+            delayed.update();
+
+            if frame == SHOT_FRAME + LATENCY_FRAMES - 1 {
+                assert!(bullets(&mut delayed).is_empty(), "The shot was fired before its input arrived");
+            }
+        }
+
+        let expected = bullets(&mut instant);
+        let corrected = bullets(&mut delayed);
+
+        assert_eq!(expected.len(), 1, "The tagger didn't shoot");
+        assert_eq!(corrected.len(), 1, "The late shot wasn't fired");
+        assert!(corrected[0].distance(expected[0]) < 0.01, "{} should be {}", corrected[0], expected[0]);
+
+        let mut query = delayed.world.query::<(&player::Player, Option<&player::Cooldown>)>();
+        assert!(query.iter(&delayed.world).any(|(player, cooldown)| player.id == 0 && cooldown.is_some()), "The late shot didn't start the cooldown");
+    }
+}
@@ -0,0 +1,182 @@
+use bevy::{prelude::*, ecs::schedule::ShouldRun, render::camera::{Camera, CameraPlugin}};
+use crate::{GameState, game, mode, net, player, replay};
+use crate::ability::{Loadout, Role};
+
+pub const CAMERA_SPEED: f32 = 500.;
+pub const ZOOM_SPEED: f32 = 1.;
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 2.;
+
+pub const PAN_UP_KEYS: [KeyCode; 2] = [KeyCode::W, KeyCode::Up];
+pub const PAN_DOWN_KEYS: [KeyCode; 2] = [KeyCode::S, KeyCode::Down];
+pub const PAN_LEFT_KEYS: [KeyCode; 2] = [KeyCode::A, KeyCode::Left];
+pub const PAN_RIGHT_KEYS: [KeyCode; 2] = [KeyCode::D, KeyCode::Right];
+pub const ZOOM_IN_KEY: KeyCode = KeyCode::E;
+pub const ZOOM_OUT_KEY: KeyCode = KeyCode::Q;
+pub const RESET_CAMERA_KEY: KeyCode = KeyCode::R;
+
+// Nobody on this machine plays, the match is watched live or from a replay
+pub struct Spectating;
+
+#[derive(Component)]
+pub struct CooldownText(pub usize);
+
+pub struct SpectatePlugin;
+
+impl Plugin for SpectatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Game)
+                .with_system(setup_spectating.after("setup_game")),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(in_spectated_match)
+                .with_system(move_free_camera)
+                .with_system(update_cooldown_texts),
+        )
+        .add_system_set(
+            SystemSet::on_pause(GameState::Game)
+                .with_system(teardown_spectating),
+        );
+    }
+}
+
+pub fn in_spectated_match(state: Res<State<GameState>>, spectating: Option<Res<Spectating>>) -> ShouldRun {
+    if *state.current() == GameState::Game && spectating.is_some() {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+pub fn setup_spectating(
+    mut commands: Commands,
+    font: Res<crate::FontHandle>,
+    settings: Res<mode::MatchSettings>,
+    session: Option<Res<net::NetSession>>,
+    playback: Option<Res<replay::Playback>>,
+) {
+    if !session.is_some_and(|session| session.spectator) && playback.is_none() {
+        return;
+    }
+
+    commands.insert_resource(Spectating);
+
+    // COOLDOWNS OF EVERY PLAYER
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size {
+                width: Val::Percent(100.0),
+                height: Val::Px(40.0),
+            },
+            position_type: PositionType::Absolute,
+            position: Rect {
+                bottom: Val::Px(0.),
+                ..Default::default()
+            },
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceEvenly,
+            ..Style::default()
+        },
+        color: UiColor(Color::rgba(23./255., 23./255., 23./255., 0.8)),
+        ..NodeBundle::default()
+    })
+    .insert(game::GameEntity)
+    .with_children(|parent| {
+        let style = TextStyle {
+            font: font.0.clone(),
+            font_size: 20.,
+            color: Color::WHITE,
+        };
+
+        for id in 0..settings.players {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section("", style.clone(), TextAlignment::default()),
+                ..Default::default()
+            })
+            .insert(CooldownText(id));
+        }
+    });
+}
+
+// The camera is free to look anywhere, the keys don't steer any player
+pub fn move_free_camera(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut query: Query<(&Camera, &mut Transform, &mut OrthographicProjection)>,
+) {
+    let pan = Vec2::new(axis(&keys, &PAN_RIGHT_KEYS, &PAN_LEFT_KEYS), axis(&keys, &PAN_UP_KEYS, &PAN_DOWN_KEYS));
+    let zoom = axis(&keys, &[ZOOM_OUT_KEY], &[ZOOM_IN_KEY]);
+
+    for (camera, mut transform, mut projection) in query.iter_mut() {
+        if camera.name.as_deref() != Some(CameraPlugin::CAMERA_2D) {
+            continue;
+        }
+
+        if keys.just_pressed(RESET_CAMERA_KEY) {
+            reset_camera(&mut transform, &mut projection);
+            continue;
+        }
+
+        // Zoomed out the camera moves faster, so it crosses the screen in the same time
+        let step = pan * CAMERA_SPEED * projection.scale * time.delta_seconds();
+        transform.translation += step.extend(0.);
+
+        projection.scale = (projection.scale + zoom * ZOOM_SPEED * time.delta_seconds()).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+fn axis(keys: &Input<KeyCode>, positive: &[KeyCode], negative: &[KeyCode]) -> f32 {
+    let pressed = |axis_keys: &[KeyCode]| if axis_keys.iter().any(|key| keys.pressed(*key)) {1.} else {0.};
+    pressed(positive) - pressed(negative)
+}
+
+fn reset_camera(transform: &mut Transform, projection: &mut OrthographicProjection) {
+    transform.translation.x = 0.;
+    transform.translation.y = 0.;
+    projection.scale = 1.;
+}
+
+pub fn update_cooldown_texts(
+    player_query: Query<(&player::Player, &Loadout, Option<&player::Tagger>, Option<&player::Cooldown>)>,
+    mut text_query: Query<(&CooldownText, &mut Text)>,
+) {
+    for (player, loadout, tagger, cooldown) in player_query.iter() {
+        let role = if tagger.is_some() {Role::Tagger} else {Role::Runner};
+
+        let state = match cooldown {
+            Some(cooldown) => format!("{:.1}s", cooldown.0.duration().as_secs_f32() - cooldown.0.elapsed_secs()),
+            None => "ready".to_string(),
+        };
+
+        for (cooldown_text, mut text) in text_query.iter_mut() {
+            if cooldown_text.0 != player.id {
+                continue;
+            }
+
+            let section = &mut text.sections[0];
+            let value = format!("{}  {}  {}", player::player_name(player.id), loadout.get(role).name(), state);
+
+            if section.value != value {
+                section.value = value;
+                section.style.color = role.color();
+            }
+        }
+    }
+}
+
+// The camera of the next match starts where it always does
+pub fn teardown_spectating(
+    mut commands: Commands,
+    mut query: Query<(&Camera, &mut Transform, &mut OrthographicProjection)>,
+) {
+    commands.remove_resource::<Spectating>();
+
+    for (camera, mut transform, mut projection) in query.iter_mut() {
+        if camera.name.as_deref() == Some(CameraPlugin::CAMERA_2D) {
+            reset_camera(&mut transform, &mut projection);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use crate::{GameState, mode, player};
+use crate::match_log::{MatchEvent, MatchEventKind};
+
+#[derive(Default, Clone)]
+pub struct PlayerStats {
+    pub shots: u32,
+    pub hits: u32,
+    pub dashes: u32,
+    // Dashes that passed through at least one bullet
+    pub dodging_dashes: u32,
+    pub orbs: u32,
+    pub tagger_time: f32,
+    pub runner_time: f32,
+    pub longest_streak: f32,
+    pub distance: f32,
+    streak: f32,
+    dash_dodged: bool,
+}
+
+impl PlayerStats {
+    // Every tag is a bullet that hit
+    pub fn hit_rate(&self) -> Option<f32> {
+        (self.shots > 0).then(|| self.hits as f32 / self.shots as f32)
+    }
+}
+
+// By player id, started over with every match
+#[derive(Default)]
+pub struct MatchStats(pub Vec<PlayerStats>);
+
+impl MatchStats {
+    pub fn player(&mut self, id: usize) -> Option<&mut PlayerStats> {
+        self.0.get_mut(id)
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchStats>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game)
+                    .with_system(reset_stats),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Game)
+                    .with_system(track_roles.after("collisions")),
+            )
+            // The events of the frame the match ends in still count
+            .add_system_to_stage(CoreStage::PostUpdate, count_match_events);
+    }
+}
+
+pub fn reset_stats(mut stats: ResMut<MatchStats>, settings: Res<mode::MatchSettings>) {
+    *stats = MatchStats(vec![PlayerStats::default(); settings.players]);
+}
+
+// A survival streak lasts as long as the runner is neither tagged nor frozen
+pub fn track_roles(
+    time: Res<Time>,
+    mut stats: ResMut<MatchStats>,
+    query: Query<(&player::Player, Option<&player::Tagger>, Option<&player::Frozen>)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (player, tagger, frozen) in query.iter() {
+        let stats = match stats.player(player.id) {
+            Some(stats) => stats,
+            None => continue,
+        };
+
+        if tagger.is_some() {
+            stats.tagger_time += delta;
+            stats.streak = 0.;
+            continue;
+        }
+
+        stats.runner_time += delta;
+
+        if frozen.is_some() {
+            stats.streak = 0.;
+        } else {
+            stats.streak += delta;
+            stats.longest_streak = stats.longest_streak.max(stats.streak);
+        }
+    }
+}
+
+pub fn count_match_events(
+    mut stats: ResMut<MatchStats>,
+    mut match_events: EventReader<MatchEvent>,
+    mut action_events: EventReader<player::ActionEvent>,
+    query: Query<&player::Player>,
+) {
+    for event in match_events.iter() {
+        match event.kind {
+            MatchEventKind::Shot { player, .. } => {
+                if let Some(stats) = stats.player(player) {
+                    stats.shots += 1;
+                }
+            },
+            MatchEventKind::Tag { tagger, .. } => {
+                if let Some(stats) = stats.player(tagger) {
+                    stats.hits += 1;
+                }
+            },
+            MatchEventKind::DashStart { player, .. } => {
+                if let Some(stats) = stats.player(player) {
+                    stats.dashes += 1;
+                    stats.dash_dodged = false;
+                }
+            },
+            MatchEventKind::OrbTouch { player, .. } => {
+                if let Some(stats) = stats.player(player) {
+                    stats.orbs += 1;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    for event in action_events.iter() {
+        if event.kind != player::ActionKind::Dodge {
+            continue;
+        }
+
+        let stats = match query.get(event.player).ok().and_then(|player| stats.player(player.id)) {
+            Some(stats) => stats,
+            None => continue,
+        };
+
+        // A dash through several bullets counts once
+        if !stats.dash_dodged {
+            stats.dash_dodged = true;
+            stats.dodging_dashes += 1;
+        }
+    }
+}
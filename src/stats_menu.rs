@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use crate::{GameState, game, player, stats};
+
+pub const COLUMN_WIDTH: f32 = 76.;
+pub const COLUMNS: [&str; 10] = ["Player", "Shots", "Hit rate", "Dashes", "Dodges", "Orbs", "Tagger", "Runner", "Streak", "Distance"];
+
+#[derive(Component)]
+pub struct StatsMenu;
+
+#[derive(Component)]
+pub struct BackButton;
+
+pub struct StatsMenuPlugin;
+
+impl Plugin for StatsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameState::MatchStats)
+                .with_system(setup_stats_menu),
+        )
+        .add_system_set(
+            SystemSet::on_resume(GameState::MatchStats)
+                .with_system(setup_stats_menu),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::MatchStats)
+                .with_system(handle_stats_menu_interactions),
+        )
+        .add_system_set(
+            SystemSet::on_pause(GameState::MatchStats)
+                .with_system(teardown_stats_menu_items),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::MatchStats)
+                .with_system(teardown_stats_menu_items),
+        );
+    }
+}
+
+fn stats_row(id: usize, stats: &stats::PlayerStats) -> [String; 10] {
+    [
+        player::player_name(id),
+        stats.shots.to_string(),
+        stats.hit_rate().map_or("-".to_string(), |rate| format!("{:.0}%", rate * 100.)),
+        stats.dashes.to_string(),
+        stats.dodging_dashes.to_string(),
+        stats.orbs.to_string(),
+        format!("{:.0}s", stats.tagger_time),
+        format!("{:.0}s", stats.runner_time),
+        format!("{:.0}s", stats.longest_streak),
+        format!("{:.0}", stats.distance),
+    ]
+}
+
+pub fn setup_stats_menu(
+  mut commands: Commands,
+  font: Res<crate::FontHandle>,
+  match_stats: Res<stats::MatchStats>,
+) {
+    commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceEvenly,
+            ..Style::default()
+        },
+        visibility: Visibility {
+            is_visible: false,
+        },
+        ..NodeBundle::default()
+    })
+    .insert(StatsMenu)
+    .with_children(|parent| {
+        let alignment = TextAlignment {
+            vertical: VerticalAlign::Center,
+            horizontal: HorizontalAlign::Center,
+        };
+
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section("Match stats", TextStyle {
+                font: font.0.clone(),
+                font_size: 50.0,
+                color: game::TAGGER_COL,
+            }, alignment),
+            ..TextBundle::default()
+        });
+
+        // ONE ROW PER PLAYER BELOW THE COLUMN NAMES
+        parent.spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Style::default()
+            },
+            visibility: Visibility {
+                is_visible: false,
+            },
+            ..NodeBundle::default()
+        })
+        .with_children(|parent| {
+            let header = COLUMNS.map(|column| column.to_string());
+            let rows = match_stats.0.iter().enumerate().map(|(id, stats)| stats_row(id, stats));
+
+            for (index, row) in std::iter::once(header).chain(rows).enumerate() {
+                let style = TextStyle {
+                    font: font.0.clone(),
+                    font_size: 16.0,
+                    color: if index == 0 {game::RUNNER_COL} else {Color::WHITE},
+                };
+
+                parent.spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        margin: Rect {
+                            bottom: Val::Px(8.),
+                            ..Default::default()
+                        },
+                        ..Style::default()
+                    },
+                    visibility: Visibility {
+                        is_visible: false,
+                    },
+                    ..NodeBundle::default()
+                })
+                .with_children(|parent| {
+                    for cell in row {
+                        parent.spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size {
+                                    width: Val::Px(COLUMN_WIDTH),
+                                    height: Val::Px(24.0),
+                                },
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..Style::default()
+                            },
+                            visibility: Visibility {
+                                is_visible: false,
+                            },
+                            ..NodeBundle::default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn_bundle(TextBundle {
+                                text: Text::with_section(cell, style.clone(), alignment),
+                                ..TextBundle::default()
+                            });
+                        });
+                    }
+                });
+            }
+        });
+
+        // BACK BUTTON
+        parent.spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                },
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceAround,
+                ..Style::default()
+            },
+            ..ButtonBundle::default()
+        })
+        .insert(BackButton)
+        .with_children(|parent| {
+            let style = TextStyle {
+                font: font.0.clone(),
+                font_size: 30.0,
+                color: Color::WHITE,
+            };
+
+            parent.spawn_bundle(TextBundle {
+                style: Style::default(),
+                text: Text::with_section("Back", style, alignment),
+                ..TextBundle::default()
+            });
+        });
+    });
+}
+
+pub fn handle_stats_menu_interactions(
+    mut app_state: ResMut<State<GameState>>,
+    mut back_btn_query: Query<(&Interaction, &mut UiColor), With<BackButton>>,
+) {
+    for (interaction, mut button) in back_btn_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                app_state.push(GameState::MainMenu).unwrap();
+            },
+            Interaction::Hovered => {
+                button.0 = game::ORB_OUTLINE_COLOR;
+            },
+            Interaction::None => {
+                button.0 = Color::rgb(149./255., 53./255., 184./255.);
+            }
+        }
+    }
+}
+
+pub fn teardown_stats_menu_items(
+    mut commands: Commands,
+    query: Query<Entity, With<StatsMenu>>
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{GameState, game, player, ability, orb, bot};
+use crate::ability::Ability;
+use crate::mode::{self, GameMode, TagEvent, OrbEvent};
+
+pub const WAVES_FILE: &str = "assets/waves.json";
+pub const BOT_SPAWN_DIST: f32 = 250.;
+
+// One wave of bots, it lasts until the next one comes in, the last one until the end
+#[derive(Deserialize, Clone)]
+pub struct Wave {
+    pub duration: f32,
+    pub taggers: usize,
+    pub speed: f32,
+    pub ability: Ability,
+}
+
+#[derive(Deserialize)]
+pub struct WaveFile {
+    pub orb_points: u32,
+    pub waves: Vec<Wave>,
+}
+
+impl WaveFile {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let file: Self = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+
+        if file.waves.is_empty() {
+            return Err("there are no waves in it".to_string());
+        }
+
+        let positive = |value: f32| value.is_finite() && value > 0.;
+        if let Some(index) = file.waves.iter().position(|wave| !positive(wave.duration) || !positive(wave.speed)) {
+            return Err(format!("wave {} needs a positive duration and speed", index + 1));
+        }
+
+        Ok(file)
+    }
+}
+
+// The waves the game ships with, played when the file can't be read
+impl Default for WaveFile {
+    fn default() -> Self {
+        let wave = |duration, taggers, speed, ability| Wave { duration, taggers, speed, ability };
+
+        Self {
+            orb_points: 5,
+            waves: vec![
+                wave(20., 1, 0.5, Ability::Shot),
+                wave(20., 2, 0.5, Ability::Shot),
+                wave(25., 2, 0.7, Ability::ChargedShot),
+                wave(25., 3, 0.7, Ability::Mine),
+                wave(30., 3, 0.85, Ability::Shot),
+                wave(30., 4, 1.0, Ability::ChargedShot),
+            ],
+        }
+    }
+}
+
+pub struct Survival {
+    waves: WaveFile,
+    wave: usize,
+    timer: Timer,
+    orbs: u32,
+    bots_spawned: usize,
+    runners: String,
+}
+
+impl Survival {
+    pub fn score(&self, time: f32) -> u32 {
+        time.floor() as u32 + self.orbs * self.waves.orb_points
+    }
+}
+
+pub struct SurvivalPlugin;
+
+impl Plugin for SurvivalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::Survival))
+                .with_system(setup_survival)
+                .with_system(spawn_waves)
+                .with_system(collect_orbs.after("collisions"))
+                .with_system(update_survival_status.after("countdown")),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::Survival))
+                .with_system(eliminate_runners.label("resolve").after("collisions"))
+                .with_system(end_survival_match.after("resolve")),
+        );
+    }
+}
+
+// Loads the waves once the human players of the match are spawned
+pub fn setup_survival(
+    mut commands: Commands,
+    query: Query<&player::Player, (Added<player::Player>, Without<bot::Bot>)>,
+) {
+    if query.is_empty() {
+        return;
+    }
+
+    let waves = WaveFile::load(WAVES_FILE).unwrap_or_else(|err| {
+        warn!("Failed to load the waves from {}, playing the built-in ones: {}", WAVES_FILE, err);
+        WaveFile::default()
+    });
+
+    commands.insert_resource(Survival {
+        waves,
+        wave: 0,
+        timer: Timer::from_seconds(0., false),
+        orbs: 0,
+        bots_spawned: 0,
+        runners: mode::player_names(query.iter()),
+    });
+}
+
+// Every new wave replaces the bots of the previous one
+pub fn spawn_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    arena: Res<game::Arena>,
+    mut random: ResMut<crate::Random>,
+    survival: Option<ResMut<Survival>>,
+    bot_query: Query<(Entity, &Transform), With<bot::Bot>>,
+    runner_query: Query<&Transform, With<player::Runner>>,
+) {
+    let mut survival = match survival {
+        Some(survival) => survival,
+        None => return,
+    };
+
+    survival.timer.tick(time.delta());
+
+    let is_last = survival.wave >= survival.waves.waves.len();
+    if !survival.timer.just_finished() || is_last {
+        return;
+    }
+
+    for (entity, transform) in bot_query.iter() {
+        ability::spawn_burst(&mut commands, transform.translation, game::TAGGER_COL);
+        commands.entity(entity).despawn();
+    }
+
+    let wave = survival.waves.waves[survival.wave].clone();
+    let mut taken: Vec<Vec3> = runner_query.iter().map(|transform| transform.translation).collect();
+
+    for _ in 0..wave.taggers {
+        let pos = orb::free_position(&mut random, &arena, 30., &taken, BOT_SPAWN_DIST);
+
+        bot::spawn_bot(&mut commands, survival.bots_spawned, pos, wave.ability, wave.speed);
+        survival.bots_spawned += 1;
+        taken.push(pos);
+    }
+
+    survival.wave += 1;
+    survival.timer = Timer::from_seconds(wave.duration, false);
+}
+
+// There are no second chances, a tagged runner is out
+pub fn eliminate_runners(
+    mut commands: Commands,
+    mut events: EventReader<TagEvent>,
+    query: Query<&Transform, With<player::Runner>>,
+) {
+    let mut eliminated: Vec<Entity> = Vec::new();
+
+    for event in events.iter() {
+        if eliminated.contains(&event.runner) {
+            continue;
+        }
+
+        if let Ok(transform) = query.get(event.runner) {
+            ability::spawn_burst(&mut commands, transform.translation, game::RUNNER_COL);
+            commands.entity(event.runner).despawn();
+            eliminated.push(event.runner);
+        }
+    }
+}
+
+pub fn collect_orbs(
+    mut events: EventReader<OrbEvent>,
+    survival: Option<ResMut<Survival>>,
+    query: Query<(), With<player::Runner>>,
+) {
+    let count = events.iter().filter(|event| query.get(event.player).is_ok()).count() as u32;
+
+    if let Some(mut survival) = survival {
+        survival.orbs += count;
+    }
+}
+
+pub fn update_survival_status(
+    survival: Option<Res<Survival>>,
+    match_time: Res<game::MatchTime>,
+    mut query: Query<&mut Text, With<game::ModeStatus>>,
+) {
+    let survival = match survival {
+        Some(survival) => survival,
+        None => return,
+    };
+
+    let value = format!("Wave {}  {} pts", survival.wave, survival.score(match_time.0));
+
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+// The match is over once every runner is tagged, the score is shared by the whole group
+pub fn end_survival_match(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    survival: Option<Res<Survival>>,
+    match_time: Res<game::MatchTime>,
+    query: Query<(), With<player::Runner>>,
+) {
+    let survival = match survival {
+        Some(survival) => survival,
+        None => return,
+    };
+
+    if query.is_empty() {
+        let winner = format!("{} ({} points)", survival.runners, survival.score(match_time.0));
+        game::end_match(&mut commands, &mut app_state, winner);
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+
+use crate::{GameState, game, player};
+use crate::mode::{self, GameMode, TagEvent, TeamScores};
+
+pub struct TeamsPlugin;
+
+impl Plugin for TeamsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::Teams))
+                .with_system(swap_teams.label("resolve").after("collisions"))
+                .with_system(end_teams_match.after("countdown")),
+        );
+    }
+}
+
+// A tag scores a point for the tagging team and the whole teams trade roles
+pub fn swap_teams(
+    mut commands: Commands,
+    mut events: EventReader<TagEvent>,
+    mut scores: ResMut<TeamScores>,
+    query: Query<(Entity, &player::Team)>,
+) {
+    // Only the first tag of a frame counts, the others were aimed at the old runners
+    let taggers: Vec<Entity> = events.iter().map(|event| event.tagger).collect();
+
+    let tagger = match taggers.first() {
+        Some(tagger) => *tagger,
+        None => return,
+    };
+
+    let tagging_team = match query.get(tagger) {
+        Ok((_, team)) => *team,
+        Err(_) => return,
+    };
+
+    scores.0[tagging_team.0] += 1;
+
+    for (entity, team) in query.iter() {
+        if *team == tagging_team {
+            player::become_runner(&mut commands, entity);
+        } else {
+            player::become_tagger(&mut commands, entity);
+        }
+    }
+}
+
+// The team with more tags wins when the time is up
+pub fn end_teams_match(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    timer: Res<game::CountDownTimer>,
+    scores: Res<TeamScores>,
+) {
+    if timer.0.just_finished() {
+        game::end_match(&mut commands, &mut app_state, mode::best_teams(&scores));
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, game, player, ability, bot};
+use crate::ability::Role;
+use crate::mode::{self, GameMode, TagEvent};
+
+pub const TRIAL_SEED: u64 = 2022;
+pub const PATH_FILE: &str = "assets/runner_path.json";
+pub const BESTS_FILE: &str = "time_trial_bests.json";
+pub const MAX_BESTS: usize = 10;
+pub const WAYPOINT_RADIUS: f32 = 40.;
+
+// The waypoints the scripted runner goes through, it starts over after the last one
+#[derive(Deserialize)]
+pub struct RunnerPath {
+    pub speed: f32,
+    pub points: Vec<Vec2>,
+}
+
+impl RunnerPath {
+    // The runner starts on the first point and heads for the second, so a path needs both
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let runner_path: Self = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+
+        if runner_path.points.len() < 2 {
+            return Err("a path needs at least two points".to_string());
+        }
+
+        Ok(runner_path)
+    }
+}
+
+#[derive(Component)]
+pub struct ScriptedRunner {
+    points: Vec<Vec2>,
+    next: usize,
+}
+
+impl ScriptedRunner {
+    pub fn new(points: Vec<Vec2>) -> Self {
+        Self { points, next: 1 }
+    }
+}
+
+// The fastest times so far, stored next to the game
+#[derive(Serialize, Deserialize, Default)]
+pub struct PersonalBests {
+    pub times: Vec<f32>,
+}
+
+impl PersonalBests {
+    pub fn load() -> Self {
+        fs::read_to_string(BESTS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let content = serde_json::to_string_pretty(self).unwrap();
+
+        if let Err(err) = fs::write(BESTS_FILE, content) {
+            warn!("Failed to save the personal bests: {}", err);
+        }
+    }
+
+    // Returns the place the time got on the list, if it made it there at all
+    pub fn record(&mut self, time: f32) -> Option<usize> {
+        let place = self.times.iter().position(|best| time < *best).unwrap_or(self.times.len());
+
+        if place >= MAX_BESTS {
+            return None;
+        }
+
+        self.times.insert(place, time);
+        self.times.truncate(MAX_BESTS);
+
+        Some(place)
+    }
+}
+
+pub struct TimeTrialPlugin;
+
+impl Plugin for TimeTrialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::TimeTrial))
+                .with_system(setup_time_trial)
+                .with_system(drive_scripted_runner.label("intent")),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode_as_host(GameMode::TimeTrial))
+                .with_system(end_time_trial.after("collisions")),
+        );
+    }
+}
+
+// Sends the scripted runner on its path once the tagger is spawned, without a path there is nothing to chase
+pub fn setup_time_trial(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    query: Query<(), (Added<player::Player>, With<player::Tagger>)>,
+) {
+    if query.is_empty() {
+        return;
+    }
+
+    let path = match RunnerPath::load(PATH_FILE) {
+        Ok(path) => path,
+        Err(err) => {
+            error!("Failed to load the runner path from {}: {}", PATH_FILE, err);
+            commands.remove_resource::<crate::Winner>();
+            let _ = app_state.push(GameState::MainMenu);
+            return;
+        },
+    };
+    let start = path.points[0];
+
+    let runner = player::spawn_player(&mut commands, player::MAX_PLAYERS, Vec3::new(start.x, start.y, 1.), Role::Runner, ability::Loadout::default());
+
+    commands.entity(runner)
+        .insert(player::SpeedFactor(path.speed))
+        .insert(ScriptedRunner::new(path.points));
+}
+
+pub fn drive_scripted_runner(
+    mut query: Query<(&player::Player, &Transform, &mut ScriptedRunner, &mut player::Intent)>,
+) {
+    for (player, transform, mut runner, mut intent) in query.iter_mut() {
+        let pos = transform.translation.truncate();
+
+        // A path of a single point has nowhere to go
+        let target = match runner.points.get(runner.next) {
+            Some(target) => *target,
+            None => continue,
+        };
+
+        if pos.distance(target) < WAYPOINT_RADIUS {
+            runner.next = (runner.next + 1) % runner.points.len();
+        }
+
+        intent.update(bot::steering_intent(player, pos, runner.points[runner.next]));
+    }
+}
+
+// The first hit ends the attempt, its time goes on the list of personal bests
+pub fn end_time_trial(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    mut events: EventReader<TagEvent>,
+    match_time: Res<game::MatchTime>,
+    query: Query<&player::Player>,
+) {
+    let taggers: Vec<Entity> = events.iter().map(|event| event.tagger).collect();
+
+    if let Some(tagger) = taggers.first().and_then(|tagger| query.get(*tagger).ok()) {
+        let mut bests = PersonalBests::load();
+        let place = bests.record(match_time.0);
+        bests.save();
+
+        let note = match place {
+            Some(0) => " (new best)".to_string(),
+            Some(place) => format!(" (#{})", place + 1),
+            None => "".to_string(),
+        };
+
+        let winner = format!("{} in {:.2}s{}", tagger.name(), match_time.0, note);
+        game::end_match(&mut commands, &mut app_state, winner);
+    }
+}
@@ -0,0 +1,470 @@
+use std::{collections::HashMap, fs, sync::{Arc, Mutex, mpsc}, thread};
+
+use bevy::prelude::*;
+use rand::{Rng, rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{bot, gym, mode, player};
+use crate::ability::{Ability, Role};
+use crate::agent::{Observation, PlayerObservation};
+use crate::mode::MatchRules;
+
+pub const DEFAULT_RULES_FILE: &str = "assets/server_rules.json";
+// A runner dashes once a tagger gets this close
+pub const DANGER_RANGE: f32 = 150.;
+// Frames a wanderer keeps going the same way
+pub const WANDER_FRAMES: u32 = 30;
+// Fleeing runners are pulled back to the middle, so they don't get stuck in a corner
+pub const CENTER_PULL: f32 = 0.5;
+pub const FLEE_DISTANCE: f32 = 200.;
+// A match without a time limit that nobody wins ends as a draw after ten minutes of play
+pub const MAX_MATCH_FRAMES: u32 = 60 * 60 * 10;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum BotPolicy {
+    Idle,
+    Wanderer,
+    Chaser,
+    Tactician,
+}
+
+impl BotPolicy {
+    pub const ALL: [BotPolicy; 4] = [
+        BotPolicy::Idle,
+        BotPolicy::Wanderer,
+        BotPolicy::Chaser,
+        BotPolicy::Tactician,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BotPolicy::Idle => "Idle",
+            BotPolicy::Wanderer => "Wanderer",
+            BotPolicy::Chaser => "Chaser",
+            BotPolicy::Tactician => "Tactician",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|policy| policy.name().eq_ignore_ascii_case(name))
+    }
+}
+
+// A seat of a match, played by a policy with a random generator of its own
+pub struct PolicyPlayer {
+    pub policy: BotPolicy,
+    random: StdRng,
+    wander: player::Intent,
+    frame: u32,
+    last: player::Intent,
+}
+
+impl PolicyPlayer {
+    pub fn new(policy: BotPolicy, seed: u64) -> Self {
+        Self {
+            policy,
+            random: StdRng::seed_from_u64(seed),
+            wander: player::Intent::default(),
+            frame: 0,
+            last: player::Intent::default(),
+        }
+    }
+
+    pub fn act(&mut self, id: usize, observation: &Observation) -> player::Intent {
+        let me = match observation.players.iter().find(|player| player.id == id) {
+            Some(me) => me,
+            None => return player::Intent::default(),
+        };
+
+        let mut intent = match self.policy {
+            BotPolicy::Idle => player::Intent::default(),
+            BotPolicy::Wanderer => self.wander(),
+            BotPolicy::Chaser | BotPolicy::Tactician => match me.role {
+                Role::Tagger => chase(me, observation),
+                Role::Runner => flee(me, observation),
+            },
+        };
+
+        // Only the tactician uses its abilities, the button has to be let go in between
+        if self.policy == BotPolicy::Tactician && !self.last.action {
+            intent.action = match me.role {
+                Role::Tagger => aims_at_runner(me, observation),
+                Role::Runner => closest(me, observation, Role::Tagger).is_some_and(|tagger| distance(me, tagger) < DANGER_RANGE),
+            };
+        }
+
+        self.last = intent;
+        intent
+    }
+
+    fn wander(&mut self) -> player::Intent {
+        if self.frame.is_multiple_of(WANDER_FRAMES) {
+            let turn = self.random.gen_range(0..3);
+
+            self.wander = player::Intent::default();
+            self.wander.up = self.random.gen_bool(0.8);
+            self.wander.left = turn == 1;
+            self.wander.right = turn == 2;
+            self.wander.action = self.random.gen_bool(0.1);
+        }
+
+        self.frame += 1;
+        self.wander
+    }
+}
+
+fn position(player: &PlayerObservation) -> Vec2 {
+    Vec2::new(player.x, player.y)
+}
+
+fn distance(a: &PlayerObservation, b: &PlayerObservation) -> f32 {
+    position(a).distance(position(b))
+}
+
+fn heading(player: &PlayerObservation) -> Vec2 {
+    player::get_direction_vec(player.rotation).truncate()
+}
+
+fn closest<'a>(me: &PlayerObservation, observation: &'a Observation, role: Role) -> Option<&'a PlayerObservation> {
+    observation.players.iter()
+        .filter(|other| other.id != me.id && other.role == role && !other.frozen)
+        .min_by(|a, b| distance(me, a).total_cmp(&distance(me, b)))
+}
+
+fn chase(me: &PlayerObservation, observation: &Observation) -> player::Intent {
+    match closest(me, observation, Role::Runner) {
+        Some(runner) => bot::steer(heading(me), position(me), position(runner)),
+        None => player::Intent::default(),
+    }
+}
+
+fn flee(me: &PlayerObservation, observation: &Observation) -> player::Intent {
+    match closest(me, observation, Role::Tagger) {
+        Some(tagger) => {
+            let pos = position(me);
+            let away = (pos - position(tagger)).normalize_or_zero() * FLEE_DISTANCE;
+            bot::steer(heading(me), pos, pos + away - pos * CENTER_PULL)
+        },
+        None => player::Intent::default(),
+    }
+}
+
+fn aims_at_runner(me: &PlayerObservation, observation: &Observation) -> bool {
+    closest(me, observation, Role::Runner).is_some_and(|runner| {
+        let angle = heading(me).angle_between(position(runner) - position(me));
+        angle.abs() < bot::AIM_ANGLE && distance(me, runner) < bot::SHOT_RANGE
+    })
+}
+
+// The same format as the rules file of the server, anything else in it is left out
+#[derive(Deserialize)]
+pub struct RulesPresets {
+    pub matches: Vec<MatchRules>,
+}
+
+impl RulesPresets {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let presets: Self = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+        presets.check()?;
+
+        Ok(presets)
+    }
+
+    // A preset nobody can play would fail every match it is used for
+    pub fn check(&self) -> Result<(), String> {
+        if let Some(rules) = self.matches.iter().find(|rules| rules.players > player::MAX_PLAYERS || !rules.mode.allows_players(rules.players)) {
+            return Err(format!("{} can't be played by {} players", rules.mode.name(), rules.players));
+        }
+
+        Ok(())
+    }
+}
+
+pub struct TournamentConfig {
+    pub policies: Vec<BotPolicy>,
+    pub presets: Vec<MatchRules>,
+    pub seeds: u64,
+    pub threads: usize,
+}
+
+// A single match, the seats alternate between the two policies
+#[derive(Clone, Copy)]
+pub struct Job {
+    pub preset: usize,
+    pub policies: [BotPolicy; 2],
+    pub seed: u64,
+}
+
+impl Job {
+    pub fn seats(&self, players: usize) -> Vec<BotPolicy> {
+        (0..players).map(|seat| self.policies[seat % 2]).collect()
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct SeatResult {
+    pub won: bool,
+    pub tags: u32,
+    pub dashes: u32,
+    pub tagger_time: f32,
+}
+
+pub struct MatchResult {
+    pub job: Job,
+    pub seats: Vec<SeatResult>,
+    pub duration: f32,
+}
+
+// Both orders of every pair are played, the first seat starts as tagger
+pub fn jobs(config: &TournamentConfig) -> Vec<Job> {
+    let mut jobs = Vec::new();
+
+    for preset in 0..config.presets.len() {
+        for first in config.policies.iter() {
+            for second in config.policies.iter().filter(|second| *second != first) {
+                for seed in 0..config.seeds {
+                    jobs.push(Job { preset, policies: [*first, *second], seed });
+                }
+            }
+        }
+    }
+
+    jobs
+}
+
+pub fn play_match(env: &mut gym::Env, rules: &MatchRules, job: Job) -> Result<MatchResult, String> {
+    let mut observation = env.reset(job.seed, rules)?;
+    let seats = job.seats(observation.players.iter().filter(|player| player.id < player::MAX_PLAYERS).count());
+
+    let mut players: Vec<PolicyPlayer> = seats.iter().enumerate()
+        .map(|(seat, policy)| PolicyPlayer::new(*policy, job.seed * player::MAX_PLAYERS as u64 + seat as u64))
+        .collect();
+    let mut results = vec![SeatResult::default(); seats.len()];
+    let mut finished = false;
+
+    for _ in 0..MAX_MATCH_FRAMES {
+        let actions: Vec<player::Intent> = players.iter_mut().enumerate()
+            .map(|(id, player)| player.act(id, &observation))
+            .collect();
+
+        let (next, _, done) = env.step(&actions)?;
+        let elapsed = next.match_time - observation.match_time;
+
+        for player in next.players.iter().filter(|player| player.role == Role::Tagger) {
+            if let Some(result) = results.get_mut(player.id) {
+                result.tagger_time += elapsed;
+            }
+        }
+
+        for event in env.events() {
+            match *event {
+                gym::StepEvent::Tag { tagger, .. } => results[tagger].tags += 1,
+                gym::StepEvent::Ability { player, ability: Ability::Dash } => results[player].dashes += 1,
+                _ => {},
+            }
+        }
+
+        observation = next;
+
+        if done {
+            finished = true;
+            break;
+        }
+    }
+
+    // Nobody wins a match stopped at the frame limit
+    let winner = if finished {env.winner().unwrap_or_default()} else {String::new()};
+
+    for id in winners(&winner, &observation, results.len()) {
+        results[id].won = true;
+    }
+
+    Ok(MatchResult { job, seats: results, duration: observation.match_time })
+}
+
+// The winner is told as a list of names, teams or roles
+pub fn winners(winner: &str, observation: &Observation, players: usize) -> Vec<usize> {
+    let names: Vec<&str> = winner.split(" & ").collect();
+
+    (0..players)
+        .filter(|id| {
+            let role = observation.players.iter()
+                .find(|player| player.id == *id)
+                .map(|player| player.role);
+
+            names.iter().any(|name| {
+                *name == player::player_name(*id)
+                    || *name == mode::team_name(player::team_of(*id, players))
+                    || (*name == "Taggers" && role == Some(Role::Tagger))
+                    || (*name == "Runners" && role == Some(Role::Runner))
+            })
+        })
+        .collect()
+}
+
+// Every thread runs its own headless game, taking the next match until none are left.
+// A thread that fails stops taking matches, the others play the rest
+pub fn run(config: &TournamentConfig) -> Result<Vec<MatchResult>, String> {
+    let jobs = jobs(config);
+    let total = jobs.len();
+    let jobs = Arc::new(Mutex::new(jobs));
+    let presets = Arc::new(config.presets.clone());
+    let (sender, receiver) = mpsc::channel();
+
+    let workers: Vec<_> = (0..config.threads.max(1))
+        .map(|_| {
+            let jobs = jobs.clone();
+            let presets = presets.clone();
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                let mut env = gym::Env::new();
+
+                loop {
+                    let job = jobs.lock().unwrap().pop();
+
+                    let job = match job {
+                        Some(job) => job,
+                        None => break,
+                    };
+
+                    let result = play_match(&mut env, &presets[job.preset], job);
+                    let failed = result.is_err();
+
+                    if sender.send(result).is_err() || failed {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+
+    drop(sender);
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+
+    for result in receiver.iter() {
+        match result {
+            Ok(result) => results.push(result),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    for worker in workers {
+        if let Err(panic) = worker.join() {
+            let message = panic.downcast_ref::<&str>().map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            errors.push(format!("A thread panicked: {}", message));
+        }
+    }
+
+    if results.len() < total {
+        return Err(format!("{} of {} matches weren't played: {}", total - results.len(), total, errors.join(", ")));
+    }
+
+    Ok(results)
+}
+
+// How a policy did against another one under a preset, from its own seats
+#[derive(Serialize)]
+pub struct MatchupStats {
+    pub mode: String,
+    pub players: usize,
+    pub policy: String,
+    pub opponent: String,
+    pub matches: u32,
+    pub win_rate: f32,
+    pub avg_tags: f32,
+    pub avg_dashes: f32,
+    pub avg_tagger_time: f32,
+    pub avg_duration: f32,
+}
+
+#[derive(Default)]
+struct Totals {
+    matches: u32,
+    seats: u32,
+    wins: u32,
+    tags: u32,
+    dashes: u32,
+    tagger_time: f32,
+    duration: f32,
+}
+
+pub fn matchup_stats(config: &TournamentConfig, results: &[MatchResult]) -> Vec<MatchupStats> {
+    let mut totals: HashMap<(usize, BotPolicy, BotPolicy), Totals> = HashMap::new();
+
+    for result in results {
+        let seats = result.job.seats(result.seats.len());
+
+        for (side, policy) in result.job.policies.iter().enumerate() {
+            let opponent = result.job.policies[1 - side];
+            let totals = totals.entry((result.job.preset, *policy, opponent)).or_default();
+
+            totals.matches += 1;
+            totals.duration += result.duration;
+
+            // A match counts as won if any seat of the policy won it
+            let mut won = false;
+
+            for (seat, _) in seats.iter().enumerate().filter(|(_, seat_policy)| *seat_policy == policy) {
+                let seat = &result.seats[seat];
+
+                won |= seat.won;
+                totals.seats += 1;
+                totals.tags += seat.tags;
+                totals.dashes += seat.dashes;
+                totals.tagger_time += seat.tagger_time;
+            }
+
+            totals.wins += won as u32;
+        }
+    }
+
+    let mut stats = Vec::new();
+
+    for (preset, rules) in config.presets.iter().enumerate() {
+        for policy in config.policies.iter() {
+            for opponent in config.policies.iter().filter(|opponent| *opponent != policy) {
+                let totals = match totals.get(&(preset, *policy, *opponent)) {
+                    Some(totals) => totals,
+                    None => continue,
+                };
+
+                let matches = totals.matches.max(1) as f32;
+                let seats = totals.seats.max(1) as f32;
+
+                stats.push(MatchupStats {
+                    mode: rules.mode.name().to_string(),
+                    players: rules.players,
+                    policy: policy.name().to_string(),
+                    opponent: opponent.name().to_string(),
+                    matches: totals.matches,
+                    win_rate: totals.wins as f32 / matches,
+                    avg_tags: totals.tags as f32 / seats,
+                    avg_dashes: totals.dashes as f32 / seats,
+                    avg_tagger_time: totals.tagger_time / seats,
+                    avg_duration: totals.duration / matches,
+                });
+            }
+        }
+    }
+
+    stats
+}
+
+pub fn to_csv(stats: &[MatchupStats]) -> String {
+    let mut csv = String::from("mode,players,policy,opponent,matches,win_rate,avg_tags,avg_dashes,avg_tagger_time,avg_duration\n");
+
+    for row in stats {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{:.3},{:.3},{:.3},{:.3},{:.3}\n",
+            row.mode, row.players, row.policy, row.opponent, row.matches,
+            row.win_rate, row.avg_tags, row.avg_dashes, row.avg_tagger_time, row.avg_duration,
+        ));
+    }
+
+    csv
+}
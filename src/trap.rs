@@ -0,0 +1,200 @@
+use bevy::{prelude::*, utils::Duration};
+use bevy_prototype_lyon::prelude::*;
+use bevy_tweening::*;
+use heron::prelude::*;
+
+use crate::{ability, bullet, game, player};
+
+pub const MAX_TRAPS: usize = 2;
+pub const TRAP_OFFSET: f32 = 50.;
+pub const SLOW_FIELD_RADIUS: f32 = 60.;
+pub const SLOW_FIELD_DUR: f32 = 6.;
+pub const SLOW_FACTOR: f32 = 0.5;
+pub const BARRIER_WIDTH: f32 = 110.;
+pub const BARRIER_HEIGHT: f32 = 12.;
+pub const BARRIER_DUR: f32 = 3.;
+
+// Traps belong to the runner who placed them, like bullets belong to their shooter
+#[derive(Component)]
+pub struct Trap {
+    pub owner: Entity,
+    timer: Timer,
+}
+
+#[derive(Component)]
+pub struct SlowField {
+    inside: Vec<Entity>,
+}
+
+#[derive(Component)]
+pub struct Barrier;
+
+#[derive(Component)]
+pub struct Slowed;
+
+fn grow_tween() -> Tween<Transform> {
+    Tween::new(
+        EaseFunction::QuadraticOut,
+        TweeningType::Once,
+        Duration::from_millis(200),
+        lens::TransformScaleLens {
+            start: Vec3::new(0., 0., 1.),
+            end: Vec3::new(1., 1., 1.),
+        },
+    )
+}
+
+pub fn spawn_slow_field(
+    commands: &mut Commands,
+    owner: Entity,
+    player: &player::Player,
+    transform: &Transform,
+) {
+    let pos = transform.translation - player.heading() * TRAP_OFFSET;
+    let mut fill_color = game::RUNNER_COL;
+    fill_color.set_a(0.25);
+
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::Circle {
+            radius: SLOW_FIELD_RADIUS,
+            center: Vec2::new(0., 0.),
+        },
+        DrawMode::Outlined {
+            outline_mode: StrokeMode::new(game::RUNNER_COL, 2.),
+            fill_mode: FillMode::color(fill_color),
+        },
+        Transform {
+            translation: Vec3::new(pos.x, pos.y, 0.),
+            scale: Vec3::new(0., 0., 1.),
+            ..Default::default()
+        },
+    ))
+    .insert(Animator::new(grow_tween()))
+    .insert(RigidBody::Sensor)
+    .insert(CollisionShape::Sphere{radius: SLOW_FIELD_RADIUS})
+    .insert(CollisionLayers::new(game::Layer::Trap, game::Layer::Tagger))
+    .insert(Trap {
+        owner,
+        timer: Timer::from_seconds(SLOW_FIELD_DUR, false),
+    })
+    .insert(SlowField { inside: Vec::new() })
+    .insert(game::GameEntity);
+}
+
+pub fn spawn_barrier(
+    commands: &mut Commands,
+    owner: Entity,
+    player: &player::Player,
+    transform: &Transform,
+) {
+    let pos = transform.translation - player.heading() * TRAP_OFFSET;
+
+    // The barrier is rotated with the runner, so it lies across the path of a chasing bullet
+    commands.spawn_bundle(GeometryBuilder::build_as(
+        &shapes::Rectangle {
+            extents: Vec2::new(BARRIER_WIDTH, BARRIER_HEIGHT),
+            origin: shapes::RectangleOrigin::Center,
+        },
+        DrawMode::Outlined {
+            outline_mode: StrokeMode::new(game::RUNNER_COL, 2.),
+            fill_mode: FillMode::color(Color::rgb(23./255., 23./255., 23./255.)),
+        },
+        Transform {
+            translation: Vec3::new(pos.x, pos.y, 0.),
+            rotation: transform.rotation,
+            scale: Vec3::new(0., 0., 1.),
+        },
+    ))
+    .insert(Animator::new(grow_tween()))
+    .insert(RigidBody::Static)
+    .insert(CollisionShape::Cuboid {
+        half_extends: Vec3::new(BARRIER_WIDTH / 2., BARRIER_HEIGHT / 2., 0.),
+        border_radius: None,
+    })
+    .insert(CollisionLayers::new(game::Layer::Trap, game::Layer::Runner))
+    .insert(Trap {
+        owner,
+        timer: Timer::from_seconds(BARRIER_DUR, false),
+    })
+    .insert(Barrier)
+    .insert(game::GameEntity);
+}
+
+pub fn handle_trap_collision(
+    commands: &mut Commands,
+    slow_field: Option<Mut<SlowField>>,
+    barrier: Option<&Barrier>,
+    other_entity: Entity,
+    started: bool,
+    get_bullet: &Query<&bullet::Bullet, With<bullet::Bullet>>,
+) {
+    if let Some(mut slow_field) = slow_field {
+        if started {
+            slow_field.inside.push(other_entity);
+        } else {
+            slow_field.inside.retain(|entity| *entity != other_entity);
+        }
+    }
+    else if barrier.is_some() && started && get_bullet.get(other_entity).is_ok() {
+        commands.entity(other_entity).despawn();
+    }
+}
+
+pub fn handle_trap_timer(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Trap)>,
+) {
+    for (entity, mut trap) in query.iter_mut() {
+        trap.timer.tick(time.delta());
+
+        if trap.timer.just_finished() {
+            let tween = Tween::new(
+                EaseFunction::QuadraticIn,
+                TweeningType::Once,
+                Duration::from_millis(300),
+                lens::TransformScaleLens {
+                    start: Vec3::new(1., 1., 1.),
+                    end: Vec3::new(0., 0., 1.),
+                },
+            )
+            .with_completed_event(true, 0);
+
+            // The trap stops working right away, the despawning is left to the animation
+            commands.entity(entity)
+                .remove::<Trap>()
+                .remove::<SlowField>()
+                .remove::<Barrier>()
+                .remove::<CollisionShape>()
+                .remove::<RigidBody>()
+                .insert(Animator::new(tween))
+                .insert(ability::Effect);
+        }
+    }
+}
+
+// Slow fields only slow down taggers while the runner who placed them is still running
+pub fn apply_slow_fields(
+    mut commands: Commands,
+    field_query: Query<(&Trap, &SlowField)>,
+    owner_query: Query<(), With<player::Runner>>,
+    tagger_query: Query<(Entity, Option<&Slowed>), With<player::Tagger>>,
+    runner_query: Query<Entity, (With<Slowed>, Without<player::Tagger>)>,
+) {
+    for (tagger_entity, is_slowed) in tagger_query.iter() {
+        let in_field = field_query.iter().any(|(trap, field)| {
+            owner_query.get(trap.owner).is_ok() && field.inside.contains(&tagger_entity)
+        });
+
+        if in_field && is_slowed.is_none() {
+            commands.entity(tagger_entity).insert(Slowed);
+        }
+        else if !in_field && is_slowed.is_some() {
+            commands.entity(tagger_entity).remove::<Slowed>();
+        }
+    }
+
+    for runner_entity in runner_query.iter() {
+        commands.entity(runner_entity).remove::<Slowed>();
+    }
+}
@@ -0,0 +1,269 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_kira_audio::Audio;
+use serde::Deserialize;
+
+use crate::{GameState, game, player, ability, practice};
+use crate::ability::{Ability, Role};
+use crate::player::{ActionEvent, ActionKind};
+use crate::mode::{self, GameMode, TagEvent, OrbEvent};
+
+pub const TUTORIAL_FILE: &str = "assets/tutorial.json";
+pub const STEP_PAUSE: f32 = 1.;
+
+// What the player has to do to finish a step
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    Forward,
+    Backward,
+    Turn,
+    Ability(Ability),
+    Dodge,
+    Orb,
+    Tag,
+}
+
+impl Goal {
+    fn matches(&self, kind: ActionKind) -> bool {
+        match (self, kind) {
+            (Goal::Forward, ActionKind::Forward) => true,
+            (Goal::Backward, ActionKind::Backward) => true,
+            (Goal::Turn, ActionKind::TurnLeft | ActionKind::TurnRight) => true,
+            (Goal::Ability(goal), ActionKind::Ability(ability)) => *goal == ability,
+            (Goal::Dodge, ActionKind::Dodge) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DummySpawn {
+    pub role: Role,
+    pub x: f32,
+    pub y: f32,
+}
+
+// A goal held for some seconds has to be done that long, e.g. moving forward
+#[derive(Deserialize)]
+pub struct Step {
+    pub prompt: String,
+    pub goal: Goal,
+    #[serde(default)]
+    pub hold: f32,
+    #[serde(default)]
+    pub role: Option<Role>,
+    #[serde(default)]
+    pub dummies: Vec<DummySpawn>,
+}
+
+#[derive(Deserialize)]
+pub struct TutorialFile {
+    pub loadout: ability::Loadout,
+    pub steps: Vec<Step>,
+}
+
+impl TutorialFile {
+    // The tutorial always has a current step, so it needs at least one
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let file: Self = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+
+        if file.steps.is_empty() {
+            return Err("there are no steps in it".to_string());
+        }
+
+        Ok(file)
+    }
+}
+
+pub struct Tutorial {
+    steps: Vec<Step>,
+    current: usize,
+    player: Entity,
+    held: f32,
+    pause: Option<Timer>,
+    dummies: Vec<Entity>,
+}
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(mode::in_mode(GameMode::Tutorial))
+                .with_system(setup_tutorial)
+                .with_system(practice::fire_dummy_taggers.after("move"))
+                .with_system(check_step.after("collisions"))
+                .with_system(advance_step.after("collisions"))
+                .with_system(update_tutorial_status.after("collisions"))
+                .with_system(practice::leave_practice),
+        );
+    }
+}
+
+// Loads the steps once the player is spawned and starts with the first one, without steps there is nothing to teach
+pub fn setup_tutorial(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    font: Res<crate::FontHandle>,
+    query: Query<Entity, (Added<player::Player>, Without<practice::Dummy>)>,
+) {
+    let player_entity = match query.iter().next() {
+        Some(entity) => entity,
+        None => return,
+    };
+
+    let file = match TutorialFile::load(TUTORIAL_FILE) {
+        Ok(file) => file,
+        Err(err) => {
+            error!("Failed to load the tutorial from {}: {}", TUTORIAL_FILE, err);
+            commands.remove_resource::<crate::Winner>();
+            let _ = app_state.push(GameState::MainMenu);
+            return;
+        },
+    };
+    commands.entity(player_entity).insert(file.loadout);
+
+    let mut tutorial = Tutorial {
+        steps: file.steps,
+        current: 0,
+        player: player_entity,
+        held: 0.,
+        pause: None,
+        dummies: Vec::new(),
+    };
+    start_step(&mut commands, &mut tutorial);
+    commands.insert_resource(tutorial);
+
+    game::spawn_prompt(&mut commands, font.0.clone(), "");
+}
+
+// Puts the player in the role of the step and sets up its dummies
+fn start_step(commands: &mut Commands, tutorial: &mut Tutorial) {
+    for entity in tutorial.dummies.drain(..) {
+        commands.entity(entity).despawn();
+    }
+
+    tutorial.held = 0.;
+
+    let step = &tutorial.steps[tutorial.current];
+
+    match step.role {
+        Some(Role::Tagger) => player::become_tagger(commands, tutorial.player),
+        Some(Role::Runner) => player::become_runner(commands, tutorial.player),
+        None => {},
+    }
+
+    for dummy in step.dummies.iter() {
+        let entity = practice::spawn_dummy(commands, Vec2::new(dummy.x, dummy.y), dummy.role);
+        tutorial.dummies.push(entity);
+    }
+}
+
+pub fn check_step(
+    time: Res<Time>,
+    audio: Res<Audio>,
+    orb_sound: Res<crate::OrbSound>,
+    tutorial: Option<ResMut<Tutorial>>,
+    mut action_events: EventReader<ActionEvent>,
+    mut orb_events: EventReader<OrbEvent>,
+    mut tag_events: EventReader<TagEvent>,
+) {
+    let actions: Vec<(Entity, ActionKind)> = action_events.iter().map(|event| (event.player, event.kind)).collect();
+    let orbs: Vec<Entity> = orb_events.iter().map(|event| event.player).collect();
+    let taggers: Vec<Entity> = tag_events.iter().map(|event| event.tagger).collect();
+
+    let mut tutorial = match tutorial {
+        Some(tutorial) => tutorial,
+        None => return,
+    };
+
+    if tutorial.pause.is_some() {
+        return;
+    }
+
+    let player = tutorial.player;
+    let step = &tutorial.steps[tutorial.current];
+
+    let done_now = match step.goal {
+        Goal::Orb => orbs.contains(&player),
+        Goal::Tag => taggers.contains(&player),
+        goal => actions.iter().any(|(entity, kind)| *entity == player && goal.matches(*kind)),
+    };
+
+    if !done_now {
+        return;
+    }
+
+    let hold = step.hold;
+    tutorial.held += time.delta_seconds();
+
+    // Goals without a hold time are done right away
+    if tutorial.held >= hold {
+        audio.play(orb_sound.0.clone());
+        tutorial.pause = Some(Timer::from_seconds(STEP_PAUSE, false));
+    }
+}
+
+// After a short pause the next step starts, once all are done the tutorial is left
+pub fn advance_step(
+    mut commands: Commands,
+    mut app_state: ResMut<State<GameState>>,
+    time: Res<Time>,
+    tutorial: Option<ResMut<Tutorial>>,
+) {
+    let mut tutorial = match tutorial {
+        Some(tutorial) => tutorial,
+        None => return,
+    };
+
+    let finished = match tutorial.pause.as_mut() {
+        Some(timer) => timer.tick(time.delta()).just_finished(),
+        None => false,
+    };
+
+    if !finished {
+        return;
+    }
+
+    tutorial.pause = None;
+    tutorial.current += 1;
+
+    if tutorial.current < tutorial.steps.len() {
+        start_step(&mut commands, &mut tutorial);
+    } else {
+        // A change of state already on its way leaves the tutorial as well
+        commands.remove_resource::<Tutorial>();
+        commands.remove_resource::<crate::Winner>();
+        let _ = app_state.push(GameState::MainMenu);
+    }
+}
+
+pub fn update_tutorial_status(
+    tutorial: Option<Res<Tutorial>>,
+    mut prompt_query: Query<&mut Text, (With<game::Prompt>, Without<game::ModeStatus>)>,
+    mut status_query: Query<&mut Text, (With<game::ModeStatus>, Without<game::Prompt>)>,
+) {
+    let tutorial = match tutorial {
+        Some(tutorial) => tutorial,
+        None => return,
+    };
+
+    let step = tutorial.current.min(tutorial.steps.len() - 1);
+    let prompt = if tutorial.pause.is_some() {"Well done!".to_string()} else {tutorial.steps[step].prompt.clone()};
+    let status = format!("Step {}/{}", step + 1, tutorial.steps.len());
+
+    for mut text in prompt_query.iter_mut() {
+        if text.sections[0].value != prompt {
+            text.sections[0].value = prompt.clone();
+        }
+    }
+
+    for mut text in status_query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}